@@ -1,13 +1,170 @@
 use crate::AppState;
-use crate::services::AssetService;
+use crate::db::models::{
+  Asset, AssetCleanupAction, AssetCleanupReport, AssetSettings, Attachment, UnusedAsset,
+};
+use crate::services::assets::ASSETS_DIR;
+use crate::services::{AssetService, NoteService};
+use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::State;
+use tauri::ipc::{InvokeBody, Request};
 
+/// attach_file のファイル名を渡すヘッダー (ヘッダーは ASCII のみなのでパーセントエンコードする)
+const FILE_NAME_HEADER: &str = "x-file-name";
+
+// encodeURIComponent でエンコードされたファイル名を戻す
+fn decode_file_name(encoded: &str) -> String {
+  let bytes = encoded.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let hex = (bytes[i] == b'%')
+      .then(|| encoded.get(i + 1..i + 3))
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    match hex {
+      Some(byte) => {
+        decoded.push(byte);
+        i += 3;
+      }
+      None => {
+        decoded.push(bytes[i]);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// 画像を保存してノートから参照するリンク (assets/<hash>.png) を返す
 #[tauri::command]
-pub fn save_image(
-  state: State<AppState>,
+pub async fn save_image(
+  state: State<'_, AppState>,
   image_data: Vec<u8>,
   extension: String,
 ) -> Result<String, String> {
   let context = state.get_context()?;
-  AssetService::save_image(&image_data, &extension, &context.config)
+  let assets_dir = PathBuf::from(&context.config.data_dir).join(ASSETS_DIR);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    AssetService::new(db, assets_dir).save_image(&image_data, &extension)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+/// 任意のファイルを添付してノートに挿入する Markdown のリンクを返す
+///
+/// ファイルの内容は JSON の数値配列ではなく、生のバイト列としてリクエストの本文で受け取る。
+#[tauri::command]
+pub async fn attach_file(
+  state: State<'_, AppState>,
+  request: Request<'_>,
+) -> Result<Attachment, String> {
+  let InvokeBody::Raw(file_data) = request.body() else {
+    return Err("Attachment content must be sent as raw bytes".to_string());
+  };
+  let file_data = file_data.clone();
+  let file_name = request
+    .headers()
+    .get(FILE_NAME_HEADER)
+    .and_then(|value| value.to_str().ok())
+    .map(decode_file_name)
+    .unwrap_or_default();
+  let context = state.get_context()?;
+  let assets_dir = PathBuf::from(&context.config.data_dir).join(ASSETS_DIR);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    AssetService::new(db, assets_dir).attach_file(&file_data, &file_name)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+/// ノートが参照している添付ファイル (画像を含む)
+#[tauri::command]
+pub async fn get_note_attachments(
+  state: State<'_, AppState>,
+  note_id: i64,
+) -> Result<Vec<Asset>, String> {
+  let context = state.get_context()?;
+  let data_dir = PathBuf::from(&context.config.data_dir);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let note = NoteService::new(db.clone(), data_dir.join("notes")).get_note_by_id(note_id)?;
+    AssetService::new(db, data_dir.join(ASSETS_DIR)).get_attachments(&note.content)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_asset_settings(state: State<'_, AppState>) -> Result<AssetSettings, String> {
+  let context = state.get_context()?;
+  let assets_dir = PathBuf::from(&context.config.data_dir).join(ASSETS_DIR);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || AssetService::new(db, assets_dir).get_settings())
+    .await
+    .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn update_asset_settings(
+  state: State<'_, AppState>,
+  settings: AssetSettings,
+) -> Result<AssetSettings, String> {
+  let context = state.get_context()?;
+  let assets_dir = PathBuf::from(&context.config.data_dir).join(ASSETS_DIR);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    AssetService::new(db, assets_dir).update_settings(settings)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_asset(state: State<'_, AppState>, file_name: String) -> Result<Asset, String> {
+  let context = state.get_context()?;
+  let assets_dir = PathBuf::from(&context.config.data_dir).join(ASSETS_DIR);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    AssetService::new(db, assets_dir).get_asset(&file_name)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+/// どのノート (ゴミ箱を含む) からも参照されていないアセット
+#[tauri::command]
+pub async fn find_unused_assets(state: State<'_, AppState>) -> Result<Vec<UnusedAsset>, String> {
+  let context = state.get_context()?;
+  let data_dir = PathBuf::from(&context.config.data_dir);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    AssetService::new(db, data_dir.join(ASSETS_DIR)).find_unused_assets(&data_dir.join("notes"))
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+/// 参照されていないアセットをゴミ箱に移すか削除する (dry_run なら一覧だけ返す)
+#[tauri::command]
+pub async fn clean_up_unused_assets(
+  state: State<'_, AppState>,
+  action: AssetCleanupAction,
+  dry_run: bool,
+) -> Result<AssetCleanupReport, String> {
+  let context = state.get_context()?;
+  let data_dir = PathBuf::from(&context.config.data_dir);
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    AssetService::new(db, data_dir.join(ASSETS_DIR)).clean_up_unused_assets(
+      &data_dir.join("notes"),
+      action,
+      dry_run,
+    )
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
 }
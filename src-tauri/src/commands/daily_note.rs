@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+
+use crate::AppState;
+use crate::db::models::*;
+use crate::services::DailyNoteService;
+use tauri::State;
+
+// YYYY-MM-DD を日付にする (None なら今日)
+fn parse_date(date: Option<String>) -> Result<NaiveDate, String> {
+  match date {
+    Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+      .map_err(|_| format!("日付の形式が正しくありません: {}", date)),
+    None => Ok(chrono::Local::now().date_naive()),
+  }
+}
+
+#[tauri::command]
+pub async fn open_or_create_daily_note<R: tauri::Runtime>(
+  date: Option<String>,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<NoteWithContent, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+  let date = parse_date(date)?;
+
+  tauri::async_runtime::spawn_blocking(move || {
+    DailyNoteService::new(db, notes_dir).open_or_create_daily_note(date)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_daily_note_neighbors<R: tauri::Runtime>(
+  date: Option<String>,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<DailyNoteNeighbors, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+  let date = parse_date(date)?;
+
+  tauri::async_runtime::spawn_blocking(move || {
+    DailyNoteService::new(db, notes_dir).get_neighbors(date)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_daily_note_settings<R: tauri::Runtime>(
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<DailyNoteSettings, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || DailyNoteService::new(db, notes_dir).get_settings())
+    .await
+    .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn update_daily_note_settings<R: tauri::Runtime>(
+  settings: DailyNoteSettings,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<DailyNoteSettings, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    DailyNoteService::new(db, notes_dir).update_settings(settings)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
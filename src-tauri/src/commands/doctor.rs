@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use crate::db::models::VaultReport;
+use crate::services::VaultDoctor;
+use tauri::State;
+
+use crate::AppState;
+
+// ボールトの整合性チェック (repair が true なら修復も行う)
+#[tauri::command]
+pub async fn check_vault<R: tauri::Runtime>(
+  repair: bool,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<VaultReport, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let doctor = VaultDoctor::new(db, notes_dir);
+    doctor.check(repair)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
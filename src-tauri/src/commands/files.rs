@@ -2,7 +2,7 @@ use std::sync::Arc;
 use tauri::State;
 
 use crate::AppState;
-use crate::db::models::FileItem;
+use crate::db::models::{FileItem, ReorderFilesInput, SortSettings};
 use crate::services::FileService;
 
 #[tauri::command]
@@ -20,3 +20,53 @@ pub async fn get_all_files<R: tauri::Runtime>(
   .await
   .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
 }
+
+#[tauri::command]
+pub async fn get_default_sort<R: tauri::Runtime>(
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<SortSettings, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let file_service = FileService::new(db);
+    file_service.get_default_sort()
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn update_default_sort<R: tauri::Runtime>(
+  input: SortSettings,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<SortSettings, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let file_service = FileService::new(db);
+    file_service.update_default_sort(input)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn reorder_files<R: tauri::Runtime>(
+  input: ReorderFilesInput,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<(), String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let file_service = FileService::new(db);
+    file_service.reorder_files(input.parent_id, &input.folder_ids, &input.note_ids)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
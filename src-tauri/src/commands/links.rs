@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use crate::db::models::{Note, NoteLink};
+use crate::services::LinkService;
+use tauri::State;
+
+use crate::AppState;
+
+#[tauri::command]
+pub async fn get_backlinks<R: tauri::Runtime>(
+  note_id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<Note>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let link_service = LinkService::new(db, notes_dir);
+    link_service.get_backlinks(note_id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_outgoing_links<R: tauri::Runtime>(
+  note_id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<NoteLink>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let link_service = LinkService::new(db, notes_dir);
+    link_service.get_outgoing_links(note_id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
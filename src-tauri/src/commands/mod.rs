@@ -2,10 +2,15 @@ pub mod activity;
 pub mod app;
 pub mod assets;
 pub mod backup;
+pub mod daily_note;
+pub mod doctor;
 pub mod files;
 pub mod folder;
 pub mod hotkeys;
+pub mod links;
 pub mod note;
 pub mod notification;
+pub mod revision;
+pub mod saved_search;
 pub mod tags;
 pub mod template;
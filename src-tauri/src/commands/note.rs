@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::db::models::*;
-use crate::services::NoteService;
+use crate::services::{NoteService, SearchService};
 use tauri::State;
 
 use crate::AppState;
@@ -82,6 +82,24 @@ pub async fn update_note<R: tauri::Runtime>(
   .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
 }
 
+#[tauri::command]
+pub async fn update_note_properties<R: tauri::Runtime>(
+  input: UpdateNotePropertiesInput,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<NoteWithContent, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let note_service = NoteService::new(db, notes_dir);
+    note_service.update_note_properties(input.id, input.properties)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
 #[tauri::command]
 pub async fn delete_note<R: tauri::Runtime>(
   id: i64,
@@ -136,6 +154,24 @@ pub async fn search_notes<R: tauri::Runtime>(
   .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
 }
 
+#[tauri::command]
+pub async fn search_note_hits<R: tauri::Runtime>(
+  query: String,
+  sort: Option<SearchSort>,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<SearchHit>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let search_service = SearchService::new(db);
+    search_service.search(&query, sort.unwrap_or_default())
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
 #[tauri::command]
 pub async fn restore_note<R: tauri::Runtime>(
   id: i64,
@@ -298,3 +334,21 @@ pub async fn update_favorite_order<R: tauri::Runtime>(
   .await
   .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
 }
+
+#[tauri::command]
+pub async fn reorder_favorites<R: tauri::Runtime>(
+  ids: Vec<i64>,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<(), String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let note_service = NoteService::new(db, notes_dir);
+    note_service.reorder_favorites(&ids)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use crate::db::models::{DiffLine, NoteRevision, NoteRevisionWithContent, NoteWithContent};
+use crate::services::RevisionService;
+use tauri::State;
+
+use crate::AppState;
+
+#[tauri::command]
+pub async fn get_note_revisions<R: tauri::Runtime>(
+  note_id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<NoteRevision>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let revision_service = RevisionService::new(db, notes_dir);
+    revision_service.get_revisions(note_id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_note_revision<R: tauri::Runtime>(
+  id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<NoteRevisionWithContent, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let revision_service = RevisionService::new(db, notes_dir);
+    revision_service.get_revision(id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn diff_note_revisions<R: tauri::Runtime>(
+  from_id: i64,
+  to_id: Option<i64>,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<DiffLine>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let revision_service = RevisionService::new(db, notes_dir);
+    revision_service.diff_revisions(from_id, to_id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn restore_note_revision<R: tauri::Runtime>(
+  id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<NoteWithContent, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let revision_service = RevisionService::new(db, notes_dir);
+    revision_service.restore_revision(id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use crate::AppState;
+use crate::db::models::*;
+use crate::services::SavedSearchService;
+use tauri::State;
+
+#[tauri::command]
+pub async fn create_saved_search<R: tauri::Runtime>(
+  input: CreateSavedSearchInput,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<SavedSearch, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let service = SavedSearchService::new(db);
+    service.create_saved_search(input.name, input.query, input.icon, input.sort)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_saved_searches<R: tauri::Runtime>(
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<SavedSearch>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let service = SavedSearchService::new(db);
+    service.get_saved_searches()
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn update_saved_search<R: tauri::Runtime>(
+  input: UpdateSavedSearchInput,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<SavedSearch, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let service = SavedSearchService::new(db);
+    service.update_saved_search(input.id, input.name, input.query, input.icon, input.sort)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn delete_saved_search<R: tauri::Runtime>(
+  id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<(), String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let service = SavedSearchService::new(db);
+    service.delete_saved_search(id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
+
+#[tauri::command]
+pub async fn run_saved_search<R: tauri::Runtime>(
+  id: i64,
+  state: State<'_, AppState>,
+  _app: tauri::AppHandle<R>,
+) -> Result<Vec<SearchHit>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+
+  tauri::async_runtime::spawn_blocking(move || {
+    let service = SavedSearchService::new(db);
+    service.run_saved_search(id)
+  })
+  .await
+  .map_err(|e| format!("バックグラウンド処理エラー: {}", e))?
+}
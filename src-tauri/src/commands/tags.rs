@@ -22,7 +22,31 @@ pub async fn update_tag(input: UpdateTagInput, state: State<'_, AppState>) -> Re
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
     let tag_service = TagService::new(db);
-    tag_service.update_tag(input.id, input.name, input.color)
+    tag_service.update_tag(input.id, input.name, input.color, input.rewrite_content)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn move_tag(input: MoveTagInput, state: State<'_, AppState>) -> Result<Tag, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let tag_service = TagService::new(db);
+    tag_service.move_tag(input.id, input.new_parent_id)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn merge_tags(input: MergeTagsInput, state: State<'_, AppState>) -> Result<Tag, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let tag_service = TagService::new(db);
+    tag_service.merge_tags(&input.source_ids, input.target_id)
   })
   .await
   .map_err(|e| format!("Background task error: {}", e))?
@@ -41,7 +65,7 @@ pub async fn delete_tag(id: i64, state: State<'_, AppState>) -> Result<(), Strin
 }
 
 #[tauri::command]
-pub async fn get_all_tags(state: State<'_, AppState>) -> Result<Vec<Tag>, String> {
+pub async fn get_all_tags(state: State<'_, AppState>) -> Result<Vec<TagWithStats>, String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
@@ -103,13 +127,14 @@ pub async fn remove_tag_from_note(
 #[tauri::command]
 pub async fn get_notes_by_tag(
   tag_id: i64,
+  include_descendants: Option<bool>,
   state: State<'_, AppState>,
 ) -> Result<Vec<Note>, String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
     let tag_service = TagService::new(db);
-    tag_service.get_notes_by_tag(tag_id)
+    tag_service.get_notes_by_tag(tag_id, include_descendants.unwrap_or(false))
   })
   .await
   .map_err(|e| format!("Background task error: {}", e))?
@@ -119,7 +144,7 @@ pub async fn get_notes_by_tag(
 pub async fn get_tags_by_note(
   note_id: i64,
   state: State<'_, AppState>,
-) -> Result<Vec<Tag>, String> {
+) -> Result<Vec<NoteTag>, String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
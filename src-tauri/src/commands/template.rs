@@ -1,15 +1,22 @@
 use crate::AppState;
-use crate::db::models::{CreateTemplateInput, Template, UpdateTemplateInput};
-use crate::services::TemplateService;
+use crate::db::models::{
+  CreateNoteFromTemplateInput, CreateTemplateInput, NoteFromTemplate, RenderedTemplate, Template,
+  TemplateContext, UpdateTemplateInput,
+};
+use crate::services::{NoteService, TemplateService};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::State;
 
 #[tauri::command]
 pub async fn get_all_templates(state: State<'_, AppState>) -> Result<Vec<Template>, String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
+  let templates_dir = PathBuf::from(&context.config.data_dir).join("templates");
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
-    let template_service = TemplateService::new(db);
+    let template_service = TemplateService::with_templates_dir(db, templates_dir);
+    // 外部のエディタで編集されたテンプレートを反映してから返す
+    template_service.sync_templates_dir()?;
     template_service.get_all_templates()
   })
   .await
@@ -34,9 +41,10 @@ pub async fn create_template(
   state: State<'_, AppState>,
 ) -> Result<Template, String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
+  let templates_dir = PathBuf::from(&context.config.data_dir).join("templates");
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
-    let template_service = TemplateService::new(db);
+    let template_service = TemplateService::with_templates_dir(db, templates_dir);
     template_service.create_template(input)
   })
   .await
@@ -49,9 +57,10 @@ pub async fn update_template(
   state: State<'_, AppState>,
 ) -> Result<Template, String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
+  let templates_dir = PathBuf::from(&context.config.data_dir).join("templates");
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
-    let template_service = TemplateService::new(db);
+    let template_service = TemplateService::with_templates_dir(db, templates_dir);
     template_service.update_template(input)
   })
   .await
@@ -61,11 +70,96 @@ pub async fn update_template(
 #[tauri::command]
 pub async fn delete_template(id: i64, state: State<'_, AppState>) -> Result<(), String> {
   let context = state.get_context().map_err(|e| e.to_string())?;
+  let templates_dir = PathBuf::from(&context.config.data_dir).join("templates");
   let db = Arc::clone(&context.db);
   tauri::async_runtime::spawn_blocking(move || {
-    let template_service = TemplateService::new(db);
+    let template_service = TemplateService::with_templates_dir(db, templates_dir);
     template_service.delete_template(id)
   })
   .await
   .map_err(|e| format!("Background task error: {}", e))?
 }
+
+#[tauri::command]
+pub async fn render_template(
+  id: i64,
+  context: TemplateContext,
+  state: State<'_, AppState>,
+) -> Result<RenderedTemplate, String> {
+  let context_state = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context_state.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let template_service = TemplateService::new(db);
+    template_service.render_template(id, &context)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_template_prompts(
+  id: i64,
+  state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let template_service = TemplateService::new(db);
+    template_service.get_template_prompts(id)
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn create_note_from_template(
+  input: CreateNoteFromTemplateInput,
+  state: State<'_, AppState>,
+) -> Result<NoteFromTemplate, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let notes_dir = std::path::PathBuf::from(&context.config.data_dir).join("notes");
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let note_service = NoteService::new(db, notes_dir);
+    note_service.create_note_from_template(
+      input.template_id,
+      input.title,
+      input.parent_id,
+      input.folder_path,
+      input.prompts,
+    )
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn export_templates(path: String, state: State<'_, AppState>) -> Result<usize, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let templates_dir = PathBuf::from(&context.config.data_dir).join("templates");
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let template_service = TemplateService::with_templates_dir(db, templates_dir);
+    template_service.sync_templates_dir()?;
+    template_service.export_templates(&PathBuf::from(path))
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn import_templates(
+  path: String,
+  state: State<'_, AppState>,
+) -> Result<Vec<Template>, String> {
+  let context = state.get_context().map_err(|e| e.to_string())?;
+  let templates_dir = PathBuf::from(&context.config.data_dir).join("templates");
+  let db = Arc::clone(&context.db);
+  tauri::async_runtime::spawn_blocking(move || {
+    let template_service = TemplateService::with_templates_dir(db, templates_dir);
+    template_service.sync_templates_dir()?;
+    template_service.import_templates(&PathBuf::from(path))
+  })
+  .await
+  .map_err(|e| format!("Background task error: {}", e))?
+}
@@ -1,7 +1,213 @@
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, params};
+use std::fmt;
 use std::fs;
 
-pub fn migrate(conn: &Connection) -> Result<()> {
+/// 番号付きのスキーママイグレーション
+///
+/// 適用済みのバージョンは `PRAGMA user_version` に記録される。
+/// 新しいマイグレーションは必ず末尾に追加し、既存のものは変更しないこと。
+struct Migration {
+  version: i64,
+  description: &'static str,
+  up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    version: 1,
+    description: "baseline schema",
+    up: migrate_v1_baseline,
+  },
+  Migration {
+    version: 2,
+    description: "note revisions",
+    up: migrate_v2_note_revisions,
+  },
+  Migration {
+    version: 3,
+    description: "wiki-link index",
+    up: migrate_v3_note_links,
+  },
+  Migration {
+    version: 4,
+    description: "normalized search index",
+    up: migrate_v4_search_index,
+  },
+  Migration {
+    version: 5,
+    description: "saved searches",
+    up: migrate_v5_saved_searches,
+  },
+  Migration {
+    version: 6,
+    description: "manual order and default sort",
+    up: migrate_v6_sort_settings,
+  },
+  Migration {
+    version: 7,
+    description: "favorites stored on notes",
+    up: migrate_v7_favorites,
+  },
+  Migration {
+    version: 8,
+    description: "hierarchical tags",
+    up: migrate_v8_tag_hierarchy,
+  },
+  Migration {
+    version: 9,
+    description: "inline hashtags",
+    up: migrate_v9_inline_tags,
+  },
+  Migration {
+    version: 10,
+    description: "note properties",
+    up: migrate_v10_note_properties,
+  },
+  Migration {
+    version: 11,
+    description: "daily notes",
+    up: migrate_v11_daily_notes,
+  },
+  Migration {
+    version: 12,
+    description: "folder default template",
+    up: migrate_v12_folder_default_template,
+  },
+  Migration {
+    version: 13,
+    description: "template files",
+    up: migrate_v13_template_files,
+  },
+  Migration {
+    version: 14,
+    description: "assets",
+    up: migrate_v14_assets,
+  },
+  Migration {
+    version: 15,
+    description: "asset trash",
+    up: migrate_v15_asset_trash,
+  },
+  Migration {
+    version: 16,
+    description: "attachments",
+    up: migrate_v16_attachments,
+  },
+  Migration {
+    version: 17,
+    description: "image processing",
+    up: migrate_v17_image_processing,
+  },
+];
+
+/// このバイナリが理解できる最新のスキーマバージョン
+pub const LATEST_SCHEMA_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+#[derive(Debug)]
+pub enum MigrationError {
+  /// データベースがこのバイナリより新しいスキーマで作成されている
+  DatabaseTooNew {
+    found: i64,
+    supported: i64,
+  },
+  /// マイグレーションの適用に失敗した
+  Failed {
+    version: i64,
+    description: &'static str,
+    source: rusqlite::Error,
+  },
+  Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for MigrationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      MigrationError::DatabaseTooNew { found, supported } => write!(
+        f,
+        "データベースのスキーマバージョン ({}) がこのアプリの対応バージョン ({}) より新しいです。アプリを更新してください",
+        found, supported
+      ),
+      MigrationError::Failed {
+        version,
+        description,
+        source,
+      } => write!(
+        f,
+        "マイグレーション {} ({}) の適用に失敗しました: {}",
+        version, description, source
+      ),
+      MigrationError::Sqlite(e) => write!(f, "マイグレーションに失敗しました: {}", e),
+    }
+  }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+  fn from(e: rusqlite::Error) -> Self {
+    MigrationError::Sqlite(e)
+  }
+}
+
+/// 現在のスキーマバージョンを取得
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<i64> {
+  conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// 未適用のマイグレーションを順番に、それぞれ1つのトランザクション内で適用する
+pub fn migrate(conn: &Connection) -> Result<(), MigrationError> {
+  let current = schema_version(conn)?;
+
+  if current > LATEST_SCHEMA_VERSION {
+    return Err(MigrationError::DatabaseTooNew {
+      found: current,
+      supported: LATEST_SCHEMA_VERSION,
+    });
+  }
+
+  for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+    let tx = conn.unchecked_transaction()?;
+    (migration.up)(&tx)
+      .and_then(|_| tx.pragma_update(None, "user_version", migration.version))
+      .map_err(|source| MigrationError::Failed {
+        version: migration.version,
+        description: migration.description,
+        source,
+      })?;
+    tx.commit()?;
+  }
+
+  Ok(())
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+  conn.query_row(
+    "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?) WHERE name = ?)",
+    params![table, column],
+    |row| row.get(0),
+  )
+}
+
+fn add_column_if_missing(
+  conn: &Connection,
+  table: &str,
+  column: &str,
+  definition: &str,
+) -> rusqlite::Result<()> {
+  if !column_exists(conn, table, column)? {
+    conn.execute(
+      &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+      [],
+    )?;
+  }
+  Ok(())
+}
+
+/// バージョン管理導入前のスキーマ
+///
+/// 既存のデータベースは `user_version = 0` のまま各テーブルを持っているため、
+/// テーブルは IF NOT EXISTS で作成し、後から追加されたカラムは存在しない場合のみ追加する。
+fn migrate_v1_baseline(conn: &Connection) -> rusqlite::Result<()> {
   conn.execute(
     "CREATE TABLE IF NOT EXISTS notes (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -16,11 +222,6 @@ pub fn migrate(conn: &Connection) -> Result<()> {
     [],
   )?;
 
-  // Add preview column if it doesn't exist (for existing databases)
-  conn
-    .execute("ALTER TABLE notes ADD COLUMN preview TEXT DEFAULT ''", [])
-    .ok(); // Ignore error if column already exists
-
   conn.execute(
     "CREATE TABLE IF NOT EXISTS folders (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -34,69 +235,18 @@ pub fn migrate(conn: &Connection) -> Result<()> {
     [],
   )?;
 
-  // Add is_deleted and deleted_at columns to notes
-  conn
-    .execute(
-      "ALTER TABLE notes ADD COLUMN is_deleted BOOLEAN DEFAULT FALSE",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "ALTER TABLE notes ADD COLUMN deleted_at DATETIME DEFAULT NULL",
-      [],
-    )
-    .ok();
-
-  // Add is_favorite and favorite_order columns to notes
-  conn
-    .execute(
-      "ALTER TABLE notes ADD COLUMN is_favorite BOOLEAN DEFAULT FALSE",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "ALTER TABLE notes ADD COLUMN favorite_order INTEGER DEFAULT NULL",
-      [],
-    )
-    .ok();
-
-  // Add is_deleted and deleted_at columns to folders
-  conn
-    .execute(
-      "ALTER TABLE folders ADD COLUMN is_deleted BOOLEAN DEFAULT FALSE",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "ALTER TABLE folders ADD COLUMN deleted_at DATETIME DEFAULT NULL",
-      [],
-    )
-    .ok();
+  add_column_if_missing(conn, "notes", "preview", "TEXT DEFAULT ''")?;
+  add_column_if_missing(conn, "notes", "is_deleted", "BOOLEAN DEFAULT FALSE")?;
+  add_column_if_missing(conn, "notes", "deleted_at", "DATETIME DEFAULT NULL")?;
+  add_column_if_missing(conn, "notes", "is_favorite", "BOOLEAN DEFAULT FALSE")?;
+  add_column_if_missing(conn, "notes", "favorite_order", "INTEGER DEFAULT NULL")?;
 
-  // Add icon and color columns to folders for customization
-  conn
-    .execute("ALTER TABLE folders ADD COLUMN icon TEXT DEFAULT NULL", [])
-    .ok();
-  conn
-    .execute("ALTER TABLE folders ADD COLUMN color TEXT DEFAULT NULL", [])
-    .ok();
-
-  // Add sort_by and sort_order columns to folders for per-folder sorting
-  conn
-    .execute(
-      "ALTER TABLE folders ADD COLUMN sort_by TEXT DEFAULT NULL",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "ALTER TABLE folders ADD COLUMN sort_order TEXT DEFAULT NULL",
-      [],
-    )
-    .ok();
+  add_column_if_missing(conn, "folders", "is_deleted", "BOOLEAN DEFAULT FALSE")?;
+  add_column_if_missing(conn, "folders", "deleted_at", "DATETIME DEFAULT NULL")?;
+  add_column_if_missing(conn, "folders", "icon", "TEXT DEFAULT NULL")?;
+  add_column_if_missing(conn, "folders", "color", "TEXT DEFAULT NULL")?;
+  add_column_if_missing(conn, "folders", "sort_by", "TEXT DEFAULT NULL")?;
+  add_column_if_missing(conn, "folders", "sort_order", "TEXT DEFAULT NULL")?;
 
   conn.execute(
     "CREATE TABLE IF NOT EXISTS activity_log (
@@ -109,19 +259,8 @@ pub fn migrate(conn: &Connection) -> Result<()> {
     [],
   )?;
 
-  // Add activity_count and char_count columns to activity_log if they don't exist
-  conn
-    .execute(
-      "ALTER TABLE activity_log ADD COLUMN activity_count INTEGER DEFAULT 0",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "ALTER TABLE activity_log ADD COLUMN char_count INTEGER DEFAULT 0",
-      [],
-    )
-    .ok();
+  add_column_if_missing(conn, "activity_log", "activity_count", "INTEGER DEFAULT 0")?;
+  add_column_if_missing(conn, "activity_log", "char_count", "INTEGER DEFAULT 0")?;
 
   conn.execute(
     "CREATE TABLE IF NOT EXISTS user_goals (
@@ -140,21 +279,14 @@ pub fn migrate(conn: &Connection) -> Result<()> {
   )?;
 
   // Generate previews for existing notes that don't have one
-  let mut stmt = conn.prepare("SELECT id, file_path, preview FROM notes")?;
-  let notes_to_update: Vec<(i64, String)> = stmt
-    .query_map([], |row| {
-      let id: i64 = row.get(0)?;
-      let file_path: String = row.get(1)?;
-      let preview: String = row.get(2)?;
-
-      if preview.is_empty() {
-        Ok(Some((id, file_path)))
-      } else {
-        Ok(None)
-      }
-    })?
-    .filter_map(|r| r.ok().flatten())
-    .collect();
+  let notes_to_update: Vec<(i64, String)> = {
+    let mut stmt = conn.prepare(
+      "SELECT id, file_path FROM notes WHERE (preview IS NULL OR preview = '') AND file_path IS NOT NULL",
+    )?;
+    stmt
+      .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+      .collect::<rusqlite::Result<_>>()?
+  };
 
   for (id, file_path) in notes_to_update {
     if let Ok(content) = fs::read_to_string(&file_path) {
@@ -241,7 +373,7 @@ pub fn migrate(conn: &Connection) -> Result<()> {
 
   // Insert default hotkeys
   conn.execute(
-    "INSERT OR IGNORE INTO hotkeys (action, shortcut) VALUES 
+    "INSERT OR IGNORE INTO hotkeys (action, shortcut) VALUES
     ('quick_note', 'CommandOrControl+Shift+N'),
     ('toggle_window', 'CommandOrControl+Shift+Space')",
     [],
@@ -264,100 +396,436 @@ pub fn migrate(conn: &Connection) -> Result<()> {
 
   // Insert default backup settings if not exists
   conn.execute(
-    "INSERT OR IGNORE INTO backup_settings (id, enabled, frequency, max_backups) 
+    "INSERT OR IGNORE INTO backup_settings (id, enabled, frequency, max_backups)
      VALUES (1, FALSE, 'daily', 10)",
     [],
   )?;
 
   // Create FTS table for full-text search
   // id is unindexed to allow mapping back to notes table
-  conn.execute(
-    "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(id UNINDEXED, title, content, tokenize='trigram')",
-    [],
-  ).or_else(|_| {
-    // Fallback if trigram tokenizer is not available (though it should be in modern sqlite)
-    conn.execute(
-      "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(id UNINDEXED, title, content)",
+  conn
+    .execute(
+      "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(id UNINDEXED, title, content, tokenize='trigram')",
       [],
     )
-  })?;
-
-  // Check if FTS table needs population
-  let fts_count: i64 = conn
-    .query_row("SELECT count(*) FROM notes_fts", [], |row| row.get(0))
-    .unwrap_or(0);
+    .or_else(|_| {
+      // Fallback if trigram tokenizer is not available (though it should be in modern sqlite)
+      conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(id UNINDEXED, title, content)",
+        [],
+      )
+    })?;
 
-  let notes_count: i64 = conn
-    .query_row(
-      "SELECT count(*) FROM notes WHERE is_deleted = FALSE",
-      [],
-      |row| row.get(0),
-    )
-    .unwrap_or(0);
+  // Populate FTS for databases created before the index existed
+  let fts_count: i64 = conn.query_row("SELECT count(*) FROM notes_fts", [], |row| row.get(0))?;
 
-  // If FTS is empty or significantly out of sync, repopulate
-  // Note: This is a simple check. Ideally we'd have a more robust sync mechanism.
-  if fts_count == 0 && notes_count > 0 {
-    let mut stmt =
-      conn.prepare("SELECT id, title, file_path FROM notes WHERE is_deleted = FALSE")?;
-    let notes_iter = stmt.query_map([], |row| {
-      Ok((
-        row.get::<_, i64>(0)?,
-        row.get::<_, String>(1)?,
-        row.get::<_, String>(2)?,
-      ))
-    })?;
+  if fts_count == 0 {
+    let notes: Vec<(i64, String, String)> = {
+      let mut stmt = conn.prepare(
+        "SELECT id, title, file_path FROM notes WHERE is_deleted = FALSE AND file_path IS NOT NULL",
+      )?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?
+    };
 
-    for (id, title, file_path) in notes_iter.flatten() {
+    for (id, title, file_path) in notes {
+      // A missing file is not a schema problem; the note simply stays unindexed
       if let Ok(content) = fs::read_to_string(&file_path) {
-        conn
-          .execute(
-            "INSERT INTO notes_fts (id, title, content) VALUES (?, ?, ?)",
-            params![id, title, content],
-          )
-          .ok(); // Ignore errors for individual notes to allow migration to complete
+        conn.execute(
+          "INSERT INTO notes_fts (id, title, content) VALUES (?, ?, ?)",
+          params![id, title, content],
+        )?;
       }
     }
   }
 
   // Add indexes for performance optimization
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_notes_parent_id ON notes(parent_id)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_notes_is_deleted ON notes(is_deleted)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_notes_is_favorite ON notes(is_favorite)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_folders_parent_id ON folders(parent_id)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_folders_is_deleted ON folders(is_deleted)",
+    [],
+  )?;
+
+  Ok(())
+}
+
+fn migrate_v2_note_revisions(conn: &Connection) -> rusqlite::Result<()> {
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS note_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+    )",
+    [],
+  )?;
+
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_note_revisions_note_id ON note_revisions(note_id, created_at)",
+    [],
+  )?;
+
+  Ok(())
+}
+
+fn migrate_v3_note_links(conn: &Connection) -> rusqlite::Result<()> {
+  // target は [[...]] 内のリンク先文字列、target_note_id は解決できたノートのID
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS note_links (
+    source_note_id INTEGER NOT NULL,
+    target TEXT NOT NULL,
+    target_note_id INTEGER DEFAULT NULL,
+    PRIMARY KEY (source_note_id, target),
+    FOREIGN KEY (source_note_id) REFERENCES notes(id) ON DELETE CASCADE,
+    FOREIGN KEY (target_note_id) REFERENCES notes(id) ON DELETE SET NULL
+    )",
+    [],
+  )?;
+
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_note_links_target_note_id ON note_links(target_note_id)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_note_links_target ON note_links(target)",
+    [],
+  )?;
+
+  // リンクの解決にはノートディレクトリのパスが必要なため、既存ノートの索引は起動時に作成する
+  add_column_if_missing(conn, "notes", "links_indexed", "BOOLEAN DEFAULT FALSE")?;
+
+  Ok(())
+}
+
+fn migrate_v4_search_index(conn: &Connection) -> rusqlite::Result<()> {
+  // notes_fts の rowid をノートの id に揃えて、id から直接引けるようにする
+  conn.execute(
+    "CREATE TEMP TABLE notes_fts_rekey AS SELECT CAST(id AS INTEGER) AS id, title, content FROM notes_fts",
+    [],
+  )?;
+  conn.execute("DELETE FROM notes_fts", [])?;
+  conn.execute(
+    "INSERT INTO notes_fts (rowid, id, title, content)
+     SELECT id, id, title, content FROM notes_fts_rekey GROUP BY id",
+    [],
+  )?;
+  conn.execute("DROP TABLE notes_fts_rekey", [])?;
+
+  // 全角/半角やカタカナ/ひらがなを揃えた文字列の索引 (rowid はノートの id)
   conn
     .execute(
-      "CREATE INDEX IF NOT EXISTS idx_notes_parent_id ON notes(parent_id)",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "CREATE INDEX IF NOT EXISTS idx_notes_is_deleted ON notes(is_deleted)",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "CREATE INDEX IF NOT EXISTS idx_notes_is_favorite ON notes(is_favorite)",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at)",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "CREATE INDEX IF NOT EXISTS idx_folders_parent_id ON folders(parent_id)",
-      [],
-    )
-    .ok();
-  conn
-    .execute(
-      "CREATE INDEX IF NOT EXISTS idx_folders_is_deleted ON folders(is_deleted)",
+      "CREATE VIRTUAL TABLE IF NOT EXISTS notes_search USING fts5(title, content, tokenize='trigram')",
       [],
     )
-    .ok();
+    .or_else(|_| {
+      conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS notes_search USING fts5(title, content)",
+        [],
+      )
+    })?;
 
+  // 表記ゆれを揃える処理はアプリ側にあるため、既存ノートの索引は起動時に作成する
+  Ok(())
+}
+
+fn migrate_v5_saved_searches(conn: &Connection) -> rusqlite::Result<()> {
+  // sort は SearchSort の値 (relevance / updated / created)
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS saved_searches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    query TEXT NOT NULL,
+    icon TEXT DEFAULT NULL,
+    sort TEXT NOT NULL DEFAULT 'relevance',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    )",
+    [],
+  )?;
+
+  Ok(())
+}
+
+fn migrate_v6_sort_settings(conn: &Connection) -> rusqlite::Result<()> {
+  // 手動並び替えでの順番 (NULL は未指定で、指定済みの項目の後ろに作成順で並ぶ)
+  add_column_if_missing(conn, "notes", "position", "INTEGER DEFAULT NULL")?;
+  add_column_if_missing(conn, "folders", "position", "INTEGER DEFAULT NULL")?;
+
+  // ボールト全体の既定の並び順 (sort_by が未指定のフォルダとルートに使う)
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS vault_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    sort_by TEXT NOT NULL DEFAULT 'name',
+    sort_order TEXT NOT NULL DEFAULT 'asc',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    )",
+    [],
+  )?;
+  conn.execute("INSERT OR IGNORE INTO vault_settings (id) VALUES (1)", [])?;
+
+  Ok(())
+}
+
+fn migrate_v7_favorites(conn: &Connection) -> rusqlite::Result<()> {
+  // これまでお気に入りは「お気に入り」タグで管理していたため、
+  // タグを付けた順を favorite_order にして notes に移す
+  conn.execute(
+    "UPDATE notes SET is_favorite = FALSE, favorite_order = NULL",
+    [],
+  )?;
+  conn.execute(
+    "UPDATE notes SET is_favorite = TRUE, favorite_order = ranked.position
+    FROM (
+      SELECT nt.note_id, ROW_NUMBER() OVER (ORDER BY nt.created_at, nt.note_id) - 1 AS position
+      FROM note_tags nt JOIN tags t ON nt.tag_id = t.id
+      WHERE t.name = 'お気に入り'
+    ) AS ranked
+    WHERE notes.id = ranked.note_id",
+    [],
+  )?;
+  conn.execute(
+    "DELETE FROM note_tags WHERE tag_id IN (SELECT id FROM tags WHERE name = 'お気に入り')",
+    [],
+  )?;
+  conn.execute("DELETE FROM tags WHERE name = 'お気に入り'", [])?;
+
+  Ok(())
+}
+
+fn migrate_v8_tag_hierarchy(conn: &Connection) -> rusqlite::Result<()> {
+  // 親のタグ (名前は "親/子" のように / で区切る)
+  add_column_if_missing(conn, "tags", "parent_id", "INTEGER DEFAULT NULL")?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_tags_parent_id ON tags(parent_id)",
+    [],
+  )?;
+
+  // 既に / を含む名前のタグには親のタグを作ってつなぐ (短い名前から順に処理する)
+  let names: Vec<String> = {
+    let mut stmt =
+      conn.prepare("SELECT name FROM tags WHERE name LIKE '%/%' ORDER BY length(name)")?;
+    stmt
+      .query_map([], |row| row.get(0))?
+      .collect::<rusqlite::Result<_>>()?
+  };
+  for name in names {
+    let Some((parent, _)) = name.rsplit_once('/') else {
+      continue;
+    };
+    let mut parent_id: Option<i64> = None;
+    let mut path = String::new();
+    for segment in parent.split('/') {
+      if !path.is_empty() {
+        path.push('/');
+      }
+      path.push_str(segment);
+      conn.execute(
+        "INSERT OR IGNORE INTO tags (name, parent_id) VALUES (?, ?)",
+        params![path, parent_id],
+      )?;
+      parent_id = Some(conn.query_row(
+        "SELECT id FROM tags WHERE name = ?",
+        params![path],
+        |row| row.get(0),
+      )?);
+    }
+    conn.execute(
+      "UPDATE tags SET parent_id = ? WHERE name = ?",
+      params![parent_id, name],
+    )?;
+  }
+
+  Ok(())
+}
+
+fn migrate_v9_inline_tags(conn: &Connection) -> rusqlite::Result<()> {
+  // 手動で付けたタグと本文の #タグ から付いたタグを区別する (既存の関連付けはすべて手動)
+  add_column_if_missing(
+    conn,
+    "note_tags",
+    "is_manual",
+    "BOOLEAN NOT NULL DEFAULT TRUE",
+  )?;
+  add_column_if_missing(
+    conn,
+    "note_tags",
+    "is_inline",
+    "BOOLEAN NOT NULL DEFAULT FALSE",
+  )?;
+  // 既存のノートは起動時に #タグ を解析する
+  add_column_if_missing(
+    conn,
+    "notes",
+    "tags_indexed",
+    "BOOLEAN NOT NULL DEFAULT FALSE",
+  )?;
+  Ok(())
+}
+
+fn migrate_v10_note_properties(conn: &Connection) -> rusqlite::Result<()> {
+  // front matter のプロパティ (リストは要素ごとに 1 行)
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS note_properties (
+      note_id INTEGER NOT NULL,
+      key TEXT NOT NULL,
+      value TEXT NOT NULL,
+      position INTEGER NOT NULL DEFAULT 0,
+      FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+    )",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_note_properties_note_id ON note_properties(note_id)",
+    [],
+  )?;
+  conn.execute(
+    "CREATE INDEX IF NOT EXISTS idx_note_properties_key_value ON note_properties(key, value COLLATE NOCASE)",
+    [],
+  )?;
+  // 既存のノートは起動時に front matter を読み取る
+  add_column_if_missing(
+    conn,
+    "notes",
+    "properties_indexed",
+    "BOOLEAN NOT NULL DEFAULT FALSE",
+  )?;
+  Ok(())
+}
+
+fn migrate_v11_daily_notes(conn: &Connection) -> rusqlite::Result<()> {
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS daily_note_settings (
+      id INTEGER PRIMARY KEY CHECK (id = 1),
+      folder TEXT NOT NULL DEFAULT 'Daily',
+      pattern TEXT NOT NULL DEFAULT 'YYYY/MM/YYYY-MM-DD',
+      template_id INTEGER DEFAULT NULL,
+      updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+      FOREIGN KEY (template_id) REFERENCES templates(id) ON DELETE SET NULL
+    )",
+    [],
+  )?;
+  conn.execute(
+    "INSERT OR IGNORE INTO daily_note_settings (id) VALUES (1)",
+    [],
+  )?;
+
+  // 日付ごとのデイリーノート (前後の移動に使う)
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS daily_notes (
+      date TEXT PRIMARY KEY,
+      note_id INTEGER NOT NULL,
+      FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+    )",
+    [],
+  )?;
+
+  conn.execute(
+    "INSERT OR IGNORE INTO hotkeys (action, shortcut) VALUES ('daily_note', 'CommandOrControl+Shift+D')",
+    [],
+  )?;
+  Ok(())
+}
+
+fn migrate_v12_folder_default_template(conn: &Connection) -> rusqlite::Result<()> {
+  // テンプレートを削除しても参照は残るため、使う側で存在を確かめる
+  add_column_if_missing(
+    conn,
+    "folders",
+    "default_template_id",
+    "INTEGER DEFAULT NULL",
+  )?;
+  Ok(())
+}
+
+fn migrate_v13_template_files(conn: &Connection) -> rusqlite::Result<()> {
+  // テンプレートを書き出した .md ファイル (未作成のものは起動時に書き出す)
+  add_column_if_missing(conn, "templates", "file_path", "TEXT DEFAULT NULL")?;
+  Ok(())
+}
+
+fn migrate_v14_assets(conn: &Connection) -> rusqlite::Result<()> {
+  // data_dir/assets に内容のハッシュで保存したファイル (同じ内容は一つだけ保存する)
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS assets (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      hash TEXT NOT NULL UNIQUE,
+      file_name TEXT NOT NULL UNIQUE,
+      mime_type TEXT NOT NULL,
+      size INTEGER NOT NULL,
+      width INTEGER,
+      height INTEGER,
+      created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    )",
+    [],
+  )?;
+  Ok(())
+}
+
+fn migrate_v15_asset_trash(conn: &Connection) -> rusqlite::Result<()> {
+  // どのノートからも参照されずゴミ箱 (assets/.trash) に移したアセット
+  add_column_if_missing(
+    conn,
+    "assets",
+    "is_deleted",
+    "BOOLEAN NOT NULL DEFAULT FALSE",
+  )?;
+  add_column_if_missing(conn, "assets", "deleted_at", "DATETIME DEFAULT NULL")?;
+  Ok(())
+}
+
+fn migrate_v16_attachments(conn: &Connection) -> rusqlite::Result<()> {
+  // 添付したときのファイル名 (同じ内容は最初の名前を残す)
+  add_column_if_missing(conn, "assets", "original_name", "TEXT DEFAULT NULL")?;
+
+  conn.execute(
+    "CREATE TABLE IF NOT EXISTS asset_settings (
+      id INTEGER PRIMARY KEY CHECK (id = 1),
+      max_file_size INTEGER NOT NULL DEFAULT 52428800,
+      updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    )",
+    [],
+  )?;
+  conn.execute("INSERT OR IGNORE INTO asset_settings (id) VALUES (1)", [])?;
+  Ok(())
+}
+
+fn migrate_v17_image_processing(conn: &Connection) -> rusqlite::Result<()> {
+  // 貼り付けた画像の縮小と再エンコード (既定では行わない)
+  for (column, definition) in [
+    ("process_images", "BOOLEAN NOT NULL DEFAULT FALSE"),
+    ("max_image_dimension", "INTEGER NOT NULL DEFAULT 2560"),
+    ("reencode_threshold", "INTEGER NOT NULL DEFAULT 1048576"),
+    ("reencode_format", "TEXT NOT NULL DEFAULT 'webp'"),
+    ("jpeg_quality", "INTEGER NOT NULL DEFAULT 85"),
+    // 位置情報などを含む EXIF は既定で取り除く
+    ("strip_exif", "BOOLEAN NOT NULL DEFAULT TRUE"),
+    ("thumbnail_size", "INTEGER NOT NULL DEFAULT 256"),
+  ] {
+    add_column_if_missing(conn, "asset_settings", column, definition)?;
+  }
   Ok(())
 }
 
@@ -389,3 +857,99 @@ fn generate_preview(content: &str) -> String {
     plain_text.to_string()
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_migrate_records_schema_version() {
+    let conn = Connection::open_in_memory().unwrap();
+    migrate(&conn).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), LATEST_SCHEMA_VERSION);
+
+    // 2回目は何も適用されない
+    migrate(&conn).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), LATEST_SCHEMA_VERSION);
+  }
+
+  #[test]
+  fn test_migrate_upgrades_legacy_database() {
+    let conn = Connection::open_in_memory().unwrap();
+    // バージョン管理導入前の古いスキーマ (後から追加されたカラムがない)
+    conn
+      .execute(
+        "CREATE TABLE notes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        title TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        parent_id INTEGER DEFAULT NULL,
+        file_path TEXT DEFAULT NULL
+        )",
+        [],
+      )
+      .unwrap();
+
+    migrate(&conn).unwrap();
+
+    assert!(column_exists(&conn, "notes", "preview").unwrap());
+    assert!(column_exists(&conn, "notes", "is_deleted").unwrap());
+    assert!(column_exists(&conn, "notes", "favorite_order").unwrap());
+    assert_eq!(schema_version(&conn).unwrap(), LATEST_SCHEMA_VERSION);
+  }
+
+  #[test]
+  fn test_migrate_v7_moves_favorite_tag() {
+    let conn = Connection::open_in_memory().unwrap();
+    for migration in MIGRATIONS.iter().filter(|m| m.version < 7) {
+      (migration.up)(&conn).unwrap();
+    }
+    conn
+      .execute_batch(
+        "INSERT INTO notes (id, title) VALUES (1, 'a'), (2, 'b'), (3, 'c');
+        INSERT INTO note_tags (note_id, tag_id, created_at)
+          SELECT 2, id, '2024-01-01 00:00:00' FROM tags WHERE name = 'お気に入り';
+        INSERT INTO note_tags (note_id, tag_id, created_at)
+          SELECT 3, id, '2023-01-01 00:00:00' FROM tags WHERE name = 'お気に入り';",
+      )
+      .unwrap();
+
+    migrate_v7_favorites(&conn).unwrap();
+
+    let favorites: Vec<(i64, bool, Option<i64>)> = conn
+      .prepare("SELECT id, is_favorite, favorite_order FROM notes ORDER BY id")
+      .unwrap()
+      .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+      .unwrap()
+      .collect::<rusqlite::Result<_>>()
+      .unwrap();
+    assert_eq!(
+      favorites,
+      vec![(1, false, None), (2, true, Some(1)), (3, true, Some(0))]
+    );
+    let tag_count: i64 = conn
+      .query_row(
+        "SELECT COUNT(*) FROM tags WHERE name = 'お気に入り'",
+        [],
+        |row| row.get(0),
+      )
+      .unwrap();
+    assert_eq!(tag_count, 0);
+  }
+
+  #[test]
+  fn test_migrate_rejects_newer_database() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn
+      .pragma_update(None, "user_version", LATEST_SCHEMA_VERSION + 1)
+      .unwrap();
+
+    let result = migrate(&conn);
+    assert!(matches!(
+      result,
+      Err(MigrationError::DatabaseTooNew { found, supported })
+        if found == LATEST_SCHEMA_VERSION + 1 && supported == LATEST_SCHEMA_VERSION
+    ));
+  }
+}
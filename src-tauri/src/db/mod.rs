@@ -3,4 +3,4 @@ pub mod migrate;
 pub mod models;
 
 pub use connect::Database;
-pub use migrate::migrate;
+pub use migrate::{LATEST_SCHEMA_VERSION, MigrationError, migrate, schema_version};
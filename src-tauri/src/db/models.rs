@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
@@ -29,6 +30,205 @@ pub struct NoteWithContent {
   #[serde(default)]
   pub is_deleted: bool,
   pub deleted_at: Option<String>,
+  #[serde(default)]
+  pub properties: NoteProperties,
+}
+
+/// ノートの先頭の YAML front matter に書かれたプロパティ
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoteProperties {
+  pub title: Option<String>,
+  #[serde(default)]
+  pub tags: Vec<String>,
+  #[serde(default)]
+  pub aliases: Vec<String>,
+  pub created: Option<String>,
+  // それ以外のキーと値 (書かれた順)
+  #[serde(default)]
+  pub custom: Vec<NoteProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteProperty {
+  pub key: String,
+  pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateNotePropertiesInput {
+  pub id: i64,
+  pub properties: NoteProperties,
+}
+
+/// デイリーノートの設定
+///
+/// pattern は folder からのパスで、YYYY・MM・DD を日付に置き換える。最後の要素がノートのタイトルになる。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyNoteSettings {
+  pub folder: String,
+  pub pattern: String,
+  pub template_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyNote {
+  // YYYY-MM-DD
+  pub date: String,
+  pub note_id: i64,
+}
+
+/// ある日付の前後にあるデイリーノート
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyNoteNeighbors {
+  pub previous: Option<DailyNote>,
+  pub next: Option<DailyNote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteLink {
+  pub target: String,
+  pub target_note_id: Option<i64>,
+  pub target_title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteRevision {
+  pub id: i64,
+  pub note_id: i64,
+  pub title: String,
+  pub created_at: String,
+  pub char_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteRevisionWithContent {
+  pub id: i64,
+  pub note_id: i64,
+  pub title: String,
+  pub content: String,
+  pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+  Equal,
+  Insert,
+  Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+  pub kind: DiffLineKind,
+  pub content: String,
+}
+
+// 外部での変更をデータベースに反映した結果 (files-changed イベントのペイロード)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilesChanged {
+  pub created_notes: Vec<i64>,
+  pub modified_notes: Vec<i64>,
+  pub renamed_notes: Vec<i64>,
+  pub deleted_notes: Vec<i64>,
+  pub created_folders: Vec<i64>,
+  pub renamed_folders: Vec<i64>,
+  pub deleted_folders: Vec<i64>,
+}
+
+impl FilesChanged {
+  pub fn is_empty(&self) -> bool {
+    self.created_notes.is_empty()
+      && self.modified_notes.is_empty()
+      && self.renamed_notes.is_empty()
+      && self.deleted_notes.is_empty()
+      && self.created_folders.is_empty()
+      && self.renamed_folders.is_empty()
+      && self.deleted_folders.is_empty()
+  }
+}
+
+// ボールトの整合性チェックで見つかった問題
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VaultIssue {
+  // ノートのファイルが本来の場所にも trash にも存在しない
+  MissingFile {
+    note_id: i64,
+    file_path: String,
+  },
+  // データベースに登録されていないファイル
+  OrphanFile {
+    file_path: String,
+  },
+  // データベースに登録されていないフォルダ
+  OrphanFolder {
+    folder_path: String,
+  },
+  // is_deleted とファイルの場所 (trash かどうか) が一致しない
+  TrashMismatch {
+    note_id: i64,
+    file_path: String,
+    is_deleted: bool,
+  },
+  // 検索インデックスに登録されていないノート
+  FtsMissing {
+    note_id: i64,
+  },
+  // 検索インデックスの内容がファイルと一致しない
+  FtsOutdated {
+    note_id: i64,
+  },
+  // 存在しない (または削除済みの) ノートの検索インデックス
+  FtsOrphan {
+    note_id: i64,
+  },
+  // folder_path が親フォルダの folder_path の下にない
+  FolderPathMismatch {
+    folder_id: i64,
+    folder_path: String,
+    expected_path: String,
+  },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultReport {
+  pub issues: Vec<VaultIssue>,
+  pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+  #[default]
+  Relevance,
+  Updated,
+  Created,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+  Title,
+  Content,
+}
+
+// 一致箇所 (文字単位のオフセット、end は含まない)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRange {
+  pub field: SearchField,
+  pub start: usize,
+  pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+  pub note: Note,
+  // bm25 の符号を反転した値 (大きいほど関連度が高い)
+  pub score: f64,
+  // 一致箇所を <mark> で囲んだ HTML (それ以外はエスケープ済み)
+  pub title_highlight: String,
+  pub snippet: String,
+  pub matches: Vec<MatchRange>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +267,8 @@ pub struct Folder {
   pub color: Option<String>,
   pub sort_by: Option<String>,
   pub sort_order: Option<String>,
+  // フォルダの既定のテンプレート (未設定なら親フォルダのものを使う)
+  pub default_template_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +287,8 @@ pub struct UpdateFolderInput {
   pub color: Option<String>,
   pub sort_by: Option<String>,
   pub sort_order: Option<String>,
+  #[serde(default)]
+  pub default_template_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,6 +302,7 @@ pub struct MoveFolderInput {
 pub enum FileItem {
   Folder(FolderWithChildren),
   Note(Note),
+  SavedSearch(SavedSearchWithChildren),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -117,17 +322,118 @@ pub struct FolderWithChildren {
   pub color: Option<String>,
   pub sort_by: Option<String>,
   pub sort_order: Option<String>,
+  // フォルダの既定のテンプレート (未設定なら親フォルダのものを使う)
+  pub default_template_id: Option<i64>,
+}
+
+// フォルダの sort_by に保存する値 (フロントエンドの表記に合わせる)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortBy {
+  // タイトル (数字は数値として比較する)
+  Name,
+  CreatedAt,
+  UpdatedAt,
+  // position 列の順
+  Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+  Asc,
+  Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SortSettings {
+  pub sort_by: SortBy,
+  pub sort_order: SortOrder,
+}
+
+// 手動並び替えの結果 (parent_id 直下の項目を並べたい順に指定する)
+#[derive(Debug, Deserialize)]
+pub struct ReorderFilesInput {
+  pub parent_id: Option<i64>,
+  #[serde(default)]
+  pub folder_ids: Vec<i64>,
+  #[serde(default)]
+  pub note_ids: Vec<i64>,
+}
+
+// ツリーに仮想フォルダとして表示する保存済み検索 (children は検索結果のノート)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchWithChildren {
+  pub id: i64,
+  pub name: String,
+  pub query: String,
+  pub icon: Option<String>,
+  pub sort: SearchSort,
+  pub children: Vec<FileItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+  pub id: i64,
+  pub name: String,
+  pub query: String,
+  pub icon: Option<String>,
+  pub sort: SearchSort,
+  pub created_at: String,
+  pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSavedSearchInput {
+  pub name: String,
+  pub query: String,
+  pub icon: Option<String>,
+  pub sort: Option<SearchSort>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSavedSearchInput {
+  pub id: i64,
+  pub name: String,
+  pub query: String,
+  pub icon: Option<String>,
+  pub sort: Option<SearchSort>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
   pub id: i64,
+  // 階層は "親/子" のように / で区切った名前で表す
   pub name: String,
   pub color: Option<String>,
+  pub parent_id: Option<i64>,
   pub created_at: String,
   pub updated_at: String,
 }
 
+/// ノートに付いたタグ
+///
+/// 手動で付けたものか、本文の #タグ から付いたものかを区別する (両方のこともある)。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteTag {
+  #[serde(flatten)]
+  pub tag: Tag,
+  pub is_manual: bool,
+  pub is_inline: bool,
+}
+
+/// 使用状況付きのタグ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagWithStats {
+  #[serde(flatten)]
+  pub tag: Tag,
+  // ゴミ箱にないノートの数
+  pub note_count: i64,
+  // タグを付けたノートが最後に更新された日時 (使われていなければ None)
+  pub last_used_at: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateTagInput {
   pub name: String,
@@ -139,6 +445,21 @@ pub struct UpdateTagInput {
   pub id: i64,
   pub name: String,
   pub color: Option<String>,
+  // 名前を変えたとき、ノートの本文の #タグ も書き換える
+  #[serde(default)]
+  pub rewrite_content: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeTagsInput {
+  pub source_ids: Vec<i64>,
+  pub target_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveTagInput {
+  pub id: i64,
+  pub new_parent_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -151,6 +472,42 @@ pub struct Template {
   pub updated_at: String,
 }
 
+/// テンプレートの変数に入れる値
+///
+/// date は YYYY-MM-DD (省略すると今日)。prompts はテンプレートの `{{prompt:名前}}` に入れる値。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateContext {
+  pub title: Option<String>,
+  pub folder: Option<String>,
+  pub date: Option<String>,
+  #[serde(default)]
+  pub prompts: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderedTemplate {
+  pub content: String,
+  // {{cursor}} の位置 (UTF-16 の単位でのオフセット)
+  pub cursor: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateNoteFromTemplateInput {
+  pub template_id: i64,
+  pub title: String,
+  pub parent_id: Option<i64>,
+  pub folder_path: Option<String>,
+  #[serde(default)]
+  pub prompts: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteFromTemplate {
+  #[serde(flatten)]
+  pub note: NoteWithContent,
+  pub cursor: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateTemplateInput {
   pub name: String,
@@ -202,3 +559,85 @@ pub struct UpdateBackupSettingsInput {
   pub backup_path: Option<String>,
   pub max_backups: i64,
 }
+
+/// data_dir/assets に内容のハッシュの名前で保存したファイル
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Asset {
+  pub id: i64,
+  pub hash: String,
+  pub file_name: String,
+  pub mime_type: String,
+  pub size: i64,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+  pub created_at: String,
+  #[serde(default)]
+  pub is_deleted: bool,
+  // 添付したときのファイル名 (貼り付けた画像などは None)
+  pub original_name: Option<String>,
+  // ノートから参照するリンク (assets/<hash>.png)
+  pub link: String,
+}
+
+/// 添付したファイルと、ノートに挿入する Markdown のリンク
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+  #[serde(flatten)]
+  pub asset: Asset,
+  pub markdown: String,
+}
+
+/// 貼り付けた PNG を再エンコードするときの形式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageEncoding {
+  // ロスレスの WebP
+  Webp,
+  Jpeg,
+}
+
+/// アセットの設定 (サイズはバイト単位、画像の大きさはピクセル単位)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetSettings {
+  pub max_file_size: i64,
+  // 貼り付けた画像の縮小と再エンコードを行うか
+  pub process_images: bool,
+  pub max_image_dimension: i64,
+  // これより大きい PNG は reencode_format で保存し直す
+  pub reencode_threshold: i64,
+  pub reencode_format: ImageEncoding,
+  pub jpeg_quality: i64,
+  pub strip_exif: bool,
+  pub thumbnail_size: i64,
+}
+
+/// どのノートからも参照されていないアセット
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnusedAsset {
+  pub file_name: String,
+  pub link: String,
+  pub size: i64,
+  // assets テーブルにない古いファイルは None
+  pub asset_id: Option<i64>,
+  // 既にゴミ箱に移したもの
+  pub is_deleted: bool,
+}
+
+/// 参照されていないアセットの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetCleanupAction {
+  // assets/.trash に移す (ノートから再び参照されると戻す)
+  Trash,
+  Delete,
+}
+
+/// 参照されていないアセットの整理結果 (dry_run なら何も変更していない)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetCleanupReport {
+  pub assets: Vec<UnusedAsset>,
+  pub total_size: i64,
+  pub dry_run: bool,
+  // ゴミ箱にあったが再び参照されていたため戻したアセットの数
+  pub restored: usize,
+}
@@ -7,7 +7,7 @@ use chrono::Timelike;
 use std::sync::{Arc, Mutex};
 
 use config::AppConfig;
-use db::{Database, migrate};
+use db::{Database, MigrationError, migrate};
 use std::str::FromStr;
 use tauri::{Emitter, Manager};
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
@@ -108,6 +108,41 @@ pub fn run() {
                           eprintln!("Main window not found for toggle_window");
                         }
                       }
+                      "daily_note" => {
+                        let db = context.db.clone();
+                        let notes_dir =
+                          std::path::PathBuf::from(&context.config.data_dir).join("notes");
+                        let today = chrono::Local::now().date_naive();
+                        let result = tauri::async_runtime::spawn_blocking(move || {
+                          services::DailyNoteService::new(db, notes_dir)
+                            .open_or_create_daily_note(today)
+                        })
+                        .await;
+
+                        match result {
+                          Ok(Ok(note)) => {
+                            if let Some(window) = app_handle.get_webview_window("main") {
+                              if window.is_minimized().unwrap_or(false) {
+                                let _ = window.unminimize();
+                              }
+                              if !window.is_visible().unwrap_or(false) {
+                                let _ = window.show();
+                              }
+                              let _ = window.set_focus();
+                              let _ = window.set_always_on_top(true);
+                              let _ = window.set_always_on_top(false);
+
+                              if let Err(e) = window.emit("open-daily-note", note.id) {
+                                eprintln!("Failed to emit event: {}", e);
+                              }
+                            } else {
+                              eprintln!("Main window not found for daily_note");
+                            }
+                          }
+                          Ok(Err(e)) => eprintln!("Failed to open daily note: {}", e),
+                          Err(e) => eprintln!("Failed to open daily note: {}", e),
+                        }
+                      }
                       _ => {}
                     }
                   }
@@ -121,6 +156,54 @@ pub fn run() {
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_notification::init())
+    // ノートが参照する画像 (assets/<hash>.png) を webview に渡す
+    // ファイルの読み込みやサムネイルの生成はメインスレッドを止めないよう別スレッドで行う
+    .register_asynchronous_uri_scheme_protocol(
+      services::assets::ASSET_PROTOCOL,
+      |ctx, request, responder| {
+        let app_handle = ctx.app_handle().clone();
+        let path = request.uri().path().trim_start_matches('/').to_string();
+        tauri::async_runtime::spawn_blocking(move || {
+          let state: tauri::State<AppState> = app_handle.state();
+          let asset = state.get_context().and_then(|context| {
+            let service = services::AssetService::new(
+              context.db.clone(),
+              std::path::PathBuf::from(&context.config.data_dir)
+                .join(services::assets::ASSETS_DIR),
+            );
+            // thumbnails/<size>/<hash>.png はプレビュー用の縮小画像
+            match path.strip_prefix("thumbnails/") {
+              Some(rest) => {
+                let (size, file_name) = rest
+                  .split_once('/')
+                  .ok_or_else(|| format!("Invalid thumbnail path: {}", path))?;
+                let size = size
+                  .parse()
+                  .map_err(|_| format!("Invalid thumbnail size: {}", size))?;
+                service.read_thumbnail(file_name, size)
+              }
+              None => service.read_asset(&path),
+            }
+          });
+          let response = match asset {
+            Ok((data, mime_type)) => tauri::http::Response::builder()
+              .header(tauri::http::header::CONTENT_TYPE, mime_type)
+              // 内容が変わればファイル名も変わり、サムネイルは大きさも URL に含むため、ずっとキャッシュしてよい
+              .header(
+                tauri::http::header::CACHE_CONTROL,
+                "public, max-age=31536000, immutable",
+              )
+              .body(data)
+              .unwrap(),
+            Err(_) => tauri::http::Response::builder()
+              .status(tauri::http::StatusCode::NOT_FOUND)
+              .body(Vec::new())
+              .unwrap(),
+          };
+          responder.respond(response);
+        });
+      },
+    )
     .setup(|app| {
       let app_dir = app.path().app_data_dir()?;
       std::fs::create_dir_all(&app_dir)?;
@@ -132,16 +215,26 @@ pub fn run() {
         let db_path = metadata_dir.join("app.db");
 
         if let Ok(db) = Database::new(db_path.to_str().unwrap()) {
-          {
+          let migration_result = {
             let conn = db.conn.lock().unwrap();
-            if let Err(e) = migrate(&conn) {
+            migrate(&conn)
+          };
+          match migration_result {
+            // 新しいバージョンのアプリで作成されたデータベースは壊さないよう開かない
+            Err(e @ MigrationError::DatabaseTooNew { .. }) => {
               eprintln!("Migration failed: {}", e);
+              None
+            }
+            result => {
+              if let Err(e) = result {
+                eprintln!("Migration failed: {}", e);
+              }
+              Some(Arc::new(AppContext {
+                db: Arc::new(db),
+                config,
+              }))
             }
           }
-          Some(Arc::new(AppContext {
-            db: Arc::new(db),
-            config,
-          }))
         } else {
           None
         }
@@ -182,6 +275,76 @@ pub fn run() {
           }
         });
 
+        // 既存のノートのリンクを索引に登録
+        let link_service = services::LinkService::new(
+          ctx.db.clone(),
+          std::path::PathBuf::from(&ctx.config.data_dir).join("notes"),
+        );
+        std::thread::spawn(move || {
+          if let Err(e) = link_service.ensure_index() {
+            eprintln!("Link indexing failed: {}", e);
+          }
+        });
+
+        // 既存のノートの #タグ をタグとして登録
+        let tag_service = services::TagService::new(ctx.db.clone());
+        std::thread::spawn(move || {
+          if let Err(e) = tag_service.ensure_index() {
+            eprintln!("Tag indexing failed: {}", e);
+          }
+        });
+
+        // 既存のノートの front matter をプロパティとして登録
+        let property_service = services::PropertyService::new(ctx.db.clone());
+        std::thread::spawn(move || {
+          if let Err(e) = property_service.ensure_index() {
+            eprintln!("Property indexing failed: {}", e);
+          }
+        });
+
+        // テンプレートを data_dir/templates の .md ファイルと同期
+        let template_service = services::TemplateService::with_templates_dir(
+          ctx.db.clone(),
+          std::path::PathBuf::from(&ctx.config.data_dir).join("templates"),
+        );
+        std::thread::spawn(move || {
+          if let Err(e) = template_service.sync_templates_dir() {
+            eprintln!("Template sync failed: {}", e);
+          }
+        });
+
+        // 既存のノートを表記ゆれを揃えた検索インデックスに登録
+        let search_service = services::SearchService::new(ctx.db.clone());
+        std::thread::spawn(move || {
+          if let Err(e) = search_service.ensure_index() {
+            eprintln!("Search indexing failed: {}", e);
+          }
+        });
+
+        // 外部のエディタや git による変更をデータベースに反映
+        let app_handle_for_watcher = app_handle.clone();
+        let mut watcher = services::WatcherService::new(
+          ctx.db.clone(),
+          std::path::PathBuf::from(&ctx.config.data_dir).join("notes"),
+        );
+        std::thread::spawn(move || {
+          loop {
+            std::thread::sleep(std::time::Duration::from_secs(
+              services::watcher::WATCH_INTERVAL_SECS,
+            ));
+
+            match watcher.sync_changes() {
+              Ok(changes) if !changes.is_empty() => {
+                if let Err(e) = app_handle_for_watcher.emit("files-changed", &changes) {
+                  eprintln!("Failed to emit event: {}", e);
+                }
+              }
+              Ok(_) => {}
+              Err(e) => eprintln!("File sync failed: {}", e),
+            }
+          }
+        });
+
         std::thread::spawn(move || {
           let mut last_notified_minute = None;
           loop {
@@ -238,9 +401,11 @@ pub fn run() {
       commands::note::get_note_by_id,
       commands::note::create_note,
       commands::note::update_note,
+      commands::note::update_note_properties,
       commands::note::delete_note,
       commands::note::move_note,
       commands::note::search_notes,
+      commands::note::search_note_hits,
       commands::note::restore_note,
       commands::note::permanently_delete_note,
       commands::note::get_deleted_notes,
@@ -250,6 +415,14 @@ pub fn run() {
       commands::note::import_note,
       commands::note::import_notes,
       commands::note::update_favorite_order,
+      commands::note::reorder_favorites,
+      commands::revision::get_note_revisions,
+      commands::revision::get_note_revision,
+      commands::revision::diff_note_revisions,
+      commands::revision::restore_note_revision,
+      commands::links::get_backlinks,
+      commands::links::get_outgoing_links,
+      commands::doctor::check_vault,
       commands::folder::get_all_folders,
       commands::folder::get_folder_by_id,
       commands::folder::create_folder,
@@ -260,6 +433,9 @@ pub fn run() {
       commands::folder::permanently_delete_folder,
       commands::folder::get_deleted_folders,
       commands::files::get_all_files,
+      commands::files::get_default_sort,
+      commands::files::update_default_sort,
+      commands::files::reorder_files,
       commands::activity::record_daily_activity,
       commands::activity::get_streak,
       commands::activity::get_activity_heatmap,
@@ -268,6 +444,8 @@ pub fn run() {
       commands::activity::get_daily_progress,
       commands::tags::create_tag,
       commands::tags::update_tag,
+      commands::tags::move_tag,
+      commands::tags::merge_tags,
       commands::tags::delete_tag,
       commands::tags::get_all_tags,
       commands::tags::add_tag_to_note,
@@ -275,16 +453,37 @@ pub fn run() {
       commands::tags::remove_tag_from_note,
       commands::tags::get_notes_by_tag,
       commands::tags::get_tags_by_note,
+      commands::saved_search::create_saved_search,
+      commands::saved_search::get_saved_searches,
+      commands::saved_search::update_saved_search,
+      commands::saved_search::delete_saved_search,
+      commands::saved_search::run_saved_search,
       commands::notification::get_notification_settings,
       commands::notification::update_notification_settings,
       commands::hotkeys::get_hotkeys,
       commands::hotkeys::update_hotkey,
       commands::assets::save_image,
+      commands::assets::attach_file,
+      commands::assets::get_note_attachments,
+      commands::assets::get_asset_settings,
+      commands::assets::update_asset_settings,
+      commands::assets::get_asset,
+      commands::assets::find_unused_assets,
+      commands::assets::clean_up_unused_assets,
       commands::template::get_all_templates,
       commands::template::get_template_by_id,
       commands::template::create_template,
       commands::template::update_template,
       commands::template::delete_template,
+      commands::template::render_template,
+      commands::template::get_template_prompts,
+      commands::template::create_note_from_template,
+      commands::template::export_templates,
+      commands::template::import_templates,
+      commands::daily_note::open_or_create_daily_note,
+      commands::daily_note::get_daily_note_neighbors,
+      commands::daily_note::get_daily_note_settings,
+      commands::daily_note::update_daily_note_settings,
       commands::backup::create_backup,
       commands::backup::restore_backup,
       commands::backup::read_backup_metadata,
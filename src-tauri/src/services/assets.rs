@@ -1,72 +1,964 @@
-use crate::config::AppConfig;
-use chrono::Local;
+use crate::db::Database;
+use crate::db::models::{
+  Asset, AssetCleanupAction, AssetCleanupReport, AssetSettings, Attachment, ImageEncoding,
+  UnusedAsset,
+};
+use crate::services::{atomic, images};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-pub struct AssetService;
+/// Scheme of the custom protocol that serves assets to the webview
+pub const ASSET_PROTOCOL: &str = "notly-asset";
+
+/// Directory under data_dir that assets are stored in (also the prefix of asset links)
+pub const ASSETS_DIR: &str = "assets";
+
+/// Assets newer than this are never treated as unused, since the note that
+/// links to a just pasted image may not have been saved yet
+const RECENT_ASSET_GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// Largest thumbnail size that can be configured or requested
+pub const MAX_THUMBNAIL_SIZE: i64 = 1024;
+
+/// MIME type for a file extension (used when the content itself does not tell)
+pub fn mime_type_for(extension: &str) -> &'static str {
+  match extension.to_lowercase().as_str() {
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "webp" => "image/webp",
+    "svg" => "image/svg+xml",
+    "txt" => "text/plain",
+    "md" => "text/markdown",
+    "csv" => "text/csv",
+    "json" => "application/json",
+    _ => "application/octet-stream",
+  }
+}
+
+/// Detect the extension and MIME type of a file from its magic bytes
+///
+/// Text formats such as SVG have no magic bytes, so the given extension is used for them.
+pub fn detect_content_type(data: &[u8], extension: &str) -> (String, String) {
+  if let Some(kind) = infer::get(data) {
+    let extension = match kind.extension() {
+      "jpeg" => "jpg",
+      other => other,
+    };
+    return (extension.to_string(), kind.mime_type().to_string());
+  }
+  let mut extension = extension.trim_start_matches('.').to_lowercase();
+  if extension == "jpeg" {
+    extension = "jpg".to_string();
+  }
+  if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+    extension = "bin".to_string();
+  }
+  let mime_type = mime_type_for(&extension).to_string();
+  (extension, mime_type)
+}
+
+/// Markdown that embeds an image or links to any other file
+pub fn attachment_markdown(asset: &Asset, label: &str) -> String {
+  let mut escaped = String::new();
+  for c in label.chars() {
+    if matches!(c, '[' | ']' | '\\') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  if asset.mime_type.starts_with("image/") {
+    format!("![{}]({})", escaped, asset.link)
+  } else {
+    format!("[{}]({})", escaped, asset.link)
+  }
+}
+
+/// Link that notes use to reference an asset, e.g. `assets/<hash>.png`
+pub fn asset_link(file_name: &str) -> String {
+  format!("{}/{}", ASSETS_DIR, file_name)
+}
+
+// Asset file names are always `<hex hash>.<extension>`, so anything else is rejected
+fn is_asset_file_name(file_name: &str) -> bool {
+  match file_name.split_once('.') {
+    Some((hash, extension)) => {
+      !hash.is_empty()
+        && hash.chars().all(|c| c.is_ascii_hexdigit())
+        && !extension.is_empty()
+        && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+    None => false,
+  }
+}
+
+/// Names of the assets that content links to through `assets/<name>`
+pub fn referenced_asset_names(content: &str) -> HashSet<String> {
+  let prefix = format!("{}/", ASSETS_DIR);
+  let mut names = HashSet::new();
+  for (start, _) in content.match_indices(&prefix) {
+    let rest = &content[start + prefix.len()..];
+    let end = rest
+      .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')))
+      .unwrap_or(rest.len());
+    // 文末の句点などは名前に含めない
+    let name = rest[..end].trim_end_matches('.');
+    if !name.is_empty() {
+      names.insert(name.to_string());
+    }
+  }
+  names
+}
+
+const ASSET_COLUMNS: &str =
+  "id, hash, file_name, mime_type, size, width, height, created_at, is_deleted, original_name";
+
+fn row_to_asset(row: &Row) -> rusqlite::Result<Asset> {
+  let file_name: String = row.get(2)?;
+  Ok(Asset {
+    id: row.get(0)?,
+    hash: row.get(1)?,
+    link: asset_link(&file_name),
+    file_name,
+    mime_type: row.get(3)?,
+    size: row.get(4)?,
+    width: row.get(5)?,
+    height: row.get(6)?,
+    created_at: row.get(7)?,
+    is_deleted: row.get(8)?,
+    original_name: row.get(9)?,
+  })
+}
+
+pub struct AssetService {
+  db: Arc<Database>,
+  assets_dir: PathBuf,
+}
 
 impl AssetService {
-  pub fn save_image(
+  pub fn new(db: Arc<Database>, assets_dir: PathBuf) -> Self {
+    AssetService { db, assets_dir }
+  }
+
+  // ゴミ箱に移したアセットは assets/.trash に置く
+  fn asset_path(&self, file_name: &str, is_deleted: bool) -> PathBuf {
+    if is_deleted {
+      self.assets_dir.join(".trash").join(file_name)
+    } else {
+      self.assets_dir.join(file_name)
+    }
+  }
+
+  fn find_by_hash(conn: &Connection, hash: &str) -> Result<Option<Asset>, String> {
+    conn
+      .query_row(
+        &format!("SELECT {} FROM assets WHERE hash = ?", ASSET_COLUMNS),
+        params![hash],
+        row_to_asset,
+      )
+      .optional()
+      .map_err(|e| format!("Failed to fetch asset: {}", e))
+  }
+
+  /// Store a file named by the SHA-256 hash of its content
+  ///
+  /// Saving the same content again returns the existing asset instead of a copy.
+  pub fn save_asset(&self, data: &[u8], extension: &str) -> Result<Asset, String> {
+    self.store(data, extension, None)
+  }
+
+  fn store(
+    &self,
     data: &[u8],
     extension: &str,
-    app_config: &AppConfig,
-  ) -> Result<String, String> {
-    let data_dir = PathBuf::from(&app_config.data_dir);
-    let assets_dir = data_dir.join("assets");
-
-    if !assets_dir.exists() {
-      fs::create_dir_all(&assets_dir)
-        .map_err(|e| format!("Failed to create assets directory: {}", e))?;
-    }
-
-    let now = Local::now();
-    // Use milliseconds to avoid collisions
-    let filename = format!("{}.{}", now.format("%Y-%m-%d-%H%M%S%3f"), extension);
-    let file_path = assets_dir.join(&filename);
-
-    fs::write(&file_path, data).map_err(|e| format!("Failed to write image file: {}", e))?;
-
-    // Determine MIME type
-    let mime_type = match extension.to_lowercase().as_str() {
-      "png" => "image/png",
-      "jpg" | "jpeg" => "image/jpeg",
-      "gif" => "image/gif",
-      "webp" => "image/webp",
-      "svg" => "image/svg+xml",
-      _ => "image/png",
+    original_name: Option<&str>,
+  ) -> Result<Asset, String> {
+    let hash = format!("{:x}", Sha256::digest(data));
+    let conn = self.db.conn.lock().unwrap();
+    let settings = Self::read_settings(&conn)?;
+    if data.len() as i64 > settings.max_file_size {
+      return Err(format!(
+        "File is too large: {} bytes (limit {} bytes)",
+        data.len(),
+        settings.max_file_size
+      ));
+    }
+
+    if let Some(asset) = Self::find_by_hash(&conn, &hash)? {
+      if asset.is_deleted {
+        self.restore_asset(&conn, &asset)?;
+      }
+      // The file may have been removed by hand, so write it back
+      let path = self.assets_dir.join(&asset.file_name);
+      if !path.exists() {
+        fs::create_dir_all(&self.assets_dir)
+          .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+        atomic::write_bytes(&path, data)
+          .map_err(|e| format!("Failed to write asset file: {}", e))?;
+      }
+      return Self::find_by_hash(&conn, &hash)?.ok_or_else(|| "Failed to save asset".to_string());
+    }
+
+    let (extension, mime_type) = detect_content_type(data, extension);
+    fs::create_dir_all(&self.assets_dir)
+      .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+    let file_name = format!("{}.{}", hash, extension);
+    atomic::write_bytes(&self.assets_dir.join(&file_name), data)
+      .map_err(|e| format!("Failed to write asset file: {}", e))?;
+
+    // SVG や画像以外のファイルは幅と高さを持たない
+    let dimensions = imagesize::blob_size(data).ok();
+    conn
+      .execute(
+        "INSERT INTO assets (hash, file_name, mime_type, size, width, height, original_name) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![
+          hash,
+          file_name,
+          mime_type,
+          data.len() as i64,
+          dimensions.map(|d| d.width as u32),
+          dimensions.map(|d| d.height as u32),
+          original_name,
+        ],
+      )
+      .map_err(|e| format!("Failed to save asset: {}", e))?;
+
+    Self::find_by_hash(&conn, &hash)?.ok_or_else(|| "Failed to save asset".to_string())
+  }
+
+  /// Attach any kind of file and return the Markdown that links to it
+  pub fn attach_file(&self, data: &[u8], file_name: &str) -> Result<Attachment, String> {
+    let file_name = file_name.trim();
+    let extension = Path::new(file_name)
+      .extension()
+      .map(|ext| ext.to_string_lossy().to_string())
+      .unwrap_or_default();
+    let asset = self.store(
+      data,
+      &extension,
+      Some(file_name).filter(|name| !name.is_empty()),
+    )?;
+    let label = if file_name.is_empty() {
+      asset.file_name.clone()
+    } else {
+      file_name.to_string()
+    };
+    Ok(Attachment {
+      markdown: attachment_markdown(&asset, &label),
+      asset,
+    })
+  }
+
+  /// Assets that note content links to, in order of appearance
+  pub fn get_attachments(&self, content: &str) -> Result<Vec<Asset>, String> {
+    let names = referenced_asset_names(content);
+    let conn = self.db.conn.lock().unwrap();
+    let mut assets = Self::all_assets(&conn)?
+      .into_iter()
+      .filter(|asset| names.contains(&asset.file_name))
+      .collect::<Vec<_>>();
+    assets.sort_by_key(|asset| content.find(&asset.link));
+    Ok(assets)
+  }
+
+  fn read_settings(conn: &Connection) -> Result<AssetSettings, String> {
+    conn
+      .query_row(
+        "SELECT max_file_size, process_images, max_image_dimension, reencode_threshold, reencode_format, jpeg_quality, strip_exif, thumbnail_size FROM asset_settings WHERE id = 1",
+        [],
+        |row| {
+          Ok(AssetSettings {
+            max_file_size: row.get(0)?,
+            process_images: row.get(1)?,
+            max_image_dimension: row.get(2)?,
+            reencode_threshold: row.get(3)?,
+            reencode_format: match row.get::<_, String>(4)?.as_str() {
+              "jpeg" => ImageEncoding::Jpeg,
+              _ => ImageEncoding::Webp,
+            },
+            jpeg_quality: row.get(5)?,
+            strip_exif: row.get(6)?,
+            thumbnail_size: row.get(7)?,
+          })
+        },
+      )
+      .map_err(|e| format!("Failed to fetch asset settings: {}", e))
+  }
+
+  pub fn get_settings(&self) -> Result<AssetSettings, String> {
+    let conn = self.db.conn.lock().unwrap();
+    Self::read_settings(&conn)
+  }
+
+  pub fn update_settings(&self, settings: AssetSettings) -> Result<AssetSettings, String> {
+    if settings.max_file_size <= 0 {
+      return Err("The maximum file size must be greater than zero".to_string());
+    }
+    if settings.max_image_dimension <= 0 || settings.thumbnail_size <= 0 {
+      return Err("Image sizes must be greater than zero".to_string());
+    }
+    if settings.thumbnail_size > MAX_THUMBNAIL_SIZE {
+      return Err(format!(
+        "The thumbnail size must be at most {}",
+        MAX_THUMBNAIL_SIZE
+      ));
+    }
+    if !(1..=100).contains(&settings.jpeg_quality) {
+      return Err("JPEG quality must be between 1 and 100".to_string());
+    }
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .execute(
+        "UPDATE asset_settings SET max_file_size = ?, process_images = ?, max_image_dimension = ?, reencode_threshold = ?, reencode_format = ?, jpeg_quality = ?, strip_exif = ?, thumbnail_size = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
+        params![
+          settings.max_file_size,
+          settings.process_images,
+          settings.max_image_dimension,
+          settings.reencode_threshold,
+          match settings.reencode_format {
+            ImageEncoding::Webp => "webp",
+            ImageEncoding::Jpeg => "jpeg",
+          },
+          settings.jpeg_quality,
+          settings.strip_exif,
+          settings.thumbnail_size,
+        ],
+      )
+      .map_err(|e| format!("Failed to update asset settings: {}", e))?;
+    Self::read_settings(&conn)
+  }
+
+  /// Save a pasted or dropped image and return the link notes should reference
+  ///
+  /// The image is shrunk, re-encoded and stripped of EXIF first as the settings say.
+  pub fn save_image(&self, data: &[u8], extension: &str) -> Result<String, String> {
+    let settings = self.get_settings()?;
+    let data = images::process_image(data, &settings)?;
+    Ok(self.save_asset(&data, extension)?.link)
+  }
+
+  // サムネイルは assets/.thumbnails/<hash>-<size>.webp にキャッシュする
+  fn thumbnail_path(&self, file_name: &str, size: i64) -> PathBuf {
+    let hash = file_name.split('.').next().unwrap_or(file_name);
+    self
+      .assets_dir
+      .join(".thumbnails")
+      .join(format!("{}-{}.webp", hash, size))
+  }
+
+  /// Read an image's thumbnail for previews, making and caching it on first use
+  ///
+  /// The size is part of the protocol URL (`thumbnails/<size>/<name>`), so
+  /// changing the thumbnail size setting never serves a stale cached image.
+  pub fn read_thumbnail(&self, file_name: &str, size: i64) -> Result<(Vec<u8>, String), String> {
+    if !(1..=MAX_THUMBNAIL_SIZE).contains(&size) {
+      return Err(format!("Invalid thumbnail size: {}", size));
+    }
+    let (data, mime_type) = self.read_asset(file_name)?;
+    if !mime_type.starts_with("image/") || mime_type == "image/svg+xml" {
+      return Err(format!("No thumbnail for {}", file_name));
+    }
+    let path = self.thumbnail_path(file_name, size);
+    if let Ok(thumbnail) = fs::read(&path) {
+      return Ok((thumbnail, "image/webp".to_string()));
+    }
+
+    let thumbnail = images::make_thumbnail(&data, size as u32)?;
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create thumbnail directory: {}", e))?;
+    }
+    atomic::write_bytes(&path, &thumbnail)
+      .map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+    Ok((thumbnail, "image/webp".to_string()))
+  }
+
+  fn remove_thumbnails(&self, file_name: &str) {
+    let prefix = format!("{}-", file_name.split('.').next().unwrap_or(file_name));
+    let Ok(entries) = fs::read_dir(self.assets_dir.join(".thumbnails")) else {
+      return;
     };
+    for entry in entries.flatten() {
+      if entry.file_name().to_string_lossy().starts_with(&prefix) {
+        let _ = fs::remove_file(entry.path());
+      }
+    }
+  }
+
+  /// Get an asset by its file name
+  pub fn get_asset(&self, file_name: &str) -> Result<Asset, String> {
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .query_row(
+        &format!("SELECT {} FROM assets WHERE file_name = ?", ASSET_COLUMNS),
+        params![file_name],
+        row_to_asset,
+      )
+      .map_err(|e| format!("Failed to fetch asset: {}", e))
+  }
+
+  /// Read an asset's content and MIME type for the custom protocol
+  pub fn read_asset(&self, file_name: &str) -> Result<(Vec<u8>, String), String> {
+    if !is_asset_file_name(file_name) {
+      return Err(format!("Invalid asset name: {}", file_name));
+    }
+    let asset = self.get_asset(file_name)?;
+    let data = fs::read(self.asset_path(&asset.file_name, asset.is_deleted))
+      .map_err(|e| format!("Failed to read asset file: {}", e))?;
+    Ok((data, asset.mime_type))
+  }
+
+  // ゴミ箱のアセットを assets に戻す
+  fn restore_asset(&self, conn: &Connection, asset: &Asset) -> Result<(), String> {
+    let trash_path = self.asset_path(&asset.file_name, true);
+    if trash_path.exists() {
+      fs::rename(&trash_path, self.asset_path(&asset.file_name, false))
+        .map_err(|e| format!("Failed to restore asset: {}", e))?;
+    }
+    conn
+      .execute(
+        "UPDATE assets SET is_deleted = FALSE, deleted_at = NULL WHERE id = ?",
+        params![asset.id],
+      )
+      .map_err(|e| format!("Failed to restore asset: {}", e))?;
+    Ok(())
+  }
+
+  // 削除していないノートとゴミ箱のノート、その履歴とテンプレートから参照されているアセット
+  fn referenced_names(&self, notes_dir: &Path) -> Result<HashSet<String>, String> {
+    let (notes, contents) = {
+      let conn = self.db.conn.lock().unwrap();
+      let notes = conn
+        .prepare("SELECT file_path, is_deleted FROM notes WHERE file_path IS NOT NULL")
+        .and_then(|mut stmt| {
+          stmt
+            .query_map([], |row| {
+              Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .map_err(|e| format!("Failed to fetch notes: {}", e))?;
+      // 履歴を復元したりテンプレートからノートを作ると再び参照される
+      let contents = conn
+        .prepare(
+          "SELECT r.content FROM note_revisions r JOIN notes n ON n.id = r.note_id
+           UNION ALL SELECT content FROM templates",
+        )
+        .and_then(|mut stmt| {
+          stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .map_err(|e| format!("Failed to fetch note revisions: {}", e))?;
+      (notes, contents)
+    };
+
+    let mut names = HashSet::new();
+    for content in &contents {
+      names.extend(referenced_asset_names(content));
+    }
+    for (file_path, is_deleted) in notes {
+      let path = PathBuf::from(&file_path);
+      let path = if is_deleted {
+        notes_dir
+          .join(".trash")
+          .join(path.strip_prefix(notes_dir).unwrap_or(&path))
+      } else {
+        path
+      };
+      match fs::read_to_string(&path) {
+        Ok(content) => names.extend(referenced_asset_names(&content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        // 読めないノートがあるときは参照を見落とさないよう何もしない
+        Err(e) => {
+          return Err(format!(
+            "Failed to read note file {}: {}",
+            path.display(),
+            e
+          ));
+        }
+      }
+    }
+    Ok(names)
+  }
 
-    // Return as base64 data URL
-    let base64_data = base64_encode(data);
-    Ok(format!("data:{};base64,{}", mime_type, base64_data))
+  fn all_assets(conn: &Connection) -> Result<Vec<Asset>, String> {
+    conn
+      .prepare(&format!("SELECT {} FROM assets", ASSET_COLUMNS))
+      .and_then(|mut stmt| {
+        stmt
+          .query_map([], row_to_asset)?
+          .collect::<rusqlite::Result<Vec<_>>>()
+      })
+      .map_err(|e| format!("Failed to fetch assets: {}", e))
+  }
+
+  fn unused_assets(
+    &self,
+    assets: &[Asset],
+    referenced: &HashSet<String>,
+  ) -> Result<Vec<UnusedAsset>, String> {
+    let cutoff = (chrono::Utc::now() - RECENT_ASSET_GRACE)
+      .format("%Y-%m-%d %H:%M:%S")
+      .to_string();
+    let mut unused: Vec<UnusedAsset> = assets
+      .iter()
+      .filter(|asset| !referenced.contains(&asset.file_name) && asset.created_at < cutoff)
+      .map(|asset| UnusedAsset {
+        file_name: asset.file_name.clone(),
+        link: asset.link.clone(),
+        size: asset.size,
+        asset_id: Some(asset.id),
+        is_deleted: asset.is_deleted,
+      })
+      .collect();
+
+    // テーブルにないファイル (ハッシュで保存する前に貼り付けた画像など)
+    let known: HashSet<&str> = assets.iter().map(|a| a.file_name.as_str()).collect();
+    for (dir, is_deleted) in [
+      (self.assets_dir.clone(), false),
+      (self.assets_dir.join(".trash"), true),
+    ] {
+      let Ok(entries) = fs::read_dir(dir) else {
+        continue;
+      };
+      for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata() else {
+          continue;
+        };
+        let is_recent = metadata
+          .modified()
+          .ok()
+          .and_then(|modified| modified.elapsed().ok())
+          .is_none_or(|age| age < RECENT_ASSET_GRACE);
+        // 一覧を読み込んだ後に保存されたアセットもここで除かれる
+        if !metadata.is_file()
+          || file_name.starts_with('.')
+          || known.contains(file_name.as_str())
+          || referenced.contains(&file_name)
+          || is_recent
+        {
+          continue;
+        }
+        unused.push(UnusedAsset {
+          link: asset_link(&file_name),
+          file_name,
+          size: metadata.len() as i64,
+          asset_id: None,
+          is_deleted,
+        });
+      }
+    }
+
+    unused.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(unused)
+  }
+
+  /// Find assets that no live or trashed note links to
+  pub fn find_unused_assets(&self, notes_dir: &Path) -> Result<Vec<UnusedAsset>, String> {
+    let assets = Self::all_assets(&self.db.conn.lock().unwrap())?;
+    let referenced = self.referenced_names(notes_dir)?;
+    self.unused_assets(&assets, &referenced)
+  }
+
+  /// Move unused assets to `assets/.trash` or delete them
+  ///
+  /// Trashing skips assets that are already in the trash, and trashed assets that
+  /// notes link to again are moved back. With `dry_run` only the report is made.
+  ///
+  /// The asset list is loaded before notes are scanned, so an image pasted during
+  /// the scan is never mistaken for an unused one.
+  pub fn clean_up_unused_assets(
+    &self,
+    notes_dir: &Path,
+    action: AssetCleanupAction,
+    dry_run: bool,
+  ) -> Result<AssetCleanupReport, String> {
+    let all_assets = Self::all_assets(&self.db.conn.lock().unwrap())?;
+    let referenced = self.referenced_names(notes_dir)?;
+    let conn = self.db.conn.lock().unwrap();
+    let assets: Vec<UnusedAsset> = self
+      .unused_assets(&all_assets, &referenced)?
+      .into_iter()
+      .filter(|asset| action == AssetCleanupAction::Delete || !asset.is_deleted)
+      .collect();
+    let restore: Vec<&Asset> = all_assets
+      .iter()
+      .filter(|asset| asset.is_deleted && referenced.contains(&asset.file_name))
+      .collect();
+
+    if !dry_run {
+      for asset in &assets {
+        let path = self.asset_path(&asset.file_name, asset.is_deleted);
+        match action {
+          AssetCleanupAction::Trash => {
+            let trash_path = self.asset_path(&asset.file_name, true);
+            if let Some(parent) = trash_path.parent() {
+              fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create asset trash: {}", e))?;
+            }
+            if path.exists() {
+              fs::rename(&path, &trash_path)
+                .map_err(|e| format!("Failed to move asset to trash: {}", e))?;
+            }
+            if let Some(id) = asset.asset_id {
+              conn
+                .execute(
+                  "UPDATE assets SET is_deleted = TRUE, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
+                  params![id],
+                )
+                .map_err(|e| format!("Failed to move asset to trash: {}", e))?;
+            }
+          }
+          AssetCleanupAction::Delete => {
+            if path.exists() {
+              fs::remove_file(&path).map_err(|e| format!("Failed to delete asset: {}", e))?;
+            }
+            if let Some(id) = asset.asset_id {
+              conn
+                .execute("DELETE FROM assets WHERE id = ?", params![id])
+                .map_err(|e| format!("Failed to delete asset: {}", e))?;
+              self.remove_thumbnails(&asset.file_name);
+            }
+          }
+        }
+      }
+      for asset in &restore {
+        self.restore_asset(&conn, asset)?;
+      }
+    }
+
+    Ok(AssetCleanupReport {
+      total_size: assets.iter().map(|asset| asset.size).sum(),
+      assets,
+      dry_run,
+      restored: restore.len(),
+    })
   }
 }
 
-fn base64_encode(data: &[u8]) -> String {
-  const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-  let mut result = String::new();
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use tempfile::TempDir;
 
-  let mut i = 0;
-  while i < data.len() {
-    let b1 = data[i];
-    let b2 = if i + 1 < data.len() { data[i + 1] } else { 0 };
-    let b3 = if i + 2 < data.len() { data[i + 2] } else { 0 };
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
 
-    result.push(CHARS[(b1 >> 2) as usize] as char);
-    result.push(CHARS[(((b1 & 0x03) << 4) | (b2 >> 4)) as usize] as char);
-    result.push(if i + 1 < data.len() {
-      CHARS[(((b2 & 0x0f) << 2) | (b3 >> 6)) as usize] as char
-    } else {
-      '='
-    });
-    result.push(if i + 2 < data.len() {
-      CHARS[(b3 & 0x3f) as usize] as char
-    } else {
-      '='
-    });
+  // 貼り付けてから時間が経ったことにする (新しいアセットは未使用とみなさない)
+  fn age_assets(db: &Database, assets_dir: &Path) {
+    let conn = db.conn.lock().unwrap();
+    conn
+      .execute(
+        "UPDATE assets SET created_at = datetime('now', '-1 hour')",
+        [],
+      )
+      .unwrap();
+    let old = std::time::SystemTime::now() - Duration::from_secs(60 * 60);
+    for dir in [assets_dir.to_path_buf(), assets_dir.join(".trash")] {
+      for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if entry.path().is_file() {
+          fs::File::options()
+            .write(true)
+            .open(entry.path())
+            .and_then(|file| file.set_modified(old))
+            .unwrap();
+        }
+      }
+    }
+  }
 
-    i += 3;
+  // 幅 3・高さ 2 の PNG のヘッダー (サイズの読み取りにはこれだけあればよい)
+  fn png_header() -> Vec<u8> {
+    let mut data = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+    data.extend_from_slice(&3u32.to_be_bytes());
+    data.extend_from_slice(&2u32.to_be_bytes());
+    data.extend_from_slice(&[8, 6, 0, 0, 0]);
+    data
   }
 
-  result
+  #[test]
+  fn test_save_asset_deduplicates_by_content() {
+    let (db, temp_dir) = setup_test_db();
+    let assets_dir = temp_dir.path().join("assets");
+    let service = AssetService::new(db, assets_dir.clone());
+
+    let asset = service.save_asset(&png_header(), "PNG").unwrap();
+    assert_eq!(asset.hash.len(), 64);
+    assert_eq!(asset.file_name, format!("{}.png", asset.hash));
+    assert_eq!(asset.link, format!("assets/{}.png", asset.hash));
+    assert_eq!(asset.mime_type, "image/png");
+    assert_eq!(asset.size, png_header().len() as i64);
+    assert_eq!((asset.width, asset.height), (Some(3), Some(2)));
+
+    // 同じ内容は拡張子が違っても同じファイルになる
+    let link = service.save_image(&png_header(), "jpg").unwrap();
+    assert_eq!(link, asset.link);
+    assert_eq!(fs::read_dir(&assets_dir).unwrap().count(), 1);
+
+    // 手で消されたファイルは書き戻す
+    fs::remove_file(assets_dir.join(&asset.file_name)).unwrap();
+    service.save_asset(&png_header(), "png").unwrap();
+    let (data, mime_type) = service.read_asset(&asset.file_name).unwrap();
+    assert_eq!(data, png_header());
+    assert_eq!(mime_type, "image/png");
+
+    let svg = service.save_asset(b"<svg/>", ".svg").unwrap();
+    assert_eq!(svg.mime_type, "image/svg+xml");
+    assert_eq!(svg.width, None);
+  }
+
+  #[test]
+  fn test_read_asset_rejects_other_paths() {
+    let (db, temp_dir) = setup_test_db();
+    fs::write(temp_dir.path().join("secret.png"), "secret").unwrap();
+    let service = AssetService::new(db, temp_dir.path().join("assets"));
+
+    assert!(service.read_asset("../secret.png").is_err());
+    assert!(service.read_asset("secret.png").is_err());
+    assert!(service.read_asset("abc.png").is_err());
+  }
+
+  #[test]
+  fn test_referenced_asset_names() {
+    let names = referenced_asset_names(
+      "![](assets/abc.png) <img src=\"../assets/2024-01-01-000000000.jpg\">\nassets/def.webp。assets/",
+    );
+    let mut names: Vec<String> = names.into_iter().collect();
+    names.sort();
+    assert_eq!(
+      names,
+      vec!["2024-01-01-000000000.jpg", "abc.png", "def.webp"]
+    );
+  }
+
+  #[test]
+  fn test_clean_up_unused_assets() {
+    use crate::db::models::{AssetCleanupAction, NoteWithContent};
+    use crate::services::NoteService;
+
+    let (db, temp_dir) = setup_test_db();
+    let notes_dir = temp_dir.path().join("notes");
+    let assets_dir = temp_dir.path().join("assets");
+    let service = AssetService::new(db.clone(), assets_dir.clone());
+    let notes = NoteService::new(db.clone(), notes_dir.clone());
+    let create_note = |title: &str, asset: &Asset| -> NoteWithContent {
+      notes
+        .create_note(
+          title.to_string(),
+          format!("![]({})", asset.link),
+          None,
+          None,
+        )
+        .unwrap()
+    };
+
+    let live = service.save_asset(b"live", "png").unwrap();
+    let trashed = service.save_asset(b"trashed", "png").unwrap();
+    let unused = service.save_asset(b"unused", "png").unwrap();
+    let purged = service.save_asset(b"purged", "png").unwrap();
+    fs::write(assets_dir.join("2024-01-01-000000000.png"), b"legacy").unwrap();
+
+    create_note("live", &live);
+    let note = create_note("trashed", &trashed);
+    notes.delete_note(note.id).unwrap();
+    let note = create_note("purged", &purged);
+    notes.delete_note(note.id).unwrap();
+    notes.permanently_delete_note(note.id).unwrap();
+
+    // 貼り付けたばかりのアセットはノートが保存される前かもしれないので残す
+    let names = |assets: &[UnusedAsset]| -> Vec<String> {
+      assets.iter().map(|a| a.file_name.clone()).collect()
+    };
+    assert!(service.find_unused_assets(&notes_dir).unwrap().is_empty());
+    age_assets(&db, &assets_dir);
+
+    // ゴミ箱のノートが参照するアセットは残す
+    let mut expected = vec![
+      "2024-01-01-000000000.png".to_string(),
+      unused.file_name.clone(),
+      purged.file_name.clone(),
+    ];
+    expected.sort();
+    assert_eq!(
+      names(&service.find_unused_assets(&notes_dir).unwrap()),
+      expected
+    );
+
+    // dry run では何も変えない
+    let report = service
+      .clean_up_unused_assets(&notes_dir, AssetCleanupAction::Trash, true)
+      .unwrap();
+    assert_eq!(names(&report.assets), expected);
+    assert_eq!(
+      report.total_size,
+      ("unused".len() + "purged".len() + "legacy".len()) as i64
+    );
+    assert!(assets_dir.join(&unused.file_name).exists());
+
+    // ゴミ箱に移しても表示はできる
+    let report = service
+      .clean_up_unused_assets(&notes_dir, AssetCleanupAction::Trash, false)
+      .unwrap();
+    assert_eq!(report.assets.len(), 3);
+    assert!(!assets_dir.join(&unused.file_name).exists());
+    assert!(assets_dir.join(".trash").join(&unused.file_name).exists());
+    assert!(service.get_asset(&unused.file_name).unwrap().is_deleted);
+    assert_eq!(service.read_asset(&unused.file_name).unwrap().0, b"unused");
+
+    // 再び参照されたアセットは戻し、ゴミ箱にあるものは移し直さない
+    create_note("again", &unused);
+    let report = service
+      .clean_up_unused_assets(&notes_dir, AssetCleanupAction::Trash, false)
+      .unwrap();
+    assert!(report.assets.is_empty());
+    assert_eq!(report.restored, 1);
+    assert!(assets_dir.join(&unused.file_name).exists());
+    assert!(!service.get_asset(&unused.file_name).unwrap().is_deleted);
+
+    // 削除するとゴミ箱のものも消える
+    let report = service
+      .clean_up_unused_assets(&notes_dir, AssetCleanupAction::Delete, false)
+      .unwrap();
+    assert_eq!(report.assets.len(), 2);
+    assert!(report.assets.iter().all(|a| a.is_deleted));
+    assert!(service.get_asset(&purged.file_name).is_err());
+    assert_eq!(fs::read_dir(assets_dir.join(".trash")).unwrap().count(), 0);
+    assert!(service.find_unused_assets(&notes_dir).unwrap().is_empty());
+    assert!(assets_dir.join(&live.file_name).exists());
+    assert!(assets_dir.join(&trashed.file_name).exists());
+  }
+
+  #[test]
+  fn test_attach_file() {
+    use crate::db::models::AssetSettings;
+
+    let (db, temp_dir) = setup_test_db();
+    let service = AssetService::new(db, temp_dir.path().join("assets"));
+
+    // 拡張子ではなく中身で種類を判定する
+    let pdf = service
+      .attach_file(b"%PDF-1.7\n%test", "資料[最終].txt")
+      .unwrap();
+    assert_eq!(pdf.asset.mime_type, "application/pdf");
+    assert!(pdf.asset.file_name.ends_with(".pdf"));
+    assert_eq!(pdf.asset.original_name.as_deref(), Some("資料[最終].txt"));
+    assert_eq!(
+      pdf.markdown,
+      format!("[資料\\[最終\\].txt]({})", pdf.asset.link)
+    );
+
+    let image = service.attach_file(&png_header(), "shot").unwrap();
+    assert_eq!(image.asset.mime_type, "image/png");
+    assert_eq!(image.markdown, format!("![shot]({})", image.asset.link));
+
+    let notes = service.attach_file(b"memo", "notes.md").unwrap();
+    assert_eq!(notes.asset.mime_type, "text/markdown");
+    let unknown = service.attach_file(b"\x00\x01", "data").unwrap();
+    assert_eq!(unknown.asset.mime_type, "application/octet-stream");
+    assert!(unknown.asset.file_name.ends_with(".bin"));
+
+    // ノートでの出現順に並べる
+    let content = format!("{}\n{}\n{}", image.markdown, pdf.markdown, image.markdown);
+    let attachments: Vec<i64> = service
+      .get_attachments(&content)
+      .unwrap()
+      .into_iter()
+      .map(|asset| asset.id)
+      .collect();
+    assert_eq!(attachments, vec![image.asset.id, pdf.asset.id]);
+
+    // 上限を超えるファイルは保存しない
+    let settings = service.get_settings().unwrap();
+    service
+      .update_settings(AssetSettings {
+        max_file_size: 4,
+        ..settings.clone()
+      })
+      .unwrap();
+    assert!(service.attach_file(b"12345", "big.bin").is_err());
+    assert!(service.attach_file(b"1234", "small.bin").is_ok());
+    assert!(
+      service
+        .update_settings(AssetSettings {
+          max_file_size: 0,
+          ..settings
+        })
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn test_save_image_processing_and_thumbnails() {
+    use crate::db::models::AssetCleanupAction;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+
+    let (db, temp_dir) = setup_test_db();
+    let assets_dir = temp_dir.path().join("assets");
+    let service = AssetService::new(db.clone(), assets_dir.clone());
+    let settings = service.get_settings().unwrap();
+    service
+      .update_settings(AssetSettings {
+        process_images: true,
+        max_image_dimension: 50,
+        reencode_threshold: 0,
+        thumbnail_size: 16,
+        ..settings
+      })
+      .unwrap();
+
+    let png = images::encode(
+      &DynamicImage::ImageRgb8(RgbImage::new(200, 100)),
+      ImageFormat::Png,
+      85,
+    )
+    .unwrap();
+    let link = service.save_image(&png, "png").unwrap();
+    let file_name = link.trim_start_matches("assets/");
+    let asset = service.get_asset(file_name).unwrap();
+    assert_eq!(asset.mime_type, "image/webp");
+    assert_eq!((asset.width, asset.height), (Some(50), Some(25)));
+
+    // サムネイルは一度作るとキャッシュを使う
+    let (thumbnail, mime_type) = service.read_thumbnail(file_name, 16).unwrap();
+    assert_eq!(mime_type, "image/webp");
+    let cached = assets_dir
+      .join(".thumbnails")
+      .join(format!("{}-16.webp", asset.hash));
+    assert_eq!(fs::read(&cached).unwrap(), thumbnail);
+    assert_eq!(service.read_thumbnail(file_name, 16).unwrap().0, thumbnail);
+    assert!(service.read_thumbnail(file_name, 0).is_err());
+    assert!(
+      service
+        .read_thumbnail(file_name, MAX_THUMBNAIL_SIZE + 1)
+        .is_err()
+    );
+
+    // アセットを削除するとサムネイルも消える
+    age_assets(&db, &assets_dir);
+    service
+      .clean_up_unused_assets(
+        &temp_dir.path().join("notes"),
+        AssetCleanupAction::Delete,
+        false,
+      )
+      .unwrap();
+    assert!(!cached.exists());
+  }
 }
@@ -0,0 +1,84 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(test)]
+thread_local! {
+  static FAIL_WRITES: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// テスト用: このスレッドでの書き込みを失敗させる
+#[cfg(test)]
+pub fn set_fail_writes(fail: bool) {
+  FAIL_WRITES.with(|f| f.set(fail));
+}
+
+// 書き込み先と同じディレクトリの一時ファイル (rename が同じファイルシステム内で行われるように)
+fn temp_path(path: &Path) -> PathBuf {
+  let file_name = path
+    .file_name()
+    .map(|n| n.to_string_lossy().to_string())
+    .unwrap_or_default();
+  path.with_file_name(format!(
+    ".{}.{}-{}.tmp",
+    file_name,
+    std::process::id(),
+    TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+  ))
+}
+
+/// 一時ファイルに書き込んでから rename で置き換える
+///
+/// 途中でクラッシュしても、ファイルは元の内容か新しい内容のどちらかになる。
+pub fn write_file(path: &Path, content: &str) -> io::Result<()> {
+  write_bytes(path, content.as_bytes())
+}
+
+/// write_file のバイト列版 (画像などのファイルに使う)
+pub fn write_bytes(path: &Path, content: &[u8]) -> io::Result<()> {
+  let temp = temp_path(path);
+
+  let result = (|| {
+    let mut file = File::create(&temp)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+
+    #[cfg(test)]
+    if FAIL_WRITES.with(|f| f.get()) {
+      return Err(io::Error::other("書き込みの失敗 (テスト)"));
+    }
+
+    fs::rename(&temp, path)
+  })();
+
+  if result.is_err() {
+    let _ = fs::remove_file(&temp);
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_write_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("ノート.md");
+
+    write_file(&path, "最初").unwrap();
+    write_file(&path, "上書き").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "上書き");
+
+    // 失敗しても元の内容が残り、一時ファイルも残らない
+    set_fail_writes(true);
+    assert!(write_file(&path, "失敗").is_err());
+    set_fail_writes(false);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "上書き");
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+  }
+}
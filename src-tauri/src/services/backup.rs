@@ -1,4 +1,4 @@
-use crate::db::Database;
+use crate::db::{Database, LATEST_SCHEMA_VERSION, schema_version};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -12,6 +12,9 @@ pub struct BackupMetadata {
   pub created_at: String,
   pub notes_count: usize,
   pub folders_count: usize,
+  /// バックアップ作成時のスキーマバージョン (古いバックアップには含まれない)
+  #[serde(default)]
+  pub schema_version: Option<i64>,
 }
 
 pub struct BackupService {
@@ -58,12 +61,13 @@ impl BackupService {
     }
 
     // メタデータを作成
-    let (notes_count, folders_count) = self.get_backup_stats()?;
+    let (notes_count, folders_count, schema_version) = self.get_backup_stats()?;
     let metadata = BackupMetadata {
       version: env!("CARGO_PKG_VERSION").to_string(),
       created_at: chrono::Local::now().to_rfc3339(),
       notes_count,
       folders_count,
+      schema_version: Some(schema_version),
     };
 
     let metadata_json = serde_json::to_string_pretty(&metadata)
@@ -115,7 +119,7 @@ impl BackupService {
   }
 
   /// バックアップ統計を取得
-  fn get_backup_stats(&self) -> Result<(usize, usize), String> {
+  fn get_backup_stats(&self) -> Result<(usize, usize, i64), String> {
     let conn = self.db.conn.lock().unwrap();
 
     let notes_count: usize = conn
@@ -134,7 +138,10 @@ impl BackupService {
       )
       .map_err(|e| format!("Failed to count folders: {}", e))?;
 
-    Ok((notes_count, folders_count))
+    let schema_version =
+      schema_version(&conn).map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    Ok((notes_count, folders_count, schema_version))
   }
 
   /// バックアップから復元
@@ -145,6 +152,17 @@ impl BackupService {
       return Err("Backup file does not exist".to_string());
     }
 
+    // このバージョンのアプリが読めないスキーマのバックアップは復元しない
+    if let Ok(metadata) = self.read_backup_metadata(backup_path.to_string_lossy().to_string())
+      && let Some(version) = metadata.schema_version
+      && version > LATEST_SCHEMA_VERSION
+    {
+      return Err(format!(
+        "Backup schema version ({}) is newer than supported ({}). Please update the app",
+        version, LATEST_SCHEMA_VERSION
+      ));
+    }
+
     // 既存データのバックアップを作成
     let temp_backup_dir = self.base_path.parent().unwrap().join("temp_backup");
     fs::create_dir_all(&temp_backup_dir)
@@ -398,7 +416,7 @@ impl BackupService {
       .collect();
 
     // 新しい順にソート
-    backups.sort_by(|a, b| b.1.cmp(&a.1));
+    backups.sort_by_key(|b| std::cmp::Reverse(b.1));
 
     // max_backupsを超える古いバックアップを削除
     for (path, _) in backups.iter().skip(max_backups) {
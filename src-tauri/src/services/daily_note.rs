@@ -0,0 +1,366 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::db::Database;
+use crate::db::models::{
+  DailyNote, DailyNoteNeighbors, DailyNoteSettings, NoteWithContent, TemplateContext,
+};
+use crate::services::{FolderService, NoteService, TemplateService};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// pattern の YYYY・MM・DD を日付に置き換える
+pub fn format_pattern(pattern: &str, date: NaiveDate) -> String {
+  pattern
+    .replace("YYYY", &date.format("%Y").to_string())
+    .replace("MM", &date.format("%m").to_string())
+    .replace("DD", &date.format("%d").to_string())
+}
+
+// "a/b/c" を空の要素を除いて区切る
+fn segments(path: &str) -> Vec<String> {
+  path
+    .split('/')
+    .map(str::trim)
+    .filter(|segment| !segment.is_empty())
+    .map(str::to_string)
+    .collect()
+}
+
+// 日付ごとに別のノートになるよう、年・月・日をすべて含むパターンだけを受け付ける
+fn validate_settings(settings: &DailyNoteSettings) -> Result<(), String> {
+  if !["YYYY", "MM", "DD"]
+    .iter()
+    .all(|token| settings.pattern.contains(token))
+  {
+    return Err("パターンには YYYY・MM・DD をすべて含めてください".to_string());
+  }
+  if segments(&settings.pattern).is_empty() {
+    return Err("パターンが空です".to_string());
+  }
+  if segments(&settings.folder)
+    .iter()
+    .chain(segments(&settings.pattern).iter())
+    .any(|segment| segment == "." || segment == "..")
+  {
+    return Err(format!(
+      "使用できないパスです: {}/{}",
+      settings.folder, settings.pattern
+    ));
+  }
+  Ok(())
+}
+
+fn row_to_daily_note(row: &rusqlite::Row) -> rusqlite::Result<DailyNote> {
+  Ok(DailyNote {
+    date: row.get(0)?,
+    note_id: row.get(1)?,
+  })
+}
+
+pub struct DailyNoteService {
+  db: Arc<Database>,
+  base_path: PathBuf,
+}
+
+impl DailyNoteService {
+  pub fn new(db: Arc<Database>, base_path: PathBuf) -> Self {
+    Self { db, base_path }
+  }
+
+  fn read_settings(conn: &Connection) -> Result<DailyNoteSettings, String> {
+    conn
+      .query_row(
+        "SELECT folder, pattern, template_id FROM daily_note_settings WHERE id = 1",
+        [],
+        |row| {
+          Ok(DailyNoteSettings {
+            folder: row.get(0)?,
+            pattern: row.get(1)?,
+            template_id: row.get(2)?,
+          })
+        },
+      )
+      .map_err(|e| format!("デイリーノートの設定の取得に失敗しました: {}", e))
+  }
+
+  pub fn get_settings(&self) -> Result<DailyNoteSettings, String> {
+    let conn = self.db.conn.lock().unwrap();
+    Self::read_settings(&conn)
+  }
+
+  pub fn update_settings(&self, settings: DailyNoteSettings) -> Result<DailyNoteSettings, String> {
+    validate_settings(&settings)?;
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .execute(
+        "UPDATE daily_note_settings SET folder = ?, pattern = ?, template_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
+        params![
+          segments(&settings.folder).join("/"),
+          settings.pattern.trim(),
+          settings.template_id
+        ],
+      )
+      .map_err(|e| format!("デイリーノートの設定の更新に失敗しました: {}", e))?;
+    Self::read_settings(&conn)
+  }
+
+  // ゴミ箱にないデイリーノート
+  fn find_daily_note(conn: &Connection, date: &str) -> Result<Option<i64>, String> {
+    conn
+      .query_row(
+        "SELECT d.note_id FROM daily_notes d JOIN notes n ON n.id = d.note_id
+         WHERE d.date = ? AND n.is_deleted = FALSE",
+        params![date],
+        |row| row.get(0),
+      )
+      .optional()
+      .map_err(|e| format!("デイリーノートの取得に失敗しました: {}", e))
+  }
+
+  // フォルダの階層を名前でたどり、なければ作成する
+  fn ensure_folders(&self, names: &[String]) -> Result<Option<(i64, String)>, String> {
+    let folders = FolderService::new(self.db.clone(), self.base_path.clone());
+    let mut parent: Option<(i64, String)> = None;
+    for name in names {
+      let existing = {
+        let conn = self.db.conn.lock().unwrap();
+        conn
+          .query_row(
+            "SELECT id, folder_path FROM folders WHERE name = ? AND parent_id IS ? AND is_deleted = FALSE",
+            params![name, parent.as_ref().map(|(id, _)| *id)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+          )
+          .optional()
+          .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?
+      };
+      parent = Some(match existing {
+        Some(folder) => folder,
+        None => {
+          let (parent_id, parent_path) = match parent {
+            Some((id, path)) => (Some(id), Some(path)),
+            None => (None, None),
+          };
+          let folder = folders.create_folder(name.clone(), parent_id, parent_path)?;
+          (folder.id, folder.folder_path)
+        }
+      });
+    }
+    Ok(parent)
+  }
+
+  /// 日付のデイリーノートを開く (なければ設定のフォルダとテンプレートで作成する)
+  pub fn open_or_create_daily_note(&self, date: NaiveDate) -> Result<NoteWithContent, String> {
+    let key = date.format(DATE_FORMAT).to_string();
+    let notes = NoteService::new(self.db.clone(), self.base_path.clone());
+
+    let settings = {
+      let conn = self.db.conn.lock().unwrap();
+      if let Some(note_id) = Self::find_daily_note(&conn, &key)? {
+        drop(conn);
+        return notes.get_note_by_id(note_id);
+      }
+      Self::read_settings(&conn)?
+    };
+
+    let mut path = segments(&settings.folder);
+    path.extend(segments(&format_pattern(&settings.pattern, date)));
+    let Some(title) = path.pop() else {
+      return Err("パターンが空です".to_string());
+    };
+    let folder = self.ensure_folders(&path)?;
+    let parent_id = folder.as_ref().map(|(id, _)| *id);
+
+    // 同じ場所に同じタイトルのノートがあればそれをデイリーノートにする
+    let existing: Option<i64> = {
+      let conn = self.db.conn.lock().unwrap();
+      conn
+        .query_row(
+          "SELECT id FROM notes WHERE title = ? AND parent_id IS ? AND is_deleted = FALSE",
+          params![title, parent_id],
+          |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
+    };
+    let note = match existing {
+      Some(id) => notes.get_note_by_id(id)?,
+      None => {
+        let content = match settings.template_id {
+          Some(template_id) => {
+            TemplateService::new(self.db.clone())
+              .render_template(
+                template_id,
+                &TemplateContext {
+                  title: Some(title.clone()),
+                  folder: path.last().cloned(),
+                  date: Some(key.clone()),
+                  prompts: Default::default(),
+                },
+              )?
+              .content
+          }
+          None => String::new(),
+        };
+        notes.create_note(title, content, parent_id, folder.map(|(_, path)| path))?
+      }
+    };
+
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .execute(
+        "INSERT OR REPLACE INTO daily_notes (date, note_id) VALUES (?, ?)",
+        params![key, note.id],
+      )
+      .map_err(|e| format!("デイリーノートの登録に失敗しました: {}", e))?;
+    Ok(note)
+  }
+
+  /// date より前で最も近い日と、後で最も近い日のデイリーノート
+  pub fn get_neighbors(&self, date: NaiveDate) -> Result<DailyNoteNeighbors, String> {
+    let key = date.format(DATE_FORMAT).to_string();
+    let conn = self.db.conn.lock().unwrap();
+    let find = |sql: &str| {
+      conn
+        .query_row(sql, params![key], row_to_daily_note)
+        .optional()
+        .map_err(|e| format!("デイリーノートの取得に失敗しました: {}", e))
+    };
+    Ok(DailyNoteNeighbors {
+      previous: find(
+        "SELECT d.date, d.note_id FROM daily_notes d JOIN notes n ON n.id = d.note_id
+         WHERE d.date < ? AND n.is_deleted = FALSE ORDER BY d.date DESC LIMIT 1",
+      )?,
+      next: find(
+        "SELECT d.date, d.note_id FROM daily_notes d JOIN notes n ON n.id = d.note_id
+         WHERE d.date > ? AND n.is_deleted = FALSE ORDER BY d.date ASC LIMIT 1",
+      )?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::db::models::CreateTemplateInput;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap()
+  }
+
+  #[test]
+  fn test_open_or_create_daily_note() {
+    let (db, temp_dir) = setup_test_db();
+    let notes_dir = temp_dir.path().join("notes");
+    let service = DailyNoteService::new(db.clone(), notes_dir.clone());
+
+    let template = TemplateService::new(db.clone())
+      .create_template(CreateTemplateInput {
+        name: "日記".to_string(),
+        content: "# {{title}} ({{weekday}})\n## 今日やったこと\n".to_string(),
+        description: None,
+      })
+      .unwrap();
+    service
+      .update_settings(DailyNoteSettings {
+        folder: " 日記/ ".to_string(),
+        pattern: "YYYY/MM/YYYY-MM-DD".to_string(),
+        template_id: Some(template.id),
+      })
+      .unwrap();
+
+    let note = service
+      .open_or_create_daily_note(date("2024-03-05"))
+      .unwrap();
+    assert_eq!(note.title, "2024-03-05");
+    assert_eq!(note.content, "# 2024-03-05 (火曜日)\n## 今日やったこと\n");
+    assert!(
+      PathBuf::from(&note.file_path).starts_with(notes_dir.join("日記").join("2024").join("03"))
+    );
+
+    // 2 回目は同じノートを開き、フォルダも作り直さない
+    let again = service
+      .open_or_create_daily_note(date("2024-03-05"))
+      .unwrap();
+    assert_eq!(again.id, note.id);
+    let other = service
+      .open_or_create_daily_note(date("2024-03-20"))
+      .unwrap();
+    assert_eq!(other.parent_id, note.parent_id);
+
+    let folder_count: i64 = {
+      let conn = db.conn.lock().unwrap();
+      conn
+        .query_row("SELECT COUNT(*) FROM folders", [], |row| row.get(0))
+        .unwrap()
+    };
+    assert_eq!(folder_count, 3);
+
+    // 年・月・日のどれかが欠けたパターンは受け付けない
+    assert!(
+      service
+        .update_settings(DailyNoteSettings {
+          folder: String::new(),
+          pattern: "YYYY-MM".to_string(),
+          template_id: None,
+        })
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn test_daily_note_neighbors() {
+    let (db, temp_dir) = setup_test_db();
+    let notes_dir = temp_dir.path().join("notes");
+    let service = DailyNoteService::new(db.clone(), notes_dir.clone());
+
+    let first = service
+      .open_or_create_daily_note(date("2024-01-01"))
+      .unwrap();
+    let second = service
+      .open_or_create_daily_note(date("2024-01-10"))
+      .unwrap();
+    let third = service
+      .open_or_create_daily_note(date("2024-02-01"))
+      .unwrap();
+
+    let neighbors = service.get_neighbors(date("2024-01-10")).unwrap();
+    assert_eq!(neighbors.previous.unwrap().note_id, first.id);
+    assert_eq!(neighbors.next.unwrap().note_id, third.id);
+
+    // デイリーノートがない日からも前後に移動できる
+    let neighbors = service.get_neighbors(date("2024-01-05")).unwrap();
+    assert_eq!(neighbors.previous.unwrap().date, "2024-01-01");
+    assert_eq!(neighbors.next.unwrap().note_id, second.id);
+
+    // ゴミ箱のノートは飛ばす
+    NoteService::new(db.clone(), notes_dir)
+      .delete_note(second.id)
+      .unwrap();
+    let neighbors = service.get_neighbors(date("2024-01-05")).unwrap();
+    assert_eq!(neighbors.next.unwrap().note_id, third.id);
+    assert!(
+      service
+        .get_neighbors(date("2024-02-01"))
+        .unwrap()
+        .next
+        .is_none()
+    );
+  }
+}
@@ -0,0 +1,524 @@
+use crate::db::Database;
+use crate::db::models::{VaultIssue, VaultReport};
+use crate::services::{NoteService, fts};
+use rusqlite::{Connection, Result as SqlResult, params};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// データベースとノートディレクトリの整合性をチェックし、必要なら修復する
+pub struct VaultDoctor {
+  db: Arc<Database>,
+  base_path: PathBuf,
+}
+
+impl VaultDoctor {
+  pub fn new(db: Arc<Database>, base_path: PathBuf) -> Self {
+    VaultDoctor { db, base_path }
+  }
+
+  fn trash_path(&self, path: &Path) -> PathBuf {
+    self
+      .base_path
+      .join(".trash")
+      .join(path.strip_prefix(&self.base_path).unwrap_or(path))
+  }
+
+  // `.trash` などの隠しディレクトリを除いてノートディレクトリを走査
+  fn scan_dir(dir: &Path, folders: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+      return;
+    };
+
+    for entry in entries.flatten() {
+      if entry.file_name().to_string_lossy().starts_with('.') {
+        continue;
+      }
+      let path = entry.path();
+      if path.is_dir() {
+        folders.push(path.clone());
+        Self::scan_dir(&path, folders, files);
+      } else if path.extension().is_some_and(|ext| ext == "md") {
+        files.push(path);
+      }
+    }
+  }
+
+  /// 整合性をチェックする
+  ///
+  /// `repair` が true の場合は見つかった問題を1つのトランザクションで修復する。
+  /// レポートには修復前に見つかった問題が含まれる。
+  pub fn check(&self, repair: bool) -> Result<VaultReport, String> {
+    let conn = self.db.conn.lock().unwrap();
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+
+    let mut issues = Vec::new();
+    self
+      .check_folder_paths(&tx, repair, &mut issues)
+      .map_err(|e| format!("フォルダのチェックに失敗しました: {}", e))?;
+    self
+      .check_note_files(&tx, repair, &mut issues)
+      .map_err(|e| format!("ノートのチェックに失敗しました: {}", e))?;
+    self
+      .check_orphans(&tx, repair, &mut issues)
+      .map_err(|e| format!("未登録ファイルのチェックに失敗しました: {}", e))?;
+    self
+      .check_fts(&tx, repair, &mut issues)
+      .map_err(|e| format!("検索インデックスのチェックに失敗しました: {}", e))?;
+
+    if repair {
+      tx.commit()
+        .map_err(|e| format!("修復のコミットに失敗しました: {}", e))?;
+    }
+
+    Ok(VaultReport {
+      issues,
+      repaired: repair,
+    })
+  }
+
+  // folder_path が親フォルダのパスと一致するか
+  fn check_folder_paths(
+    &self,
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<VaultIssue>,
+  ) -> SqlResult<()> {
+    let folders: HashMap<i64, (String, Option<i64>, String, bool)> = {
+      let mut stmt = conn.prepare(
+        "SELECT id, name, parent_id, COALESCE(folder_path, ''), is_deleted FROM folders",
+      )?;
+      stmt
+        .query_map([], |row| {
+          Ok((
+            row.get(0)?,
+            (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?),
+          ))
+        })?
+        .collect::<SqlResult<_>>()?
+    };
+
+    let expected_path = |id: i64| {
+      let mut names = Vec::new();
+      let mut current = Some(id);
+      while let Some((name, parent_id, folder_path, _)) = current.and_then(|c| folders.get(&c)) {
+        // ディレクトリ名は表示名から作られ、衝突時は番号が付くため folder_path の末尾を使う
+        let dir_name = Path::new(folder_path)
+          .file_name()
+          .and_then(|n| n.to_str())
+          .unwrap_or(name);
+        names.push(dir_name);
+        current = *parent_id;
+        // 親子関係が循環している場合
+        if names.len() > folders.len() {
+          break;
+        }
+      }
+      names
+        .iter()
+        .rev()
+        .fold(self.base_path.clone(), |path, name| path.join(name))
+    };
+
+    // 親フォルダから順に確認する
+    let mut ids: Vec<(usize, i64)> = folders
+      .iter()
+      .filter(|(_, (_, _, _, is_deleted))| !is_deleted)
+      .map(|(id, _)| (expected_path(*id).components().count(), *id))
+      .collect();
+    ids.sort();
+
+    for (_, id) in ids {
+      let folder_path = folders[&id].2.clone();
+      let expected = expected_path(id);
+      let expected_str = expected.to_str().unwrap_or_default().to_string();
+      if folder_path == expected_str {
+        continue;
+      }
+
+      issues.push(VaultIssue::FolderPathMismatch {
+        folder_id: id,
+        folder_path: folder_path.clone(),
+        expected_path: expected_str.clone(),
+      });
+
+      // 正しい場所にフォルダがある場合のみ修復する
+      if repair && expected.is_dir() && !Path::new(&folder_path).is_dir() {
+        conn.execute(
+          "UPDATE folders SET folder_path = ? WHERE id = ?",
+          params![expected_str, id],
+        )?;
+        conn.execute(
+          "UPDATE notes SET file_path = ? || SUBSTR(file_path, ?) WHERE file_path LIKE ?",
+          params![
+            expected_str,
+            folder_path.chars().count() as i64 + 1,
+            format!("{}{}%", folder_path, std::path::MAIN_SEPARATOR)
+          ],
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+
+  // ノートのファイルが is_deleted に応じた場所にあるか
+  fn check_note_files(
+    &self,
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<VaultIssue>,
+  ) -> SqlResult<()> {
+    let notes: Vec<(i64, String, bool)> = {
+      let mut stmt = conn.prepare("SELECT id, COALESCE(file_path, ''), is_deleted FROM notes")?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<SqlResult<_>>()?
+    };
+
+    for (id, file_path, is_deleted) in notes {
+      let path = PathBuf::from(&file_path);
+      let in_place = path.is_file();
+      let in_trash = self.trash_path(&path).is_file();
+
+      match (is_deleted, in_place, in_trash) {
+        // 削除済みのノートは trash から消えていても完全に削除できるので問題にしない
+        (false, true, _) | (true, _, true) | (true, false, false) => {}
+        (false, false, true) | (true, true, false) => {
+          issues.push(VaultIssue::TrashMismatch {
+            note_id: id,
+            file_path,
+            is_deleted,
+          });
+          if repair {
+            if in_trash {
+              conn.execute(
+                "UPDATE notes SET is_deleted = TRUE, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
+                params![id],
+              )?;
+            } else {
+              conn.execute(
+                "UPDATE notes SET is_deleted = FALSE, deleted_at = NULL WHERE id = ?",
+                params![id],
+              )?;
+            }
+          }
+        }
+        _ => {
+          issues.push(VaultIssue::MissingFile {
+            note_id: id,
+            file_path,
+          });
+          // ファイルが失われたノートは trash に入れ、ユーザーが完全に削除できるようにする
+          if repair {
+            conn.execute(
+              "UPDATE notes SET is_deleted = TRUE, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
+              params![id],
+            )?;
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // データベースに登録されていないフォルダとファイル
+  fn check_orphans(
+    &self,
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<VaultIssue>,
+  ) -> SqlResult<()> {
+    let mut disk_folders = Vec::new();
+    let mut disk_files = Vec::new();
+    Self::scan_dir(&self.base_path, &mut disk_folders, &mut disk_files);
+    disk_folders.sort_by_key(|path| path.components().count());
+    disk_files.sort();
+
+    let mut folder_ids: HashMap<PathBuf, i64> = {
+      let mut stmt = conn.prepare(
+        "SELECT folder_path, id FROM folders WHERE is_deleted = FALSE AND folder_path IS NOT NULL",
+      )?;
+      stmt
+        .query_map([], |row| {
+          Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+        })?
+        .collect::<SqlResult<_>>()?
+    };
+    let note_paths: HashSet<PathBuf> = {
+      let mut stmt = conn.prepare(
+        "SELECT file_path FROM notes WHERE is_deleted = FALSE AND file_path IS NOT NULL",
+      )?;
+      stmt
+        .query_map([], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+        .collect::<SqlResult<_>>()?
+    };
+
+    for path in disk_folders {
+      if folder_ids.contains_key(&path) {
+        continue;
+      }
+      issues.push(VaultIssue::OrphanFolder {
+        folder_path: path.to_str().unwrap_or_default().to_string(),
+      });
+      if repair {
+        let name = path
+          .file_name()
+          .map(|n| n.to_string_lossy().to_string())
+          .unwrap_or_default();
+        let parent_id = path.parent().and_then(|p| folder_ids.get(p)).copied();
+        conn.execute(
+          "INSERT INTO folders (name, folder_path, parent_id) VALUES (?, ?, ?)",
+          params![name, path.to_str().unwrap_or_default(), parent_id],
+        )?;
+        folder_ids.insert(path, conn.last_insert_rowid());
+      }
+    }
+
+    for path in disk_files {
+      if note_paths.contains(&path) {
+        continue;
+      }
+      issues.push(VaultIssue::OrphanFile {
+        file_path: path.to_str().unwrap_or_default().to_string(),
+      });
+      if repair && let Ok(content) = fs::read_to_string(&path) {
+        let parent_id = path.parent().and_then(|p| folder_ids.get(p)).copied();
+        NoteService::register_existing_file(conn, &self.base_path, &path, parent_id, &content)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  // 検索インデックスが削除されていないノートと一致しているか
+  fn check_fts(
+    &self,
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<VaultIssue>,
+  ) -> SqlResult<()> {
+    let notes: Vec<(i64, String, String)> = {
+      let mut stmt = conn.prepare(
+        "SELECT id, title, COALESCE(file_path, '') FROM notes WHERE is_deleted = FALSE ORDER BY id",
+      )?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<SqlResult<_>>()?
+    };
+    let indexed: HashMap<i64, (String, String)> = {
+      let mut stmt = conn.prepare("SELECT id, title, content FROM notes_fts")?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+        .collect::<SqlResult<_>>()?
+    };
+
+    let mut contents = Vec::new();
+    let mut found = false;
+    for (id, title, file_path) in &notes {
+      let content = fs::read_to_string(file_path).ok();
+      match (indexed.get(id), &content) {
+        (None, _) => {
+          issues.push(VaultIssue::FtsMissing { note_id: *id });
+          found = true;
+        }
+        (Some((indexed_title, indexed_content)), Some(content))
+          if indexed_title != title || indexed_content != content =>
+        {
+          issues.push(VaultIssue::FtsOutdated { note_id: *id });
+          found = true;
+        }
+        _ => {}
+      }
+      if let Some(content) = content {
+        contents.push((*id, title, content));
+      }
+    }
+
+    let live_ids: HashSet<i64> = notes.iter().map(|(id, _, _)| *id).collect();
+    let mut orphan_ids: Vec<i64> = indexed
+      .keys()
+      .filter(|id| !live_ids.contains(id))
+      .copied()
+      .collect();
+    orphan_ids.sort();
+    for id in orphan_ids {
+      issues.push(VaultIssue::FtsOrphan { note_id: id });
+      found = true;
+    }
+
+    // 問題があればインデックスを作り直す
+    if repair && found {
+      fts::clear(conn)?;
+      for (id, title, content) in contents {
+        fts::insert(conn, id, title, &content)?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::services::FolderService;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_check_healthy_vault() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let folder_service = FolderService::new(db.clone(), base_path.clone());
+
+    let folder = folder_service
+      .create_folder("フォルダ".to_string(), None, None)
+      .unwrap();
+    note_service
+      .create_note(
+        "ノート".to_string(),
+        "内容".to_string(),
+        Some(folder.id),
+        Some(folder.folder_path),
+      )
+      .unwrap();
+    let deleted = note_service
+      .create_note("削除済み".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    note_service.delete_note(deleted.id).unwrap();
+
+    let doctor = VaultDoctor::new(db.clone(), base_path);
+    let report = doctor.check(false).unwrap();
+    assert_eq!(report.issues, vec![]);
+  }
+
+  #[test]
+  fn test_check_and_repair() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let folder_service = FolderService::new(db.clone(), base_path.clone());
+    let doctor = VaultDoctor::new(db.clone(), base_path.clone());
+
+    let missing = note_service
+      .create_note("消えた".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    fs::remove_file(&missing.file_path).unwrap();
+
+    let drifted = note_service
+      .create_note("外部編集".to_string(), "古い内容".to_string(), None, None)
+      .unwrap();
+    fs::write(&drifted.file_path, "新しい内容").unwrap();
+
+    let trashed = note_service
+      .create_note("ゴミ箱".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    note_service.delete_note(trashed.id).unwrap();
+    {
+      // trash にあるのに削除済みになっていない
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute(
+          "UPDATE notes SET is_deleted = FALSE WHERE id = ?",
+          params![trashed.id],
+        )
+        .unwrap();
+    }
+
+    let parent = folder_service
+      .create_folder("親".to_string(), None, None)
+      .unwrap();
+    let folder = folder_service
+      .create_folder("子".to_string(), Some(parent.id), Some(parent.folder_path))
+      .unwrap();
+    {
+      // 親フォルダの名前変更が途中で失敗した状態
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute(
+          "UPDATE folders SET folder_path = ? WHERE id = ?",
+          params![
+            base_path.join("古い親").join("子").to_str().unwrap(),
+            folder.id
+          ],
+        )
+        .unwrap();
+    }
+
+    fs::create_dir_all(base_path.join("未登録")).unwrap();
+    fs::write(base_path.join("未登録").join("孤立.md"), "[[外部編集]]").unwrap();
+
+    let report = doctor.check(false).unwrap();
+    assert!(!report.repaired);
+    assert!(report.issues.contains(&VaultIssue::MissingFile {
+      note_id: missing.id,
+      file_path: missing.file_path.clone(),
+    }));
+    assert!(report.issues.contains(&VaultIssue::FtsOutdated {
+      note_id: drifted.id
+    }));
+    assert!(report.issues.contains(&VaultIssue::TrashMismatch {
+      note_id: trashed.id,
+      file_path: trashed.file_path.clone(),
+      is_deleted: false,
+    }));
+    assert!(report.issues.iter().any(|issue| matches!(
+      issue,
+      VaultIssue::FolderPathMismatch { folder_id, .. } if *folder_id == folder.id
+    )));
+    assert!(
+      report
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, VaultIssue::OrphanFolder { .. }))
+    );
+    assert!(
+      report
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, VaultIssue::OrphanFile { .. }))
+    );
+
+    // チェックだけではデータベースは変わらない
+    assert_eq!(doctor.check(false).unwrap().issues, report.issues);
+
+    let report = doctor.check(true).unwrap();
+    assert!(report.repaired);
+    assert_eq!(doctor.check(false).unwrap().issues, vec![]);
+
+    assert_eq!(note_service.search_notes("新しい内容").unwrap().len(), 1);
+    let deleted_ids: Vec<i64> = note_service
+      .get_deleted_notes()
+      .unwrap()
+      .iter()
+      .map(|n| n.id)
+      .collect();
+    assert!(deleted_ids.contains(&missing.id));
+    assert!(deleted_ids.contains(&trashed.id));
+
+    let imported = note_service
+      .get_all_notes()
+      .unwrap()
+      .into_iter()
+      .find(|n| n.title == "孤立")
+      .unwrap();
+    assert!(imported.parent_id.is_some());
+  }
+}
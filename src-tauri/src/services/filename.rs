@@ -0,0 +1,190 @@
+use std::path::{Component, Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// ファイル名に使えない文字 (いずれかの OS で予約されているもの)
+const RESERVED_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Windows で予約されているファイル名
+const RESERVED_NAMES: [&str; 22] = [
+  "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+  "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// ファイル名の最大バイト数 (多くのファイルシステムの上限 255 バイトから拡張子と連番の分を引いたもの)
+const MAX_FILE_NAME_BYTES: usize = 200;
+
+/// 名前が空になった場合のファイル名
+const UNTITLED: &str = "無題";
+
+/// 表示用のタイトルを正規化する
+///
+/// macOS のファイルシステムは濁点などを分解した形 (NFD) で返すため、NFC に揃えて
+/// 日本語のタイトルがファイル名と往復しても変わらないようにする。
+pub fn normalize_title(title: &str) -> String {
+  title
+    .nfc()
+    .filter(|c| !c.is_control())
+    .collect::<String>()
+    .trim()
+    .to_string()
+}
+
+/// タイトルやフォルダ名からディスク上で安全に使える名前 (拡張子なし) を作る
+pub fn sanitize_file_name(name: &str) -> String {
+  let replaced: String = normalize_title(name)
+    .chars()
+    .map(|c| if RESERVED_CHARS.contains(&c) { '_' } else { c })
+    .collect();
+
+  // 先頭と末尾のドット・空白は `..` や隠しファイル、Windows で扱えない名前になるため取り除く
+  let mut sanitized = replaced
+    .trim_matches(|c: char| c == '.' || c.is_whitespace())
+    .to_string();
+
+  if sanitized.len() > MAX_FILE_NAME_BYTES {
+    let mut end = MAX_FILE_NAME_BYTES;
+    while !sanitized.is_char_boundary(end) {
+      end -= 1;
+    }
+    sanitized.truncate(end);
+    sanitized = sanitized
+      .trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+      .to_string();
+  }
+
+  if sanitized.is_empty() {
+    return UNTITLED.to_string();
+  }
+
+  // Windows は拡張子や末尾の空白があってもデバイス名として扱うので、名前の直後に _ を付ける
+  let stem = sanitized
+    .split('.')
+    .next()
+    .unwrap_or_default()
+    .trim_end();
+  if RESERVED_NAMES.contains(&stem.to_ascii_uppercase().as_str()) {
+    sanitized.insert(stem.len(), '_');
+  }
+
+  sanitized
+}
+
+/// dir 内で使われていないパスを返す
+///
+/// `is_taken` が true を返す間は「名前 (2)」「名前 (3)」のように番号を付けて試す。
+pub fn unique_path<F>(dir: &Path, name: &str, extension: Option<&str>, is_taken: F) -> PathBuf
+where
+  F: Fn(&Path) -> bool,
+{
+  let file_name = |number: Option<u32>| {
+    let stem = match number {
+      Some(n) => format!("{} ({})", name, n),
+      None => name.to_string(),
+    };
+    match extension {
+      Some(ext) => format!("{}.{}", stem, ext),
+      None => stem,
+    }
+  };
+
+  let mut path = dir.join(file_name(None));
+  let mut number = 2;
+  while is_taken(&path) {
+    path = dir.join(file_name(Some(number)));
+    number += 1;
+  }
+  path
+}
+
+/// フロントエンドから渡されたパスを base_path 内のパスとして解決する
+///
+/// 絶対パスは base_path 内を指している場合のみ受け付け、`..` を含むパスは拒否する。
+pub fn resolve_within(base_path: &Path, path: &str) -> Result<PathBuf, String> {
+  let joined = base_path.join(path);
+  let is_inside = joined
+    .strip_prefix(base_path)
+    .map(|relative| {
+      relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    })
+    .unwrap_or(false);
+
+  if is_inside {
+    Ok(joined)
+  } else {
+    Err(format!(
+      "ノートディレクトリの外のパスは使用できません: {}",
+      path
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sanitize_file_name() {
+    assert_eq!(sanitize_file_name("会議/メモ"), "会議_メモ");
+    assert_eq!(sanitize_file_name("../../etc/passwd"), "_.._etc_passwd");
+    assert_eq!(
+      sanitize_file_name("a:b*c?d\"e<f>g|h\\i"),
+      "a_b_c_d_e_f_g_h_i"
+    );
+    assert_eq!(sanitize_file_name(".."), "無題");
+    assert_eq!(sanitize_file_name("  "), "無題");
+    assert_eq!(sanitize_file_name("con"), "con_");
+    assert_eq!(sanitize_file_name("nul"), "nul_");
+    assert_eq!(sanitize_file_name("Com1.txt"), "Com1_.txt");
+    assert_eq!(sanitize_file_name("aux .md"), "aux_ .md");
+    assert_eq!(sanitize_file_name(" .隠し. "), "隠し");
+
+    let long = "あ".repeat(100);
+    let sanitized = sanitize_file_name(&long);
+    assert!(sanitized.len() <= MAX_FILE_NAME_BYTES);
+    assert!(sanitized.chars().all(|c| c == 'あ'));
+  }
+
+  #[test]
+  fn test_normalize_title() {
+    // NFD の「が」(か + 濁点) は NFC の「が」になる
+    let decomposed = "か\u{3099}いき\u{3099}";
+    assert_eq!(normalize_title(decomposed), "がいぎ");
+    assert_eq!(normalize_title(" タイトル\n"), "タイトル");
+  }
+
+  #[test]
+  fn test_unique_path() {
+    let dir = Path::new("/notes");
+    let taken = [
+      PathBuf::from("/notes/メモ.md"),
+      PathBuf::from("/notes/メモ (2).md"),
+    ];
+    let path = unique_path(dir, "メモ", Some("md"), |p| taken.iter().any(|t| t == p));
+    assert_eq!(path, PathBuf::from("/notes/メモ (3).md"));
+
+    let path = unique_path(dir, "フォルダ", None, |_| false);
+    assert_eq!(path, PathBuf::from("/notes/フォルダ"));
+  }
+
+  #[test]
+  fn test_resolve_within() {
+    let base = Path::new("/data/notes");
+    assert_eq!(
+      resolve_within(base, "仕事/会議").unwrap(),
+      PathBuf::from("/data/notes/仕事/会議")
+    );
+    assert_eq!(
+      resolve_within(base, "/data/notes/仕事").unwrap(),
+      PathBuf::from("/data/notes/仕事")
+    );
+    assert_eq!(
+      resolve_within(base, "").unwrap(),
+      PathBuf::from("/data/notes")
+    );
+    assert!(resolve_within(base, "../outside").is_err());
+    assert!(resolve_within(base, "仕事/../../outside").is_err());
+    assert!(resolve_within(base, "/etc").is_err());
+  }
+}
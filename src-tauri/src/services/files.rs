@@ -1,8 +1,14 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use rusqlite::{Connection, params};
+
 use crate::db::Database;
-use crate::db::models::{FileItem, FolderWithChildren};
+use crate::db::models::{
+  FileItem, FolderWithChildren, SavedSearchWithChildren, SortBy, SortOrder, SortSettings,
+};
+use crate::services::{SavedSearchService, fts};
 
 pub struct FileService {
   db: Arc<Database>,
@@ -14,64 +20,117 @@ impl FileService {
   }
 
   pub fn get_all_files_hierarchical(&self) -> Result<Vec<FileItem>, String> {
+    let mut result = self.saved_search_items()?;
+    result.extend(self.get_folder_tree()?);
+    Ok(result)
+  }
+
+  // 保存済み検索を仮想フォルダとして返す (子は検索結果のノート)
+  fn saved_search_items(&self) -> Result<Vec<FileItem>, String> {
+    let service = SavedSearchService::new(self.db.clone());
+    let items = service
+      .get_saved_searches()?
+      .into_iter()
+      .map(|saved| {
+        // 検索に失敗しても (クエリの構文が後から変わった場合など) ツリー全体は表示する
+        let children = service
+          .run_saved_search(saved.id)
+          .map(|hits| {
+            hits
+              .into_iter()
+              .map(|hit| FileItem::Note(hit.note))
+              .collect()
+          })
+          .unwrap_or_default();
+        FileItem::SavedSearch(SavedSearchWithChildren {
+          id: saved.id,
+          name: saved.name,
+          query: saved.query,
+          icon: saved.icon,
+          sort: saved.sort,
+          children,
+        })
+      })
+      .collect();
+    Ok(items)
+  }
+
+  fn get_folder_tree(&self) -> Result<Vec<FileItem>, String> {
     let conn = self.db.conn.lock().unwrap();
+    let default_sort = Self::read_default_sort(&conn)?;
+
+    // 手動並び替えの位置は FileItem に含めないため別に持つ
+    let mut positions: HashMap<(bool, i64), i64> = HashMap::new();
 
     let mut folder_stmt = conn
-      .prepare("SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE is_deleted = FALSE")
+      .prepare("SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id, position FROM folders WHERE is_deleted = FALSE")
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
     let folders = folder_stmt
       .query_map([], |row| {
-        Ok(FolderWithChildren {
-          id: row.get(0)?,
-          name: row.get(1)?,
-          created_at: row.get(2)?,
-          updated_at: row.get(3)?,
-          parent_id: row.get(4)?,
-          folder_path: row.get(5)?,
-          children: Vec::new(),
-          is_deleted: row.get(6)?,
-          deleted_at: row.get(7)?,
-          icon: row.get(8)?,
-          color: row.get(9)?,
-          sort_by: row.get(10)?,
-          sort_order: row.get(11)?,
-        })
+        Ok((
+          FolderWithChildren {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+            parent_id: row.get(4)?,
+            folder_path: row.get(5)?,
+            children: Vec::new(),
+            is_deleted: row.get(6)?,
+            deleted_at: row.get(7)?,
+            icon: row.get(8)?,
+            color: row.get(9)?,
+            sort_by: row.get(10)?,
+            sort_order: row.get(11)?,
+            default_template_id: row.get(12)?,
+          },
+          row.get::<_, Option<i64>>(13)?,
+        ))
       })
       .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?
-      .collect::<Result<Vec<FolderWithChildren>, rusqlite::Error>>()
+      .collect::<Result<Vec<(FolderWithChildren, Option<i64>)>, rusqlite::Error>>()
       .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?;
 
     let mut note_stmt = conn
-      .prepare("SELECT id, title, created_at, updated_at, parent_id, file_path, preview, is_deleted, deleted_at, is_favorite, favorite_order FROM notes WHERE is_deleted = FALSE ORDER BY updated_at DESC")
+      .prepare("SELECT id, title, created_at, updated_at, parent_id, file_path, preview, is_deleted, deleted_at, is_favorite, favorite_order, position FROM notes WHERE is_deleted = FALSE")
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
     let notes = note_stmt
       .query_map([], |row| {
-        Ok(crate::db::models::Note {
-          id: row.get(0)?,
-          title: row.get(1)?,
-          created_at: row.get(2)?,
-          updated_at: row.get(3)?,
-          parent_id: row.get(4)?,
-          file_path: row.get(5)?,
-          preview: row.get(6)?,
-          is_deleted: row.get(7)?,
-          deleted_at: row.get(8)?,
-          is_favorite: row.get(9)?,
-          favorite_order: row.get(10)?,
-        })
+        Ok((
+          crate::db::models::Note {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+            parent_id: row.get(4)?,
+            file_path: row.get(5)?,
+            preview: row.get(6)?,
+            is_deleted: row.get(7)?,
+            deleted_at: row.get(8)?,
+            is_favorite: row.get(9)?,
+            favorite_order: row.get(10)?,
+          },
+          row.get::<_, Option<i64>>(11)?,
+        ))
       })
       .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
-      .collect::<Result<Vec<crate::db::models::Note>, rusqlite::Error>>()
+      .collect::<Result<Vec<(crate::db::models::Note, Option<i64>)>, rusqlite::Error>>()
       .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?;
 
     let mut items_map: HashMap<Option<i64>, Vec<FileItem>> = HashMap::new();
-    for folder in folders {
+    for (folder, position) in folders {
+      if let Some(position) = position {
+        positions.insert((true, folder.id), position);
+      }
       items_map
         .entry(folder.parent_id)
         .or_default()
         .push(FileItem::Folder(folder));
     }
-    for note in notes {
+    for (note, position) in notes {
+      if let Some(position) = position {
+        positions.insert((false, note.id), position);
+      }
       items_map
         .entry(note.parent_id)
         .or_default()
@@ -80,12 +139,21 @@ impl FileService {
 
     fn build_tree(
       items_map: &mut HashMap<Option<i64>, Vec<FileItem>>,
+      positions: &HashMap<(bool, i64), i64>,
       parent_id: Option<i64>,
+      sort: SortSettings,
     ) -> Vec<FileItem> {
       if let Some(mut children) = items_map.remove(&parent_id) {
+        sort_items(&mut children, positions, sort);
         for child in &mut children {
           if let FileItem::Folder(folder) = child {
-            let sub_children = build_tree(items_map, Some(folder.id));
+            // 並び順が未指定のフォルダは親の並び順を引き継ぐ
+            let folder_sort = resolve_sort(
+              folder.sort_by.as_deref(),
+              folder.sort_order.as_deref(),
+              sort,
+            );
+            let sub_children = build_tree(items_map, positions, Some(folder.id), folder_sort);
             folder.children = sub_children;
           }
         }
@@ -94,7 +162,417 @@ impl FileService {
       Vec::new()
     }
 
-    let result = build_tree(&mut items_map, None);
+    let result = build_tree(&mut items_map, &positions, None, default_sort);
     Ok(result)
   }
+
+  fn read_default_sort(conn: &Connection) -> Result<SortSettings, String> {
+    let (sort_by, sort_order): (String, String) = conn
+      .query_row(
+        "SELECT sort_by, sort_order FROM vault_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .map_err(|e| format!("並び順の設定の取得に失敗しました: {}", e))?;
+    Ok(resolve_sort(
+      Some(&sort_by),
+      Some(&sort_order),
+      DEFAULT_SORT,
+    ))
+  }
+
+  /// ボールト全体の既定の並び順を取得する
+  pub fn get_default_sort(&self) -> Result<SortSettings, String> {
+    let conn = self.db.conn.lock().unwrap();
+    Self::read_default_sort(&conn)
+  }
+
+  /// ボールト全体の既定の並び順を更新する
+  pub fn update_default_sort(&self, sort: SortSettings) -> Result<SortSettings, String> {
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .execute(
+        "UPDATE vault_settings SET sort_by = ?, sort_order = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
+        params![sort_by_to_str(sort.sort_by), sort_order_to_str(sort.sort_order)],
+      )
+      .map_err(|e| format!("並び順の設定の更新に失敗しました: {}", e))?;
+    Self::read_default_sort(&conn)
+  }
+
+  /// parent_id 直下のフォルダとノートの手動の並び順を保存する
+  ///
+  /// 指定しなかった項目の位置は変えない (位置が未指定の項目は末尾に並ぶ)。
+  pub fn reorder_files(
+    &self,
+    parent_id: Option<i64>,
+    folder_ids: &[i64],
+    note_ids: &[i64],
+  ) -> Result<(), String> {
+    let conn = self.db.conn.lock().unwrap();
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+
+    for (position, id) in folder_ids.iter().enumerate() {
+      let updated = tx
+        .execute(
+          "UPDATE folders SET position = ? WHERE id = ? AND parent_id IS ? AND is_deleted = FALSE",
+          params![position as i64, id, parent_id],
+        )
+        .map_err(|e| format!("フォルダの並び替えに失敗しました: {}", e))?;
+      if updated == 0 {
+        return Err(format!("フォルダが指定した場所にありません: {}", id));
+      }
+    }
+    for (position, id) in note_ids.iter().enumerate() {
+      let updated = tx
+        .execute(
+          "UPDATE notes SET position = ? WHERE id = ? AND parent_id IS ? AND is_deleted = FALSE",
+          params![position as i64, id, parent_id],
+        )
+        .map_err(|e| format!("ノートの並び替えに失敗しました: {}", e))?;
+      if updated == 0 {
+        return Err(format!("ノートが指定した場所にありません: {}", id));
+      }
+    }
+
+    tx.commit()
+      .map_err(|e| format!("並び替えの保存に失敗しました: {}", e))
+  }
+}
+
+const DEFAULT_SORT: SortSettings = SortSettings {
+  sort_by: SortBy::Name,
+  sort_order: SortOrder::Asc,
+};
+
+fn sort_by_to_str(sort_by: SortBy) -> &'static str {
+  match sort_by {
+    SortBy::Name => "name",
+    SortBy::CreatedAt => "createdAt",
+    SortBy::UpdatedAt => "updatedAt",
+    SortBy::Manual => "manual",
+  }
+}
+
+fn sort_order_to_str(sort_order: SortOrder) -> &'static str {
+  match sort_order {
+    SortOrder::Asc => "asc",
+    SortOrder::Desc => "desc",
+  }
+}
+
+// 保存されている文字列を解釈する (未指定や不明な値は inherited を使う)
+fn resolve_sort(
+  sort_by: Option<&str>,
+  sort_order: Option<&str>,
+  inherited: SortSettings,
+) -> SortSettings {
+  let sort_by = match sort_by {
+    Some("name" | "title") => SortBy::Name,
+    Some("createdAt" | "created") => SortBy::CreatedAt,
+    Some("updatedAt" | "updated") => SortBy::UpdatedAt,
+    Some("manual") => SortBy::Manual,
+    _ => inherited.sort_by,
+  };
+  let sort_order = match sort_order {
+    Some("asc") => SortOrder::Asc,
+    Some("desc") => SortOrder::Desc,
+    _ => inherited.sort_order,
+  };
+  SortSettings {
+    sort_by,
+    sort_order,
+  }
+}
+
+fn item_name(item: &FileItem) -> &str {
+  match item {
+    FileItem::Folder(folder) => &folder.name,
+    FileItem::Note(note) => &note.title,
+    FileItem::SavedSearch(saved) => &saved.name,
+  }
+}
+
+fn item_key(item: &FileItem) -> (bool, i64) {
+  match item {
+    FileItem::Folder(folder) => (true, folder.id),
+    FileItem::Note(note) => (false, note.id),
+    FileItem::SavedSearch(saved) => (false, saved.id),
+  }
+}
+
+fn item_time(item: &FileItem, sort_by: SortBy) -> &str {
+  match (item, sort_by) {
+    (FileItem::Folder(folder), SortBy::CreatedAt) => &folder.created_at,
+    (FileItem::Folder(folder), _) => &folder.updated_at,
+    (FileItem::Note(note), SortBy::CreatedAt) => &note.created_at,
+    (FileItem::Note(note), _) => &note.updated_at,
+    (FileItem::SavedSearch(_), _) => "",
+  }
+}
+
+/// フォルダを先に、その中で sort の順に並べる
+///
+/// 手動の並び順では sort_order は使わず、位置が未指定の項目は作成順で末尾に並ぶ。
+fn sort_items(items: &mut [FileItem], positions: &HashMap<(bool, i64), i64>, sort: SortSettings) {
+  items.sort_by(|a, b| {
+    let (a_is_folder, a_id) = item_key(a);
+    let (b_is_folder, b_id) = item_key(b);
+    let ordering = match sort.sort_by {
+      SortBy::Name => natural_cmp(item_name(a), item_name(b)),
+      SortBy::CreatedAt | SortBy::UpdatedAt => {
+        item_time(a, sort.sort_by).cmp(item_time(b, sort.sort_by))
+      }
+      SortBy::Manual => {
+        let a_pos = positions.get(&(a_is_folder, a_id));
+        let b_pos = positions.get(&(b_is_folder, b_id));
+        return b_is_folder
+          .cmp(&a_is_folder)
+          .then(a_pos.is_none().cmp(&b_pos.is_none()))
+          .then(a_pos.cmp(&b_pos))
+          .then(a_id.cmp(&b_id));
+      }
+    };
+    let ordering = match sort.sort_order {
+      SortOrder::Asc => ordering,
+      SortOrder::Desc => ordering.reverse(),
+    };
+    b_is_folder
+      .cmp(&a_is_folder)
+      .then(ordering)
+      .then_with(|| natural_cmp(item_name(a), item_name(b)))
+      .then(a_id.cmp(&b_id))
+  });
+}
+
+/// 人が読む順に名前を比較する
+///
+/// 大文字・小文字、全角・半角、カタカナ・ひらがなの違いは無視し、
+/// 連続した数字は数値として比較する ("メモ2" < "メモ10")。
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+  let a_chars: Vec<char> = fts::fold(a).chars().collect();
+  let b_chars: Vec<char> = fts::fold(b).chars().collect();
+  let (mut i, mut j) = (0, 0);
+  while i < a_chars.len() && j < b_chars.len() {
+    if a_chars[i].is_ascii_digit() && b_chars[j].is_ascii_digit() {
+      let a_start = i;
+      let b_start = j;
+      while i < a_chars.len() && a_chars[i].is_ascii_digit() {
+        i += 1;
+      }
+      while j < b_chars.len() && b_chars[j].is_ascii_digit() {
+        j += 1;
+      }
+      let a_num = trim_leading_zeros(&a_chars[a_start..i]);
+      let b_num = trim_leading_zeros(&b_chars[b_start..j]);
+      let ordering = a_num.len().cmp(&b_num.len()).then(a_num.cmp(b_num));
+      if ordering != Ordering::Equal {
+        return ordering;
+      }
+      continue;
+    }
+    let ordering = a_chars[i].cmp(&b_chars[j]);
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+    i += 1;
+    j += 1;
+  }
+  (a_chars.len() - i)
+    .cmp(&(b_chars.len() - j))
+    .then_with(|| a.cmp(b))
+}
+
+fn trim_leading_zeros(digits: &[char]) -> &[char] {
+  let zeros = digits.iter().take_while(|c| **c == '0').count();
+  &digits[zeros..]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::db::models::UpdateFolderInput;
+  use crate::services::{FolderService, NoteService};
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_saved_searches_are_virtual_folders() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let note = notes
+      .create_note("Release plan".to_string(), String::new(), None, None)
+      .unwrap();
+    notes
+      .create_note("Other".to_string(), String::new(), None, None)
+      .unwrap();
+    SavedSearchService::new(db.clone())
+      .create_saved_search("リリース".to_string(), "release".to_string(), None, None)
+      .unwrap();
+
+    let items = FileService::new(db).get_all_files_hierarchical().unwrap();
+    // 保存済み検索が先頭に並び、実際のノートはそのまま残る
+    assert_eq!(items.len(), 3);
+    match &items[0] {
+      FileItem::SavedSearch(saved) => {
+        assert_eq!(saved.name, "リリース");
+        let ids: Vec<i64> = saved
+          .children
+          .iter()
+          .filter_map(|child| match child {
+            FileItem::Note(n) => Some(n.id),
+            _ => None,
+          })
+          .collect();
+        assert_eq!(ids, vec![note.id]);
+      }
+      other => panic!("unexpected item: {:?}", other),
+    }
+  }
+
+  fn names(items: &[FileItem]) -> Vec<&str> {
+    items.iter().map(item_name).collect()
+  }
+
+  fn children<'a>(items: &'a [FileItem], name: &str) -> &'a [FileItem] {
+    items
+      .iter()
+      .find_map(|item| match item {
+        FileItem::Folder(folder) if folder.name == name => Some(folder.children.as_slice()),
+        _ => None,
+      })
+      .unwrap()
+  }
+
+  #[test]
+  fn test_natural_cmp() {
+    assert_eq!(natural_cmp("メモ2", "メモ10"), Ordering::Less);
+    assert_eq!(natural_cmp("memo 010", "memo 9"), Ordering::Greater);
+    assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+    assert_eq!(natural_cmp("ｶﾒﾗ", "きつね"), Ordering::Less);
+    assert_eq!(natural_cmp("Note", "Note 1"), Ordering::Less);
+  }
+
+  #[test]
+  fn test_folder_sort_is_inherited() {
+    let (db, temp_dir) = setup_test_db();
+    let notes_dir = temp_dir.path().join("notes");
+    let notes = NoteService::new(db.clone(), notes_dir.clone());
+    let folders = FolderService::new(db.clone(), notes_dir);
+    let service = FileService::new(db.clone());
+
+    let parent = folders.create_folder("親".to_string(), None, None).unwrap();
+    let child = folders
+      .create_folder("子".to_string(), Some(parent.id), None)
+      .unwrap();
+    for (title, created_at) in [
+      ("Note 10", "2024-01-01 00:00:00"),
+      ("Note 2", "2024-03-01 00:00:00"),
+      ("note 1", "2024-02-01 00:00:00"),
+    ] {
+      for folder_id in [None, Some(parent.id), Some(child.id)] {
+        let note = notes
+          .create_note(title.to_string(), String::new(), folder_id, None)
+          .unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn
+          .execute(
+            "UPDATE notes SET created_at = ? WHERE id = ?",
+            params![created_at, note.id],
+          )
+          .unwrap();
+      }
+    }
+
+    folders
+      .update_folder(UpdateFolderInput {
+        id: parent.id,
+        name: "親".to_string(),
+        parent_id: None,
+        icon: None,
+        color: None,
+        sort_by: Some("createdAt".to_string()),
+        sort_order: Some("desc".to_string()),
+        default_template_id: None,
+      })
+      .unwrap();
+
+    // ルートは既定の名前順 (数字は数値として比較する)
+    let items = service.get_all_files_hierarchical().unwrap();
+    assert_eq!(names(&items), vec!["親", "note 1", "Note 2", "Note 10"]);
+    // フォルダ自身の設定と、それを引き継いだ子フォルダ
+    let parent_items = children(&items, "親");
+    assert_eq!(
+      names(parent_items),
+      vec!["子", "Note 2", "note 1", "Note 10"]
+    );
+    assert_eq!(
+      names(children(parent_items, "子")),
+      vec!["Note 2", "note 1", "Note 10"]
+    );
+
+    service
+      .update_default_sort(SortSettings {
+        sort_by: SortBy::Name,
+        sort_order: SortOrder::Desc,
+      })
+      .unwrap();
+    let items = service.get_all_files_hierarchical().unwrap();
+    assert_eq!(names(&items), vec!["親", "Note 10", "Note 2", "note 1"]);
+  }
+
+  #[test]
+  fn test_manual_order() {
+    let (db, temp_dir) = setup_test_db();
+    let notes_dir = temp_dir.path().join("notes");
+    let notes = NoteService::new(db.clone(), notes_dir.clone());
+    let folders = FolderService::new(db.clone(), notes_dir);
+    let service = FileService::new(db.clone());
+
+    let folder = folders.create_folder("F".to_string(), None, None).unwrap();
+    let a = notes
+      .create_note("A".to_string(), String::new(), None, None)
+      .unwrap();
+    notes
+      .create_note("B".to_string(), String::new(), None, None)
+      .unwrap();
+    let c = notes
+      .create_note("C".to_string(), String::new(), None, None)
+      .unwrap();
+    let inner = notes
+      .create_note("D".to_string(), String::new(), Some(folder.id), None)
+      .unwrap();
+
+    service
+      .update_default_sort(SortSettings {
+        sort_by: SortBy::Manual,
+        sort_order: SortOrder::Desc,
+      })
+      .unwrap();
+    service.reorder_files(None, &[], &[c.id, a.id]).unwrap();
+
+    // 位置が未指定のノートは末尾に作成順で並ぶ
+    let items = service.get_all_files_hierarchical().unwrap();
+    assert_eq!(names(&items), vec!["F", "C", "A", "B"]);
+
+    // 別のフォルダにある項目は並び替えられない
+    assert!(service.reorder_files(None, &[], &[inner.id]).is_err());
+    assert!(
+      service
+        .reorder_files(Some(folder.id), &[], &[inner.id])
+        .is_ok()
+    );
+  }
 }
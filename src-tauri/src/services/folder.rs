@@ -1,11 +1,13 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::db::Database;
 use crate::db::models::Folder;
+use crate::services::search::escape_like;
+use crate::services::{LinkService, filename};
 
-use rusqlite::{Result as SqlResult, params};
+use rusqlite::{Connection, Result as SqlResult, params};
 
 pub struct FolderService {
   db: Arc<Database>,
@@ -17,6 +19,18 @@ impl FolderService {
     Self { db, base_path }
   }
 
+  // ディレクトリが存在するか、削除されていないフォルダが使っているパスか
+  fn is_path_taken(conn: &Connection, path: &Path) -> bool {
+    path.exists()
+      || conn
+        .query_row(
+          "SELECT EXISTS(SELECT 1 FROM folders WHERE folder_path = ? AND is_deleted = FALSE)",
+          params![path.to_str().unwrap_or_default()],
+          |row| row.get(0),
+        )
+        .unwrap_or(false)
+  }
+
   // フォルダの作成
   pub fn create_folder(
     &self,
@@ -24,31 +38,21 @@ impl FolderService {
     parent_id: Option<i64>,
     parent_path: Option<String>,
   ) -> Result<Folder, String> {
-    let full_path = self
-      .base_path
-      .join(parent_path.clone().unwrap_or_default())
-      .join(name.clone());
+    let name = filename::normalize_title(&name);
+    let parent_dir = filename::resolve_within(&self.base_path, &parent_path.unwrap_or_default())?;
 
-    let folder_path_str = full_path.to_str().unwrap_or_default().to_string();
-
-    // 同じパスのフォルダが既に存在するかチェック
-    {
+    // 同じパスのフォルダが既に存在する場合は番号を付ける
+    let full_path = {
       let conn = self.db.conn.lock().unwrap();
-      let exists: bool = conn
-        .query_row(
-          "SELECT EXISTS(SELECT 1 FROM folders WHERE folder_path = ?)",
-          params![folder_path_str],
-          |row| row.get(0),
-        )
-        .unwrap_or(false);
+      filename::unique_path(
+        &parent_dir,
+        &filename::sanitize_file_name(&name),
+        None,
+        |path| Self::is_path_taken(&conn, path),
+      )
+    };
 
-      if exists {
-        return Err(format!(
-          "同じパスのフォルダが既に存在します: {}",
-          folder_path_str
-        ));
-      }
-    }
+    let folder_path_str = full_path.to_str().unwrap_or_default().to_string();
 
     fs::create_dir_all(&full_path)
       .map_err(|e| format!("ディレクトリの作成に失敗しました: {}", e))?;
@@ -75,7 +79,7 @@ impl FolderService {
     let conn = self.db.conn.lock().unwrap();
     let folder = conn
       .query_row(
-        "\n    SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE id = ?\n    ",
+        "\n    SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id FROM folders WHERE id = ?\n    ",
         params![id],
         |row| {
           Ok(Folder {
@@ -91,6 +95,7 @@ impl FolderService {
             color: row.get(9)?,
             sort_by: row.get(10)?,
             sort_order: row.get(11)?,
+            default_template_id: row.get(12)?,
           })
         },
       )
@@ -103,7 +108,7 @@ impl FolderService {
     let conn = self.db.conn.lock().unwrap();
 
     let mut stmt = conn
-      .prepare("SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE is_deleted = FALSE")
+      .prepare("SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id FROM folders WHERE is_deleted = FALSE")
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     let folders = stmt
@@ -121,6 +126,7 @@ impl FolderService {
           color: row.get(9)?,
           sort_by: row.get(10)?,
           sort_order: row.get(11)?,
+          default_template_id: row.get(12)?,
         })
       })
       .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?
@@ -139,7 +145,7 @@ impl FolderService {
 
     let old_folder: Folder = conn
       .query_row(
-        "SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE id = ?",
+        "SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id FROM folders WHERE id = ?",
         params![input.id],
         |row| {
           Ok(Folder {
@@ -155,16 +161,23 @@ impl FolderService {
             color: row.get(9)?,
             sort_by: row.get(10)?,
             sort_order: row.get(11)?,
+            default_template_id: row.get(12)?,
           })
         },
       )
       .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?;
 
+    let name = filename::normalize_title(&input.name);
     let old_path = PathBuf::from(&old_folder.folder_path);
-    let new_path = if let Some(parent) = old_path.parent() {
-      parent.join(&input.name)
+    let new_path = if name == old_folder.name {
+      old_path.clone()
     } else {
-      PathBuf::from(&input.name)
+      filename::unique_path(
+        old_path.parent().unwrap_or(&self.base_path),
+        &filename::sanitize_file_name(&name),
+        None,
+        |path| path != old_path && Self::is_path_taken(&conn, path),
+      )
     };
     let new_path_str = new_path.to_str().unwrap_or_default();
 
@@ -192,17 +205,19 @@ impl FolderService {
     // icon と color も更新
     conn
       .execute(
-        "UPDATE folders SET name = ?, parent_id = ?, icon = ?, color = ?, sort_by = ?, sort_order = ? WHERE id = ?",
-        params![input.name.clone(), input.parent_id, input.icon, input.color, input.sort_by, input.sort_order, input.id],
+        "UPDATE folders SET name = ?, parent_id = ?, icon = ?, color = ?, sort_by = ?, sort_order = ?, default_template_id = ? WHERE id = ?",
+        params![name, input.parent_id, input.icon, input.color, input.sort_by, input.sort_order, input.default_template_id, input.id],
       )
       .map_err(|e| format!("フォルダの更新に失敗しました: {}", e))?;
 
-    // バックリンクの更新
+    // バックリンクの更新中はロックを手放す
+    drop(conn);
     self.update_backlinks(&old_path, &new_path)?;
 
+    let conn = self.db.conn.lock().unwrap();
     let updated_folder = conn
       .query_row(
-        "SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE id = ?",
+        "SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id FROM folders WHERE id = ?",
         params![input.id],
         |row| {
           Ok(Folder {
@@ -218,6 +233,7 @@ impl FolderService {
             color: row.get(9)?,
             sort_by: row.get(10)?,
             sort_order: row.get(11)?,
+            default_template_id: row.get(12)?,
           })
         },
       )
@@ -584,7 +600,7 @@ impl FolderService {
     let conn = self.db.conn.lock().unwrap();
 
     let mut stmt = conn
-      .prepare("SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE is_deleted = TRUE ORDER BY deleted_at DESC")
+      .prepare("SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id FROM folders WHERE is_deleted = TRUE ORDER BY deleted_at DESC")
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     let folders = stmt
@@ -602,6 +618,7 @@ impl FolderService {
           color: row.get(9)?,
           sort_by: row.get(10)?,
           sort_order: row.get(11)?,
+          default_template_id: row.get(12)?,
         })
       })
       .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?
@@ -616,22 +633,27 @@ impl FolderService {
     self.delete_folder_recursive(folder_id)
   }
 
-  fn get_relative_path(&self, path: &std::path::Path) -> String {
-    path
-      .strip_prefix(&self.base_path)
-      .unwrap_or(path)
-      .with_extension("")
-      .to_string_lossy()
-      .replace("\\", "/")
+  // フォルダ以下にあるノート (パスの _ や % はワイルドカードとして扱わない)
+  fn note_ids_under(conn: &Connection, dir: &Path) -> SqlResult<Vec<i64>> {
+    let pattern = format!(
+      "{}{}%",
+      escape_like(dir.to_str().unwrap_or_default()),
+      std::path::MAIN_SEPARATOR
+    );
+    let mut stmt = conn.prepare("SELECT id FROM notes WHERE file_path LIKE ? ESCAPE '\\'")?;
+    stmt
+      .query_map(params![pattern], |row| row.get::<_, i64>(0))?
+      .collect()
   }
 
+  // 移動したフォルダ内のノートにリンクしているノートだけを書き換える
   fn update_backlinks(
     &self,
     old_path: &std::path::Path,
     new_path: &std::path::Path,
   ) -> Result<(), String> {
-    let old_rel = self.get_relative_path(old_path);
-    let new_rel = self.get_relative_path(new_path);
+    let old_rel = LinkService::relative_link_path(&self.base_path, old_path);
+    let new_rel = LinkService::relative_link_path(&self.base_path, new_path);
 
     if old_rel == new_rel {
       return Ok(());
@@ -640,48 +662,16 @@ impl FolderService {
     let old_prefix = format!("[[{}/", old_rel);
     let new_prefix = format!("[[{}/", new_rel);
 
-    let notes = {
+    let moved_ids = {
       let conn = self.db.conn.lock().unwrap();
-      let mut stmt = conn
-        .prepare("SELECT id, title, file_path FROM notes ")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-      stmt
-        .query_map([], |row| {
-          Ok((
-            row.get::<_, i64>(0)?,
-            row.get::<_, String>(1)?,
-            row.get::<_, String>(2)?,
-          ))
-        })
-        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
-        .collect::<SqlResult<Vec<(i64, String, String)>>>()
+      Self::note_ids_under(&conn, new_path)
         .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
     };
 
-    for (id, title, file_path) in notes {
-      let content = match fs::read_to_string(&file_path) {
-        Ok(c) => c,
-        Err(_) => continue,
-      };
-
-      if content.contains(&old_prefix) {
-        let new_content = content.replace(&old_prefix, &new_prefix);
-        fs::write(&file_path, &new_content)
-          .map_err(|e| format!("バックリンクの更新に失敗しました ({}): {}", title, e))?;
-
-        let preview = crate::services::note::NoteService::generate_preview(&new_content);
-        let conn = self.db.conn.lock().unwrap();
-        conn
-          .execute(
-            "UPDATE notes SET preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-            params![preview, id],
-          )
-          .map_err(|e| format!("プレビューの更新に失敗しました: {}", e))?;
-      }
-    }
-
-    Ok(())
+    LinkService::new(self.db.clone(), self.base_path.clone())
+      .rewrite_backlinks(&moved_ids, |content| {
+        content.replace(&old_prefix, &new_prefix)
+      })
   }
 
   // フォルダの移動
@@ -690,7 +680,7 @@ impl FolderService {
       let conn = self.db.conn.lock().unwrap();
       conn
         .query_row(
-          "SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order FROM folders WHERE id = ?",
+          "SELECT id, name, created_at, updated_at, parent_id, folder_path, is_deleted, deleted_at, icon, color, sort_by, sort_order, default_template_id FROM folders WHERE id = ?",
           params![id],
           |row| {
             Ok(Folder {
@@ -706,6 +696,7 @@ impl FolderService {
               color: row.get(9)?,
               sort_by: row.get(10)?,
               sort_order: row.get(11)?,
+              default_template_id: row.get(12)?,
             })
           },
         )
@@ -751,7 +742,18 @@ impl FolderService {
     let folder_name = old_path
       .file_name()
       .ok_or_else(|| "フォルダ名の取得に失敗しました".to_string())?;
-    let new_path = new_parent_path.join(folder_name);
+    let new_path = if new_parent_path.join(folder_name) == old_path {
+      old_path.clone()
+    } else {
+      // 移動先に同じ名前のフォルダがある場合は番号を付ける
+      let conn = self.db.conn.lock().unwrap();
+      filename::unique_path(
+        &new_parent_path,
+        &folder_name.to_string_lossy(),
+        None,
+        |path| Self::is_path_taken(&conn, path),
+      )
+    };
 
     if old_path != new_path {
       if let Some(parent) = new_path.parent() {
@@ -785,7 +787,7 @@ impl FolderService {
 
       conn
         .execute(
-          "UPDATE folders SET parent_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+          "UPDATE folders SET parent_id = ?, position = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
           params![new_parent_id, id],
         )
         .map_err(|e| format!("フォルダの移動に失敗しました: {}", e))?;
@@ -881,6 +883,7 @@ mod tests {
       color: Some("#FF0000".to_string()),
       sort_by: None,
       sort_order: None,
+      default_template_id: None,
     };
 
     let result = service.update_folder(update_input);
@@ -943,4 +946,136 @@ mod tests {
     let moved = result.unwrap();
     assert_eq!(moved.parent_id, Some(parent.id));
   }
+
+  #[test]
+  fn test_default_template_is_inherited_by_subfolders() {
+    use crate::db::models::{CreateTemplateInput, UpdateFolderInput};
+    use crate::services::{NoteService, TemplateService};
+
+    let (db, temp_dir) = setup_test_db();
+    let service = FolderService::new(db.clone(), temp_dir.path().to_path_buf());
+    let notes = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let templates = TemplateService::new(db.clone());
+
+    let meeting = templates
+      .create_template(CreateTemplateInput {
+        name: "会議".to_string(),
+        content: "# {{title}} ({{folder}})\n{{prompt:議題}}".to_string(),
+        description: None,
+      })
+      .unwrap();
+    let memo = templates
+      .create_template(CreateTemplateInput {
+        name: "メモ".to_string(),
+        content: "メモ".to_string(),
+        description: None,
+      })
+      .unwrap();
+
+    let parent = service
+      .create_folder("仕事".to_string(), None, None)
+      .unwrap();
+    let child = service
+      .create_folder(
+        "定例".to_string(),
+        Some(parent.id),
+        Some(parent.folder_path.clone()),
+      )
+      .unwrap();
+    let other = service
+      .create_folder(
+        "個人".to_string(),
+        Some(parent.id),
+        Some(parent.folder_path.clone()),
+      )
+      .unwrap();
+    let set_template = |folder: &Folder, template_id: Option<i64>| {
+      service
+        .update_folder(UpdateFolderInput {
+          id: folder.id,
+          name: folder.name.clone(),
+          parent_id: folder.parent_id,
+          icon: None,
+          color: None,
+          sort_by: None,
+          sort_order: None,
+          default_template_id: template_id,
+        })
+        .unwrap()
+    };
+    let updated = set_template(&parent, Some(meeting.id));
+    assert_eq!(updated.default_template_id, Some(meeting.id));
+    set_template(&other, Some(memo.id));
+
+    // 子フォルダは親の設定を引き継ぎ、自分の設定があればそちらを使う
+    let note = notes
+      .create_note(
+        "週次".to_string(),
+        String::new(),
+        Some(child.id),
+        Some(child.folder_path.clone()),
+      )
+      .unwrap();
+    assert_eq!(note.content, "# 週次 (定例)\n");
+    let note = notes
+      .create_note(
+        "買い物".to_string(),
+        String::new(),
+        Some(other.id),
+        Some(other.folder_path.clone()),
+      )
+      .unwrap();
+    assert_eq!(note.content, "メモ");
+
+    // 本文を渡した場合やフォルダの外では使わない
+    let note = notes
+      .create_note(
+        "本文あり".to_string(),
+        "本文".to_string(),
+        Some(child.id),
+        Some(child.folder_path.clone()),
+      )
+      .unwrap();
+    assert_eq!(note.content, "本文");
+    let note = notes
+      .create_note("直下".to_string(), String::new(), None, None)
+      .unwrap();
+    assert_eq!(note.content, "");
+
+    // テンプレートを削除すると親の設定に戻る
+    templates.delete_template(memo.id).unwrap();
+    assert_eq!(
+      service
+        .get_folder_by_id(other.id)
+        .unwrap()
+        .default_template_id,
+      None
+    );
+    assert_eq!(
+      notes.folder_default_template_id(other.id).unwrap(),
+      Some(meeting.id)
+    );
+  }
+
+  #[test]
+  fn test_note_ids_under_escapes_wildcards() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().to_path_buf();
+    {
+      let conn = db.conn.lock().unwrap();
+      for (id, dir) in [(1, "a_b"), (2, "axb"), (3, "100%"), (4, "1000")] {
+        conn
+          .execute(
+            "INSERT INTO notes (id, title, file_path) VALUES (?, 'note', ?)",
+            params![id, base_path.join(dir).join("note.md").to_str().unwrap()],
+          )
+          .unwrap();
+      }
+    }
+
+    let conn = db.conn.lock().unwrap();
+    let ids_under = |dir: &str| FolderService::note_ids_under(&conn, &base_path.join(dir)).unwrap();
+    assert_eq!(ids_under("a_b"), vec![1]);
+    assert_eq!(ids_under("100%"), vec![3]);
+  }
 }
@@ -0,0 +1,183 @@
+use rusqlite::{Connection, Result as SqlResult, params};
+use unicode_normalization::char::compose;
+
+/// 半角カタカナ (U+FF66〜U+FF9D) に対応するひらがな
+const HALFWIDTH_KANA: &str = "をぁぃぅぇぉゃゅょっーあいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわん";
+
+fn fold_char(c: char) -> char {
+  let c = match c {
+    // 全角英数字・記号 → 半角
+    '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+    '\u{3000}' => ' ',
+    // カタカナ → ひらがな
+    '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+    // 半角カタカナ → ひらがな (濁点・半濁点は結合文字にして直前の文字と合成する)
+    '\u{FF66}'..='\u{FF9D}' => HALFWIDTH_KANA.chars().nth(c as usize - 0xFF66).unwrap_or(c),
+    '\u{FF9E}' => '\u{3099}',
+    '\u{FF9F}' => '\u{309A}',
+    '\u{FF61}' => '。',
+    '\u{FF62}' => '「',
+    '\u{FF63}' => '」',
+    '\u{FF64}' => '、',
+    '\u{FF65}' => '・',
+    _ => c,
+  };
+  // 小文字にすると複数の文字になるもの (İ など) はそのままにする
+  let mut lower = c.to_lowercase();
+  match (lower.next(), lower.next()) {
+    (Some(l), None) => l,
+    _ => c,
+  }
+}
+
+/// 検索用に文字の表記ゆれを揃え、各文字が元の文字列の何文字目から来たかを返す
+///
+/// 全角英数字は半角に、カタカナはひらがなに、英字は小文字にする。
+/// 半角カタカナの濁点や分解された濁点 (NFD) は直前の文字と合成するため、
+/// 文字数が元の文字列より減ることがある。
+pub fn fold_with_offsets(text: &str) -> (Vec<char>, Vec<usize>) {
+  let mut folded: Vec<char> = Vec::with_capacity(text.len());
+  let mut offsets = Vec::with_capacity(text.len());
+  for (i, c) in text.chars().enumerate() {
+    let c = fold_char(c);
+    if let Some(last) = folded.last_mut()
+      && let Some(composed) = compose(*last, c)
+    {
+      *last = composed;
+      continue;
+    }
+    folded.push(c);
+    offsets.push(i);
+  }
+  (folded, offsets)
+}
+
+/// 検索用に文字の表記ゆれを揃える
+pub fn fold(text: &str) -> String {
+  fold_with_offsets(text).0.into_iter().collect()
+}
+
+fn insert_search(conn: &Connection, id: i64, title: &str, content: &str) -> SqlResult<()> {
+  conn.execute(
+    "INSERT INTO notes_search (rowid, title, content) VALUES (?, ?, ?)",
+    params![id, fold(title), fold(content)],
+  )?;
+  Ok(())
+}
+
+/// ノートを検索インデックスに追加する (既にあれば置き換える)
+///
+/// notes_fts には元の文字列を、notes_search には `fold` した文字列を入れる。
+/// どちらも rowid はノートの id にする。
+pub fn insert(conn: &Connection, id: i64, title: &str, content: &str) -> SqlResult<()> {
+  remove(conn, id)?;
+  conn.execute(
+    "INSERT INTO notes_fts (rowid, id, title, content) VALUES (?1, ?1, ?2, ?3)",
+    params![id, title, content],
+  )?;
+  insert_search(conn, id, title, content)
+}
+
+/// タイトルと内容を更新する
+pub fn update(conn: &Connection, id: i64, title: &str, content: &str) -> SqlResult<()> {
+  insert(conn, id, title, content)
+}
+
+/// タイトルだけを更新する
+pub fn update_title(conn: &Connection, id: i64, title: &str) -> SqlResult<()> {
+  conn.execute(
+    "UPDATE notes_fts SET title = ? WHERE rowid = ?",
+    params![title, id],
+  )?;
+  conn.execute(
+    "UPDATE notes_search SET title = ? WHERE rowid = ?",
+    params![fold(title), id],
+  )?;
+  Ok(())
+}
+
+/// 内容だけを更新する
+pub fn update_content(conn: &Connection, id: i64, content: &str) -> SqlResult<()> {
+  conn.execute(
+    "UPDATE notes_fts SET content = ? WHERE rowid = ?",
+    params![content, id],
+  )?;
+  conn.execute(
+    "UPDATE notes_search SET content = ? WHERE rowid = ?",
+    params![fold(content), id],
+  )?;
+  Ok(())
+}
+
+/// ノートを検索インデックスから削除する
+pub fn remove(conn: &Connection, id: i64) -> SqlResult<()> {
+  conn.execute("DELETE FROM notes_fts WHERE rowid = ?", params![id])?;
+  conn.execute("DELETE FROM notes_search WHERE rowid = ?", params![id])?;
+  Ok(())
+}
+
+/// 検索インデックスを空にする
+pub fn clear(conn: &Connection) -> SqlResult<()> {
+  conn.execute("DELETE FROM notes_fts", [])?;
+  conn.execute("DELETE FROM notes_search", [])?;
+  Ok(())
+}
+
+/// notes_fts から notes_search を作り直す (行数が食い違っている場合のみ)
+///
+/// notes_search はスキーマバージョン 4 で追加されたため、既存のデータベースでは
+/// 起動後に一度だけ作り直される。作り直した場合は true を返す。
+pub fn ensure_search_index(conn: &Connection) -> SqlResult<bool> {
+  let fts_count: i64 = conn.query_row("SELECT COUNT(*) FROM notes_fts", [], |row| row.get(0))?;
+  let search_count: i64 =
+    conn.query_row("SELECT COUNT(*) FROM notes_search", [], |row| row.get(0))?;
+  if fts_count == search_count {
+    return Ok(false);
+  }
+
+  let rows: Vec<(i64, String, String)> = {
+    let mut stmt = conn.prepare("SELECT rowid, title, content FROM notes_fts")?;
+    stmt
+      .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+      .collect::<SqlResult<_>>()?
+  };
+
+  let tx = conn.unchecked_transaction()?;
+  tx.execute("DELETE FROM notes_search", [])?;
+  for (id, title, content) in rows {
+    insert_search(&tx, id, &title, &content)?;
+  }
+  tx.commit()?;
+  Ok(true)
+}
+
+/// notes_search が trigram トークナイザで作られているか
+///
+/// trigram が使えない環境では日本語が単語に分割されないため、検索は LIKE で行う。
+pub fn uses_trigram(conn: &Connection) -> SqlResult<bool> {
+  conn.query_row(
+    "SELECT COALESCE(instr(sql, 'trigram') > 0, FALSE) FROM sqlite_master WHERE name = 'notes_search'",
+    [],
+    |row| row.get(0),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_fold() {
+    assert_eq!(fold("ＡＢＣ１２３　ｘ"), "abc123 x");
+    assert_eq!(fold("カタカナとひらがな"), "かたかなとひらがな");
+    assert_eq!(fold("ｶﾀｶﾅ"), "かたかな");
+    assert_eq!(fold("ｶﾞｲﾄﾞとﾊﾟﾝ"), "がいどとぱん");
+    assert_eq!(fold("か\u{3099}き"), "がき");
+    assert_eq!(fold("Meeting 議事録"), "meeting 議事録");
+
+    // 合成した文字は元の文字列での先頭の位置を指す
+    let (folded, offsets) = fold_with_offsets("ﾀﾏｺﾞﾔｷ");
+    assert_eq!(folded.into_iter().collect::<String>(), "たまごやき");
+    assert_eq!(offsets, vec![0, 1, 2, 4, 5]);
+  }
+}
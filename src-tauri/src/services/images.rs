@@ -0,0 +1,354 @@
+use crate::db::models::{AssetSettings, ImageEncoding};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage};
+use std::io::Cursor;
+
+/// Formats that pasted images are processed in (GIFs may be animated, so they are left alone)
+fn is_processable(format: ImageFormat) -> bool {
+  matches!(
+    format,
+    ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP
+  )
+}
+
+/// Decode an image and rotate it as its EXIF orientation says
+pub fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+  let mut decoder = ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .map_err(|e| format!("Failed to read image: {}", e))?
+    .into_decoder()
+    .map_err(|e| format!("Failed to read image: {}", e))?;
+  let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+  let mut image =
+    DynamicImage::from_decoder(decoder).map_err(|e| format!("Failed to decode image: {}", e))?;
+  image.apply_orientation(orientation);
+  Ok(image)
+}
+
+fn orientation(data: &[u8]) -> Orientation {
+  ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .ok()
+    .and_then(|reader| reader.into_decoder().ok())
+    .and_then(|mut decoder| decoder.orientation().ok())
+    .unwrap_or(Orientation::NoTransforms)
+}
+
+// JPEG は透過を持てないため白の背景に重ねる
+fn flatten(image: &DynamicImage) -> RgbImage {
+  let rgba = image.to_rgba8();
+  RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+    let [r, g, b, a] = rgba.get_pixel(x, y).0;
+    let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+    Rgb([blend(r), blend(g), blend(b)])
+  })
+}
+
+/// Encode an image (the encoders never write EXIF, so re-encoding also strips it)
+pub fn encode(
+  image: &DynamicImage,
+  format: ImageFormat,
+  jpeg_quality: u8,
+) -> Result<Vec<u8>, String> {
+  let mut data = Vec::new();
+  let result = match format {
+    ImageFormat::Jpeg => DynamicImage::ImageRgb8(flatten(image)).write_with_encoder(
+      JpegEncoder::new_with_quality(&mut data, jpeg_quality.clamp(1, 100)),
+    ),
+    // ロスレスの WebP は 8 ビットの RGB(A) しか扱えない
+    ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8())
+      .write_with_encoder(WebPEncoder::new_lossless(&mut data)),
+    _ => image.write_with_encoder(PngEncoder::new(&mut data)),
+  };
+  result.map_err(|e| format!("Failed to encode image: {}", e))?;
+  Ok(data)
+}
+
+/// Remove EXIF and XMP metadata from a JPEG, PNG or WebP file without re-encoding it
+///
+/// Returns None when the file is not in one of those formats or cannot be parsed.
+pub fn strip_metadata(data: &[u8]) -> Option<Vec<u8>> {
+  match image::guess_format(data).ok()? {
+    ImageFormat::Jpeg => strip_jpeg_metadata(data),
+    ImageFormat::Png => strip_png_metadata(data),
+    ImageFormat::WebP => strip_webp_metadata(data),
+    _ => None,
+  }
+}
+
+// APP1 セグメントの EXIF と XMP を取り除く
+fn strip_jpeg_metadata(data: &[u8]) -> Option<Vec<u8>> {
+  let mut result = data.get(..2)?.to_vec();
+  let mut pos = 2;
+  loop {
+    if *data.get(pos)? != 0xFF {
+      return None;
+    }
+    let marker = *data.get(pos + 1)?;
+    // 画像データの始まり (以降はそのまま残す)
+    if marker == 0xDA || marker == 0xD9 {
+      result.extend_from_slice(&data[pos..]);
+      return Some(result);
+    }
+    // 長さを持たないマーカー
+    if matches!(marker, 0x01 | 0xD0..=0xD7) {
+      result.extend_from_slice(&data[pos..pos + 2]);
+      pos += 2;
+      continue;
+    }
+    let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+    let segment = data.get(pos..pos + 2 + length)?;
+    // 長さが 2 未満のセグメントは壊れたファイル
+    let payload = segment.get(4..)?;
+    let is_metadata = marker == 0xE1
+      && (payload.starts_with(b"Exif\0") || payload.starts_with(b"http://ns.adobe.com/xap/1.0/\0"));
+    if !is_metadata {
+      result.extend_from_slice(segment);
+    }
+    pos += 2 + length;
+  }
+}
+
+// eXIf チャンクと XMP の iTXt チャンクを取り除く
+fn strip_png_metadata(data: &[u8]) -> Option<Vec<u8>> {
+  let mut result = data.get(..8)?.to_vec();
+  let mut pos = 8;
+  while pos < data.len() {
+    let length = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+    let chunk = data.get(pos..pos + 12 + length)?;
+    let kind = &chunk[4..8];
+    let is_metadata =
+      kind == b"eXIf" || (kind == b"iTXt" && chunk[8..].starts_with(b"XML:com.adobe.xmp\0"));
+    if !is_metadata {
+      result.extend_from_slice(chunk);
+    }
+    pos += 12 + length;
+  }
+  Some(result)
+}
+
+// EXIF と XMP のチャンクを取り除き、VP8X のフラグと RIFF のサイズを直す
+fn strip_webp_metadata(data: &[u8]) -> Option<Vec<u8>> {
+  let mut chunks = Vec::new();
+  let mut pos = 12;
+  while pos < data.len() {
+    let kind = data.get(pos..pos + 4)?;
+    let length = u32::from_le_bytes(data.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+    let end = (pos + 8 + length + length % 2).min(data.len());
+    let mut chunk = data.get(pos..end)?.to_vec();
+    match kind {
+      b"EXIF" | b"XMP " => {}
+      b"VP8X" => {
+        *chunk.get_mut(8)? &= !(0x08 | 0x04);
+        chunks.push(chunk);
+      }
+      _ => chunks.push(chunk),
+    }
+    pos = end;
+  }
+  let body: Vec<u8> = chunks.concat();
+  let mut result = b"RIFF".to_vec();
+  result.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+  result.extend_from_slice(b"WEBP");
+  result.extend_from_slice(&body);
+  Some(result)
+}
+
+/// Shrink, re-encode and strip EXIF from a pasted image as the settings say
+///
+/// Images in formats that are not processed are returned unchanged.
+pub fn process_image(data: &[u8], settings: &AssetSettings) -> Result<Vec<u8>, String> {
+  let Some(format) = image::guess_format(data)
+    .ok()
+    .filter(|f| is_processable(*f))
+  else {
+    return Ok(data.to_vec());
+  };
+  let jpeg_quality = settings.jpeg_quality.clamp(1, 100) as u8;
+
+  if settings.process_images {
+    let mut image = decode(data)?;
+    let max_dimension = settings.max_image_dimension.max(1) as u32;
+    let too_large = image.width().max(image.height()) > max_dimension;
+    let reencode = format == ImageFormat::Png && data.len() as i64 > settings.reencode_threshold;
+    if too_large {
+      image = image.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+    }
+    if too_large || reencode {
+      let target = if reencode {
+        match settings.reencode_format {
+          ImageEncoding::Webp => ImageFormat::WebP,
+          ImageEncoding::Jpeg => ImageFormat::Jpeg,
+        }
+      } else {
+        format
+      };
+      let encoded = encode(&image, target, jpeg_quality)?;
+      // 縮小していないのに大きくなる場合は元の画像を使う
+      if too_large || encoded.len() < data.len() {
+        return Ok(encoded);
+      }
+    }
+  }
+
+  if settings.strip_exif {
+    // 向きは EXIF にしか残っていないため、回転が必要なら画素を回してから保存し直す
+    if orientation(data) != Orientation::NoTransforms {
+      return encode(&decode(data)?, format, jpeg_quality);
+    }
+    if let Some(stripped) = strip_metadata(data) {
+      return Ok(stripped);
+    }
+  }
+  Ok(data.to_vec())
+}
+
+/// Thumbnail that fits in a `size` x `size` square, encoded as lossless WebP
+pub fn make_thumbnail(data: &[u8], size: u32) -> Result<Vec<u8>, String> {
+  let image = decode(data)?;
+  let image = if image.width().max(image.height()) > size {
+    image.thumbnail(size, size)
+  } else {
+    image
+  };
+  encode(&image, ImageFormat::WebP, 100)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::GenericImageView;
+
+  fn settings() -> AssetSettings {
+    AssetSettings {
+      max_file_size: 50 * 1024 * 1024,
+      process_images: true,
+      max_image_dimension: 100,
+      reencode_threshold: 1024 * 1024,
+      reencode_format: ImageEncoding::Webp,
+      jpeg_quality: 85,
+      strip_exif: true,
+      thumbnail_size: 64,
+    }
+  }
+
+  // 圧縮しにくい模様の画像
+  fn pattern(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+      Rgb([((x * 7) ^ (y * 13)) as u8, (x * y) as u8, (x + y * 3) as u8])
+    }))
+  }
+
+  // SOI の直後に APP1 (EXIF) を差し込む
+  fn with_exif(jpeg: &[u8], orientation: Option<u16>) -> Vec<u8> {
+    let mut tiff = b"MM\0*\0\0\0\x08".to_vec();
+    match orientation {
+      Some(value) => {
+        tiff.extend_from_slice(&[0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1]);
+        tiff.extend_from_slice(&value.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+      }
+      None => tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]),
+    }
+    let mut payload = b"Exif\0\0".to_vec();
+    payload.extend_from_slice(&tiff);
+
+    let mut data = jpeg[..2].to_vec();
+    data.extend_from_slice(&[0xFF, 0xE1]);
+    data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    data.extend_from_slice(&payload);
+    data.extend_from_slice(&jpeg[2..]);
+    data
+  }
+
+  #[test]
+  fn test_process_image_resizes_and_reencodes() {
+    let png = encode(&pattern(300, 200), ImageFormat::Png, 85).unwrap();
+
+    // 大きすぎる画像は元の形式のまま縮小する
+    let resized = process_image(&png, &settings()).unwrap();
+    assert_eq!(image::guess_format(&resized).unwrap(), ImageFormat::Png);
+    assert_eq!(decode(&resized).unwrap().dimensions(), (100, 67));
+
+    // しきい値を超える PNG は指定の形式で保存し直す
+    let small = encode(&pattern(80, 60), ImageFormat::Png, 85).unwrap();
+    let reencoded = process_image(
+      &small,
+      &AssetSettings {
+        reencode_threshold: 0,
+        reencode_format: ImageEncoding::Jpeg,
+        ..settings()
+      },
+    )
+    .unwrap();
+    assert_eq!(image::guess_format(&reencoded).unwrap(), ImageFormat::Jpeg);
+    assert_eq!(decode(&reencoded).unwrap().dimensions(), (80, 60));
+
+    // 処理しない設定や対象外の形式はそのまま
+    let untouched = AssetSettings {
+      process_images: false,
+      ..settings()
+    };
+    assert_eq!(process_image(&png, &untouched).unwrap(), png);
+    assert_eq!(process_image(b"<svg/>", &settings()).unwrap(), b"<svg/>");
+  }
+
+  #[test]
+  fn test_process_image_strips_exif() {
+    let settings = AssetSettings {
+      process_images: false,
+      ..settings()
+    };
+    let jpeg = encode(&pattern(4, 2), ImageFormat::Jpeg, 90).unwrap();
+
+    // 再エンコードせずに EXIF だけを取り除く
+    let stripped = process_image(&with_exif(&jpeg, None), &settings).unwrap();
+    assert_eq!(stripped, jpeg);
+
+    // 向きの指定がある場合は回転してから保存し直す
+    let rotated = process_image(&with_exif(&jpeg, Some(6)), &settings).unwrap();
+    assert!(!rotated.windows(6).any(|w| w == b"Exif\0\0"));
+    assert_eq!(decode(&rotated).unwrap().dimensions(), (2, 4));
+
+    // 取り除かない設定なら残す
+    let kept = process_image(
+      &with_exif(&jpeg, None),
+      &AssetSettings {
+        strip_exif: false,
+        ..settings
+      },
+    )
+    .unwrap();
+    assert_eq!(kept, with_exif(&jpeg, None));
+  }
+
+  #[test]
+  fn test_strip_metadata_rejects_corrupt_jpeg() {
+    // SOI の直後に長さ 0 と 1 のセグメントを持つ壊れた JPEG
+    for length in [0u8, 1] {
+      let corrupt = [0xFF, 0xD8, 0xFF, 0xE1, 0x00, length, 0xFF, 0xD9];
+      assert_eq!(strip_metadata(&corrupt), None);
+    }
+  }
+
+  #[test]
+  fn test_make_thumbnail() {
+    let png = encode(&pattern(300, 200), ImageFormat::Png, 85).unwrap();
+    let thumbnail = make_thumbnail(&png, 64).unwrap();
+    assert_eq!(image::guess_format(&thumbnail).unwrap(), ImageFormat::WebP);
+    assert_eq!(decode(&thumbnail).unwrap().dimensions(), (64, 43));
+
+    // 小さい画像は拡大しない
+    let small = encode(&pattern(10, 20), ImageFormat::Png, 85).unwrap();
+    assert_eq!(
+      decode(&make_thumbnail(&small, 64).unwrap())
+        .unwrap()
+        .dimensions(),
+      (10, 20)
+    );
+  }
+}
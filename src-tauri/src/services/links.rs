@@ -0,0 +1,454 @@
+use crate::db::Database;
+use crate::db::models::{Note, NoteLink};
+use crate::services::{NoteService, atomic, fts};
+use rusqlite::{Connection, Result as SqlResult, params};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub struct LinkService {
+  db: Arc<Database>,
+  base_path: PathBuf,
+}
+
+impl LinkService {
+  pub fn new(db: Arc<Database>, base_path: PathBuf) -> Self {
+    LinkService { db, base_path }
+  }
+
+  /// 本文から `[[...]]` 形式のリンク先を重複なく出現順に取り出す
+  ///
+  /// `[[target|alias]]` や `[[target#heading]]` はリンク先部分のみを返す。
+  pub fn parse_links(content: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("[[") {
+      let after = &rest[start + 2..];
+      let Some(end) = after.find("]]") else {
+        break;
+      };
+      let inner = &after[..end];
+
+      if inner.contains('\n') || inner.contains("[[") {
+        rest = after;
+        continue;
+      }
+
+      let target = Self::normalize_target(inner);
+      if !target.is_empty() && !targets.contains(&target) {
+        targets.push(target);
+      }
+      rest = &after[end + 2..];
+    }
+
+    targets
+  }
+
+  fn normalize_target(inner: &str) -> String {
+    let target = inner.split(['|', '#']).next().unwrap_or_default().trim();
+    target.strip_suffix(".md").unwrap_or(target).to_string()
+  }
+
+  /// ノートディレクトリからの相対パス (拡張子なし、`/` 区切り) を取得
+  pub fn relative_link_path(base_path: &Path, path: &Path) -> String {
+    path
+      .strip_prefix(base_path)
+      .unwrap_or(path)
+      .with_extension("")
+      .to_string_lossy()
+      .replace("\\", "/")
+  }
+
+  /// `[[old]]`、`[[old|...]]`、`[[old#...]]` のリンク先を new に置き換える
+  pub fn rewrite_link_target(content: &str, old: &str, new: &str) -> String {
+    ["]]", "|", "#"]
+      .iter()
+      .fold(content.to_string(), |acc, suffix| {
+        acc.replace(
+          &format!("[[{}{}", old, suffix),
+          &format!("[[{}{}", new, suffix),
+        )
+      })
+  }
+
+  // リンク先の文字列をノートIDに解決する (相対パスを優先し、次にタイトルで探す)
+  fn resolve_target(conn: &Connection, base_path: &Path, target: &str) -> SqlResult<Option<i64>> {
+    let mut path = base_path.to_path_buf();
+    let mut parts = target.split('/').peekable();
+    while let Some(part) = parts.next() {
+      if parts.peek().is_some() {
+        path.push(part);
+      } else {
+        path.push(format!("{}.md", part));
+      }
+    }
+
+    let by_path: Option<i64> = conn
+      .query_row(
+        "SELECT id FROM notes WHERE file_path = ? AND is_deleted = FALSE",
+        params![path.to_str().unwrap_or_default()],
+        |row| row.get(0),
+      )
+      .ok();
+    if by_path.is_some() {
+      return Ok(by_path);
+    }
+
+    let by_title: Option<i64> = conn
+      .query_row(
+        "SELECT id FROM notes WHERE title = ? AND is_deleted = FALSE ORDER BY id LIMIT 1",
+        params![target],
+        |row| row.get(0),
+      )
+      .ok();
+    Ok(by_title)
+  }
+
+  /// ノートのリンクを解析して note_links を更新する
+  pub fn index_note(
+    conn: &Connection,
+    base_path: &Path,
+    note_id: i64,
+    content: &str,
+  ) -> SqlResult<()> {
+    conn.execute(
+      "DELETE FROM note_links WHERE source_note_id = ?",
+      params![note_id],
+    )?;
+
+    for target in Self::parse_links(content) {
+      let target_note_id = Self::resolve_target(conn, base_path, &target)?;
+      conn.execute(
+        "INSERT OR REPLACE INTO note_links (source_note_id, target, target_note_id) VALUES (?, ?, ?)",
+        params![note_id, target, target_note_id],
+      )?;
+    }
+
+    conn.execute(
+      "UPDATE notes SET links_indexed = TRUE WHERE id = ?",
+      params![note_id],
+    )?;
+
+    Ok(())
+  }
+
+  /// 未解決のリンクのうち、このノートを指しているものを解決する
+  pub fn resolve_links_to(
+    conn: &Connection,
+    base_path: &Path,
+    note_id: i64,
+    file_path: &str,
+    title: &str,
+  ) -> SqlResult<()> {
+    let rel = Self::relative_link_path(base_path, Path::new(file_path));
+    conn.execute(
+      "UPDATE note_links SET target_note_id = ? WHERE target_note_id IS NULL AND target IN (?, ?)",
+      params![note_id, rel, title],
+    )?;
+    Ok(())
+  }
+
+  /// まだ索引に含まれていないノートのリンクを解析する
+  ///
+  /// ファイルの読み込み中はデータベースをロックしないため、起動直後にバックグラウンドで実行できる。
+  pub fn ensure_index(&self) -> Result<usize, String> {
+    let pending: Vec<(i64, String)> = {
+      let conn = self.db.conn.lock().unwrap();
+      let mut stmt = conn
+        .prepare(
+          "SELECT id, file_path FROM notes WHERE links_indexed = FALSE AND is_deleted = FALSE AND file_path IS NOT NULL",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
+        .collect::<SqlResult<Vec<(i64, String)>>>()
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
+    };
+
+    let mut indexed = 0;
+    for (id, file_path) in pending {
+      let Ok(content) = fs::read_to_string(&file_path) else {
+        continue;
+      };
+      let conn = self.db.conn.lock().unwrap();
+      Self::index_note(&conn, &self.base_path, id, &content)
+        .map_err(|e| format!("リンクの索引作成に失敗しました: {}", e))?;
+      indexed += 1;
+    }
+
+    Ok(indexed)
+  }
+
+  /// target_ids のいずれかにリンクしているノートの本文を rewrite で書き換える
+  ///
+  /// 書き換えたノートはプレビュー、検索インデックス、リンクの索引も更新する。
+  pub fn rewrite_backlinks<F>(&self, target_ids: &[i64], rewrite: F) -> Result<(), String>
+  where
+    F: Fn(&str) -> String,
+  {
+    if target_ids.is_empty() {
+      return Ok(());
+    }
+
+    let sources: Vec<(i64, String, String)> = {
+      let conn = self.db.conn.lock().unwrap();
+      let placeholders = target_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+      let query = format!(
+        "SELECT DISTINCT n.id, n.title, n.file_path FROM note_links nl
+         JOIN notes n ON n.id = nl.source_note_id
+         WHERE nl.target_note_id IN ({}) AND n.is_deleted = FALSE",
+        placeholders
+      );
+      let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+      let params: Vec<&dyn rusqlite::ToSql> = target_ids
+        .iter()
+        .map(|id| id as &dyn rusqlite::ToSql)
+        .collect();
+      stmt
+        .query_map(params.as_slice(), |row| {
+          Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| format!("バックリンクの取得に失敗しました: {}", e))?
+        .collect::<SqlResult<Vec<(i64, String, String)>>>()
+        .map_err(|e| format!("バックリンクの取得に失敗しました: {}", e))?
+    };
+
+    for (id, title, file_path) in sources {
+      let content = match fs::read_to_string(&file_path) {
+        Ok(c) => c,
+        Err(_) => continue,
+      };
+
+      let new_content = rewrite(&content);
+      if new_content == content {
+        continue;
+      }
+
+      let preview = NoteService::generate_preview(&new_content);
+      let conn = self.db.conn.lock().unwrap();
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+      tx.execute(
+        "UPDATE notes SET preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![preview, id],
+      )
+      .map_err(|e| format!("プレビューの更新に失敗しました ({}): {}", title, e))?;
+      fts::update_content(&tx, id, &new_content)
+        .map_err(|e| format!("検索インデックスの更新に失敗しました ({}): {}", title, e))?;
+      Self::index_note(&tx, &self.base_path, id, &new_content)
+        .map_err(|e| format!("リンクの索引更新に失敗しました ({}): {}", title, e))?;
+
+      atomic::write_file(Path::new(&file_path), &new_content)
+        .map_err(|e| format!("バックリンクの更新に失敗しました ({}): {}", title, e))?;
+      if let Err(e) = tx.commit() {
+        let _ = atomic::write_file(Path::new(&file_path), &content);
+        return Err(format!(
+          "バックリンクの更新に失敗しました ({}): {}",
+          title, e
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  // ノートにリンクしているノートの一覧を取得
+  pub fn get_backlinks(&self, note_id: i64) -> Result<Vec<Note>, String> {
+    let conn = self.db.conn.lock().unwrap();
+    let mut stmt = conn
+      .prepare(
+        "SELECT DISTINCT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at,
+        n.is_favorite, n.favorite_order
+        FROM note_links nl
+        JOIN notes n ON n.id = nl.source_note_id
+        WHERE nl.target_note_id = ? AND n.is_deleted = FALSE
+        ORDER BY n.updated_at DESC",
+      )
+      .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let notes = stmt
+      .query_map(params![note_id], |row| {
+        Ok(Note {
+          id: row.get(0)?,
+          title: row.get(1)?,
+          created_at: row.get(2)?,
+          updated_at: row.get(3)?,
+          parent_id: row.get(4)?,
+          file_path: row.get(5)?,
+          preview: row.get(6)?,
+          is_deleted: row.get(7)?,
+          deleted_at: row.get(8)?,
+          is_favorite: row.get(9)?,
+          favorite_order: row.get(10)?,
+        })
+      })
+      .map_err(|e| format!("バックリンクの取得に失敗しました: {}", e))?
+      .collect::<SqlResult<Vec<Note>>>()
+      .map_err(|e| format!("バックリンクの取得に失敗しました: {}", e))?;
+
+    Ok(notes)
+  }
+
+  // ノートから出ているリンクの一覧を取得 (未解決のリンクも含む)
+  pub fn get_outgoing_links(&self, note_id: i64) -> Result<Vec<NoteLink>, String> {
+    let conn = self.db.conn.lock().unwrap();
+    let mut stmt = conn
+      .prepare(
+        "SELECT nl.target, n.id, n.title
+         FROM note_links nl
+         LEFT JOIN notes n ON n.id = nl.target_note_id AND n.is_deleted = FALSE
+         WHERE nl.source_note_id = ?
+         ORDER BY nl.rowid",
+      )
+      .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let links = stmt
+      .query_map(params![note_id], |row| {
+        Ok(NoteLink {
+          target: row.get(0)?,
+          target_note_id: row.get(1)?,
+          target_title: row.get(2)?,
+        })
+      })
+      .map_err(|e| format!("リンクの取得に失敗しました: {}", e))?
+      .collect::<SqlResult<Vec<NoteLink>>>()
+      .map_err(|e| format!("リンクの取得に失敗しました: {}", e))?;
+
+    Ok(links)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::services::FolderService;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_parse_links() {
+    let links = LinkService::parse_links(
+      "[[ノートA]] と [[フォルダ/ノートB|別名]]、[[ノートC#見出し]]、もう一度 [[ノートA]]\n[[壊れた\n]]",
+    );
+    assert_eq!(links, vec!["ノートA", "フォルダ/ノートB", "ノートC"]);
+  }
+
+  #[test]
+  fn test_backlinks_and_outgoing_links() {
+    let (db, temp_dir) = setup_test_db();
+    let note_service = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let service = LinkService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let source = note_service
+      .create_note(
+        "参照元".to_string(),
+        "[[参照先]] と [[未作成]]".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    // 後から作成されたノートへのリンクも解決される
+    let target = note_service
+      .create_note("参照先".to_string(), "本文".to_string(), None, None)
+      .unwrap();
+
+    let backlinks = service.get_backlinks(target.id).unwrap();
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].id, source.id);
+
+    let outgoing = service.get_outgoing_links(source.id).unwrap();
+    assert_eq!(outgoing.len(), 2);
+    assert_eq!(outgoing[0].target_note_id, Some(target.id));
+    assert_eq!(outgoing[1].target, "未作成");
+    assert_eq!(outgoing[1].target_note_id, None);
+  }
+
+  #[test]
+  fn test_rename_rewrites_only_linking_notes() {
+    let (db, temp_dir) = setup_test_db();
+    let note_service = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let service = LinkService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let target = note_service
+      .create_note("旧タイトル".to_string(), "本文".to_string(), None, None)
+      .unwrap();
+    let source = note_service
+      .create_note(
+        "参照元".to_string(),
+        "[[旧タイトル]] と [[旧タイトル|別名]]".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+
+    note_service
+      .update_note(target.id, "新タイトル".to_string(), "本文".to_string())
+      .unwrap();
+
+    let source = note_service.get_note_by_id(source.id).unwrap();
+    assert_eq!(source.content, "[[新タイトル]] と [[新タイトル|別名]]");
+
+    let backlinks = service.get_backlinks(target.id).unwrap();
+    assert_eq!(backlinks.len(), 1);
+  }
+
+  #[test]
+  fn test_folder_rename_rewrites_links() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().to_path_buf();
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let folder_service = FolderService::new(db.clone(), base_path.clone());
+
+    let folder = folder_service
+      .create_folder("旧フォルダ".to_string(), None, None)
+      .unwrap();
+    note_service
+      .create_note(
+        "中身".to_string(),
+        "本文".to_string(),
+        Some(folder.id),
+        Some("旧フォルダ".to_string()),
+      )
+      .unwrap();
+    let source = note_service
+      .create_note(
+        "参照元".to_string(),
+        "[[旧フォルダ/中身]]".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+
+    folder_service
+      .update_folder(crate::db::models::UpdateFolderInput {
+        id: folder.id,
+        name: "新フォルダ".to_string(),
+        parent_id: None,
+        icon: None,
+        color: None,
+        sort_by: None,
+        sort_order: None,
+        default_template_id: None,
+      })
+      .unwrap();
+
+    let source = note_service.get_note_by_id(source.id).unwrap();
+    assert_eq!(source.content, "[[新フォルダ/中身]]");
+  }
+}
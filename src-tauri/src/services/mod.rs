@@ -1,21 +1,42 @@
 pub mod activity;
+pub mod atomic;
 pub mod backup;
+pub mod daily_note;
+pub mod doctor;
+pub mod filename;
 pub mod files;
 pub mod folder;
+pub mod fts;
 pub mod hotkeys;
+pub mod images;
+pub mod links;
 pub mod note;
 pub mod notification;
+pub mod properties;
+pub mod query;
+pub mod revision;
+pub mod saved_search;
+pub mod search;
 pub mod tags;
 pub mod template;
+pub mod watcher;
 
 pub mod assets;
 
 pub use assets::AssetService;
 pub use backup::BackupService;
+pub use daily_note::DailyNoteService;
+pub use doctor::VaultDoctor;
 pub use files::FileService;
 pub use folder::FolderService;
 pub use hotkeys::HotkeyService;
+pub use links::LinkService;
 pub use note::NoteService;
 pub use notification::NotificationService;
+pub use properties::PropertyService;
+pub use revision::RevisionService;
+pub use saved_search::SavedSearchService;
+pub use search::SearchService;
 pub use tags::TagService;
 pub use template::TemplateService;
+pub use watcher::WatcherService;
@@ -1,6 +1,13 @@
-use crate::db::models::{Note, NoteWithContent};
-use rusqlite::{Result as SqlResult, params};
-use std::{path::PathBuf, sync::Arc};
+use crate::db::models::{
+  Note, NoteFromTemplate, NoteProperties, NoteWithContent, SearchSort, TemplateContext,
+};
+use crate::services::{
+  LinkService, PropertyService, RevisionService, SearchService, TagService, TemplateService,
+  atomic, filename, fts, properties,
+};
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult, params};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::db::Database;
 use std::fs;
@@ -15,6 +22,27 @@ impl NoteService {
     NoteService { base_path, db }
   }
 
+  // 大文字小文字を区別しないファイルシステムでは名前の違うパスが同じファイルを指す
+  fn is_same_file(path: &Path, other: &Path) -> bool {
+    path == other
+      || matches!(
+        (fs::canonicalize(path), fs::canonicalize(other)),
+        (Ok(a), Ok(b)) if a == b
+      )
+  }
+
+  // ファイルが存在するか、削除されていないノートが使っているパスか
+  fn is_path_taken(conn: &Connection, path: &Path) -> bool {
+    path.exists()
+      || conn
+        .query_row(
+          "SELECT EXISTS(SELECT 1 FROM notes WHERE file_path = ? AND is_deleted = FALSE)",
+          params![path.to_str().unwrap_or_default()],
+          |row| row.get(0),
+        )
+        .unwrap_or(false)
+  }
+
   // ノートの作成
   pub fn create_note(
     &self,
@@ -23,72 +51,214 @@ impl NoteService {
     parent_id: Option<i64>,
     folder_path: Option<String>,
   ) -> Result<NoteWithContent, String> {
-    let full_path = self
-      .base_path
-      .join(folder_path.clone().unwrap_or_default())
-      .join(format!("{}.md", title.clone()));
-
-    let file_path_str = full_path.to_str().unwrap_or_default().to_string();
-
-    // 同じパスのノートが既に存在するかチェック（削除されていないもののみ）
-    {
-      let conn = self.db.conn.lock().unwrap();
-      let exists: bool = conn
-        .query_row(
-          "SELECT EXISTS(SELECT 1 FROM notes WHERE file_path = ? AND is_deleted = FALSE)",
-          params![file_path_str],
-          |row| row.get(0),
-        )
-        .unwrap_or(false);
-
-      if exists {
-        return Err(format!(
-          "同じパスのノートが既に存在します: {}",
-          file_path_str
-        ));
-      }
-    }
+    let title = filename::normalize_title(&title);
+    let folder_dir = filename::resolve_within(&self.base_path, &folder_path.unwrap_or_default())?;
+
+    // 本文がなければフォルダの既定のテンプレートを使う
+    let content = match parent_id {
+      Some(parent_id) if content.is_empty() => self
+        .render_folder_template(parent_id, &title)?
+        .unwrap_or(content),
+      _ => content,
+    };
 
-    if let Some(parent) = full_path.parent() {
-      fs::create_dir_all(parent)
-        .map_err(|e| format!("ノートディレクトリの作成に失敗しました: {}", e))?;
-    }
+    fs::create_dir_all(&folder_dir)
+      .map_err(|e| format!("ノートディレクトリの作成に失敗しました: {}", e))?;
 
     let preview = Self::generate_preview(&content);
 
     let note_id = {
       let conn = self.db.conn.lock().unwrap();
 
-      conn
-        .execute(
-          "
+      // 同じパスのノートが既に存在する場合は番号を付ける
+      let full_path = filename::unique_path(
+        &folder_dir,
+        &filename::sanitize_file_name(&title),
+        Some("md"),
+        |path| Self::is_path_taken(&conn, path),
+      );
+      let file_path_str = full_path.to_str().unwrap_or_default().to_string();
+
+      // ファイルの書き込みに失敗した場合はデータベースへの変更も取り消す
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+
+      tx.execute(
+        "
 		    INSERT INTO notes (title, parent_id, file_path, preview)
 		    VALUES (?, ?, ?, ?)
 		    ",
-          params![title, parent_id, file_path_str, preview],
-        )
-        .map_err(|e| format!("ノートの作成に失敗しました: {}", e))?;
-      let id = conn.last_insert_rowid();
+        params![title, parent_id, file_path_str, preview],
+      )
+      .map_err(|e| format!("ノートの作成に失敗しました: {}", e))?;
+      let id = tx.last_insert_rowid();
 
       // Update FTS index
-      conn
-        .execute(
-          "INSERT INTO notes_fts (id, title, content) VALUES (?, ?, ?)",
-          params![id, title, content],
-        )
+      fts::insert(&tx, id, &title, &content)
         .map_err(|e| format!("検索インデックスの更新に失敗しました: {}", e))?;
 
+      LinkService::index_note(&tx, &self.base_path, id, &content)
+        .and_then(|_| {
+          LinkService::resolve_links_to(&tx, &self.base_path, id, &file_path_str, &title)
+        })
+        .map_err(|e| format!("リンクの索引更新に失敗しました: {}", e))?;
+      TagService::index_note(&tx, id, &content)
+        .map_err(|e| format!("タグの更新に失敗しました: {}", e))?;
+      PropertyService::index_note(&tx, id, &content)
+        .map_err(|e| format!("プロパティの索引更新に失敗しました: {}", e))?;
+
+      atomic::write_file(&full_path, &content)
+        .map_err(|e| format!("ノートの作成に失敗しました: {}", e))?;
+
+      if let Err(e) = tx.commit() {
+        let _ = fs::remove_file(&full_path);
+        return Err(format!("ノートの作成に失敗しました: {}", e));
+      }
+
       id
     };
 
-    fs::write(&full_path, content.clone())
-      .map_err(|e| format!("ノートの作成に失敗しました: {}", e))?;
-
     let note = self.get_note_by_id(note_id)?;
 
     Ok(note)
   }
 
+  /// フォルダの既定のテンプレートを探す
+  ///
+  /// フォルダに設定がなければ親フォルダをさかのぼり、最も近いものを使う。
+  pub fn folder_default_template_id(&self, folder_id: i64) -> Result<Option<i64>, String> {
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .query_row(
+        "
+        WITH RECURSIVE ancestors(id, parent_id, template_id, depth) AS (
+          SELECT id, parent_id, default_template_id, 0 FROM folders WHERE id = ?
+          UNION ALL
+          SELECT f.id, f.parent_id, f.default_template_id, a.depth + 1
+          FROM folders f JOIN ancestors a ON f.id = a.parent_id
+        )
+        SELECT a.template_id FROM ancestors a
+        JOIN templates t ON t.id = a.template_id
+        ORDER BY a.depth LIMIT 1
+        ",
+        params![folder_id],
+        |row| row.get(0),
+      )
+      .optional()
+      .map_err(|e| format!("既定のテンプレートの取得に失敗しました: {}", e))
+  }
+
+  // フォルダの既定のテンプレートを展開する (入力を求める変数は空にする)
+  fn render_folder_template(&self, folder_id: i64, title: &str) -> Result<Option<String>, String> {
+    let Some(template_id) = self.folder_default_template_id(folder_id)? else {
+      return Ok(None);
+    };
+    let folder: String = {
+      let conn = self.db.conn.lock().unwrap();
+      conn
+        .query_row(
+          "SELECT name FROM folders WHERE id = ?",
+          params![folder_id],
+          |row| row.get(0),
+        )
+        .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?
+    };
+    let templates = TemplateService::new(self.db.clone());
+    let prompts = templates
+      .get_template_prompts(template_id)?
+      .into_iter()
+      .map(|name| (name, String::new()))
+      .collect();
+    let rendered = templates.render_template(
+      template_id,
+      &TemplateContext {
+        title: Some(title.to_string()),
+        folder: Some(folder),
+        date: None,
+        prompts,
+      },
+    )?;
+    Ok(Some(rendered.content))
+  }
+
+  /// テンプレートの変数を展開してノートを作成する
+  ///
+  /// {{title}} と {{folder}} には作成するノートのタイトルとフォルダ名が入る。
+  pub fn create_note_from_template(
+    &self,
+    template_id: i64,
+    title: String,
+    parent_id: Option<i64>,
+    folder_path: Option<String>,
+    prompts: std::collections::HashMap<String, String>,
+  ) -> Result<NoteFromTemplate, String> {
+    let folder = match parent_id {
+      Some(parent_id) => {
+        let conn = self.db.conn.lock().unwrap();
+        conn
+          .query_row(
+            "SELECT name FROM folders WHERE id = ?",
+            params![parent_id],
+            |row| row.get(0),
+          )
+          .map_err(|e| format!("フォルダの取得に失敗しました: {}", e))?
+      }
+      None => String::new(),
+    };
+    let rendered = TemplateService::new(self.db.clone()).render_template(
+      template_id,
+      &TemplateContext {
+        title: Some(filename::normalize_title(&title)),
+        folder: Some(folder),
+        date: None,
+        prompts,
+      },
+    )?;
+
+    let note = self.create_note(title, rendered.content, parent_id, folder_path)?;
+    Ok(NoteFromTemplate {
+      note,
+      cursor: rendered.cursor,
+    })
+  }
+
+  /// ディスク上に既にあるファイルをノートとして登録する (ファイルには書き込まない)
+  pub fn register_existing_file(
+    conn: &Connection,
+    base_path: &Path,
+    file_path: &Path,
+    parent_id: Option<i64>,
+    content: &str,
+  ) -> SqlResult<i64> {
+    let title = filename::normalize_title(
+      &file_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default(),
+    );
+    let file_path_str = file_path.to_str().unwrap_or_default();
+
+    conn.execute(
+      "INSERT INTO notes (title, parent_id, file_path, preview) VALUES (?, ?, ?, ?)",
+      params![
+        title,
+        parent_id,
+        file_path_str,
+        Self::generate_preview(content)
+      ],
+    )?;
+    let id = conn.last_insert_rowid();
+
+    fts::insert(conn, id, &title, content)?;
+    LinkService::index_note(conn, base_path, id, content)?;
+    LinkService::resolve_links_to(conn, base_path, id, file_path_str, &title)?;
+    TagService::index_note(conn, id, content)?;
+    PropertyService::index_note(conn, id, content)?;
+
+    Ok(id)
+  }
+
   // ノートをidで取得
   pub fn get_note_by_id(&self, id: i64) -> Result<NoteWithContent, String> {
     let conn = self.db.conn.lock().unwrap();
@@ -96,7 +266,7 @@ impl NoteService {
     let note = conn
       .query_row(
         "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.is_deleted, n.deleted_at,
-        EXISTS(SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id WHERE nt.note_id = n.id AND t.name = 'お気に入り') as is_favorite
+        n.is_favorite, n.favorite_order
         FROM notes n WHERE n.id = ?",
         params![id],
         |row| {
@@ -111,7 +281,7 @@ impl NoteService {
             is_deleted: row.get(6)?,
             deleted_at: row.get(7)?,
             is_favorite: row.get(8)?,
-            favorite_order: None,
+            favorite_order: row.get(9)?,
           })
         },
       )
@@ -127,6 +297,7 @@ impl NoteService {
       created_at: note.created_at,
       updated_at: note.updated_at,
       parent_id: note.parent_id,
+      properties: properties::parse_properties(&content).unwrap_or_default(),
       content,
       is_deleted: note.is_deleted,
       deleted_at: note.deleted_at,
@@ -171,7 +342,7 @@ impl NoteService {
     let mut stmt = conn
       .prepare(
         "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at,
-        EXISTS(SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id WHERE nt.note_id = n.id AND t.name = 'お気に入り') as is_favorite
+        n.is_favorite, n.favorite_order
         FROM notes n WHERE n.is_deleted = FALSE ORDER BY n.updated_at DESC",
       )
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
@@ -189,7 +360,7 @@ impl NoteService {
           is_deleted: row.get(7)?,
           deleted_at: row.get(8)?,
           is_favorite: row.get(9)?,
-          favorite_order: None,
+          favorite_order: row.get(10)?,
         })
       })
       .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
@@ -199,158 +370,36 @@ impl NoteService {
     Ok(notes)
   }
 
+  // 関連度順の検索結果をノートだけにしたもの
   pub fn search_notes(&self, query: &str) -> Result<Vec<Note>, String> {
-    // 1. Parse Query
-    let mut text_parts = Vec::new();
-    let mut tags = Vec::new();
-    let mut is_favorite = None;
-
-    for part in query.split_whitespace() {
-      if let Some(tag_name) = part.strip_prefix("tag:") {
-        tags.push(tag_name.to_string());
-      } else if part == "is:favorite" {
-        is_favorite = Some(true);
-      } else if part == "-is:favorite" {
-        is_favorite = Some(false);
-      } else {
-        text_parts.push(part);
-      }
-    }
-    let text_query = text_parts.join(" ");
-
-    // 2. Fetch all notes (base candidates)
-    let mut candidates = self.get_all_notes()?;
-
-    // 3. Filter by is_favorite
-    if let Some(fav) = is_favorite {
-      candidates.retain(|n| n.is_favorite == fav);
-    }
-
-    // 4. Filter by tags
-    if !tags.is_empty() {
-      let conn = self.db.conn.lock().unwrap();
-      // Construct SQL to find IDs that have ALL tags
-      let placeholders = tags.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-      let sql = format!(
-        "SELECT nt.note_id FROM note_tags nt
-              JOIN tags t ON nt.tag_id = t.id
-              WHERE t.name IN ({})
-              GROUP BY nt.note_id
-              HAVING COUNT(DISTINCT t.name) = ?",
-        placeholders
-      );
-
-      let mut params: Vec<&dyn rusqlite::ToSql> =
-        tags.iter().map(|t| t as &dyn rusqlite::ToSql).collect();
-      let count = tags.len() as i64;
-      params.push(&count);
-
-      let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
-      let valid_ids: std::collections::HashSet<i64> = stmt
-        .query_map(params.as_slice(), |row| row.get(0))
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<_>>()
-        .map_err(|e| e.to_string())?;
-
-      candidates.retain(|n| valid_ids.contains(&n.id));
-    }
-
-    // 5. Text Search
-    if text_query.is_empty() {
-      return Ok(candidates);
-    }
-
-    // Use FTS for text search
-    let conn = self.db.conn.lock().unwrap();
-
-    // Construct FTS query: treat spaces as AND
-    let fts_query = text_query
-      .split_whitespace()
-      .map(|s| format!("\"{}\"", s.replace("\"", ""))) // Simple quote escaping
-      .collect::<Vec<_>>()
-      .join(" AND ");
-
-    // If query became empty after processing (e.g. only quotes), return empty
-    if fts_query.is_empty() {
-      return Ok(Vec::new());
-    }
-
-    let mut stmt = conn
-      .prepare("SELECT id FROM notes_fts WHERE notes_fts MATCH ? ORDER BY rank")
-      .map_err(|e| e.to_string())?;
-
-    let fts_ids: std::collections::HashSet<i64> = stmt
-      .query_map(params![fts_query], |row| row.get(0))
-      .map_err(|e| e.to_string())?
-      .filter_map(Result::ok)
-      .collect();
-
-    // Filter candidates by FTS results
-    candidates.retain(|n| fts_ids.contains(&n.id));
-
-    Ok(candidates)
-  }
-
-  fn get_relative_link_path(&self, path: &std::path::Path) -> String {
-    path
-      .strip_prefix(&self.base_path)
-      .unwrap_or(path)
-      .with_extension("")
-      .to_string_lossy()
-      .replace("\\", "/")
+    let hits = SearchService::new(self.db.clone()).search(query, SearchSort::Relevance)?;
+    Ok(hits.into_iter().map(|hit| hit.note).collect())
   }
 
+  // リネーム・移動したノートにリンクしているノートだけを書き換える
   fn update_backlinks(
     &self,
+    id: i64,
     old_path: &std::path::Path,
     new_path: &std::path::Path,
     old_title: &str,
     new_title: &str,
   ) -> Result<(), String> {
-    let old_rel = self.get_relative_link_path(old_path);
-    let new_rel = self.get_relative_link_path(new_path);
+    let old_rel = LinkService::relative_link_path(&self.base_path, old_path);
+    let new_rel = LinkService::relative_link_path(&self.base_path, new_path);
 
     if old_rel == new_rel && old_title == new_title {
       return Ok(());
     }
 
-    let all_notes = self.get_all_notes()?;
-
-    for note in all_notes {
-      let content = match fs::read_to_string(&note.file_path) {
-        Ok(c) => c,
-        Err(_) => continue,
-      };
-
-      let mut new_content = content.clone();
-
-      // Replace [[old_rel]] -> [[new_rel]]
-      let old_link_rel = format!("[[{}]]", old_rel);
-      let new_link_rel = format!("[[{}]]", new_rel);
-      new_content = new_content.replace(&old_link_rel, &new_link_rel);
-
-      // Replace [[old_title]] -> [[new_title]]
+    LinkService::new(self.db.clone(), self.base_path.clone()).rewrite_backlinks(&[id], |content| {
+      let content = LinkService::rewrite_link_target(content, &old_rel, &new_rel);
       if old_rel != old_title {
-        let old_link_title = format!("[[{}]]", old_title);
-        let new_link_title = format!("[[{}]]", new_title);
-        new_content = new_content.replace(&old_link_title, &new_link_title);
-      }
-
-      if new_content != content {
-        fs::write(&note.file_path, &new_content)
-          .map_err(|e| format!("バックリンクの更新に失敗しました ({}): {}", note.title, e))?;
-
-        let preview = Self::generate_preview(&new_content);
-        let conn = self.db.conn.lock().unwrap();
-        conn
-          .execute(
-            "UPDATE notes SET preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-            params![preview, note.id],
-          )
-          .map_err(|e| format!("プレビューの更新に失敗しました ({}): {}", note.title, e))?;
+        LinkService::rewrite_link_target(&content, old_title, new_title)
+      } else {
+        content
       }
-    }
-    Ok(())
+    })
   }
 
   // ノートの更新
@@ -360,12 +409,13 @@ impl NoteService {
     title: String,
     content: String,
   ) -> Result<NoteWithContent, String> {
+    let title = filename::normalize_title(&title);
     let old_note: Note = {
       let conn = self.db.conn.lock().unwrap();
       conn
         .query_row(
           "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.is_deleted, n.deleted_at,
-          EXISTS(SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id WHERE nt.note_id = n.id AND t.name = 'お気に入り') as is_favorite
+          n.is_favorite, n.favorite_order
           FROM notes n WHERE n.id = ?",
           params![id],
           |row| {
@@ -380,7 +430,7 @@ impl NoteService {
               is_deleted: row.get(6)?,
               deleted_at: row.get(7)?,
               is_favorite: row.get(8)?,
-              favorite_order: None,
+              favorite_order: row.get(9)?,
             })
           },
         )
@@ -388,51 +438,90 @@ impl NoteService {
     };
 
     let old_path = PathBuf::from(&old_note.file_path);
-    let new_path = if let Some(parent) = old_path.parent() {
-      parent.join(format!("{}.md", title))
-    } else {
-      PathBuf::from(format!("{}.md", title))
-    };
-
-    if old_path != new_path {
-      fs::rename(&old_path, &new_path)
-        .map_err(|e| format!("ノートファイルのリネームに失敗しました: {}", e))?;
-    }
-
-    let new_path_str = new_path.to_str().unwrap_or_default().to_string();
+    let old_content = fs::read_to_string(&old_path).ok();
     let preview = Self::generate_preview(&content);
 
-    let updated_at = {
+    let (new_path, updated_at) = {
       let conn = self.db.conn.lock().unwrap();
-      conn
-        .execute(
-          "UPDATE notes SET title = ?, file_path = ?, preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-          params![title, new_path_str, preview, id],
-        )
-        .map_err(|e| format!("ノートの更新に失敗しました: {}", e))?;
+
+      let new_path = filename::unique_path(
+        old_path.parent().unwrap_or(&self.base_path),
+        &filename::sanitize_file_name(&title),
+        Some("md"),
+        |path| !Self::is_same_file(path, &old_path) && Self::is_path_taken(&conn, path),
+      );
+      let new_path_str = new_path.to_str().unwrap_or_default().to_string();
+
+      // ファイルの書き込みに失敗した場合はデータベースへの変更も取り消す
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+
+      // 上書きする前の内容を履歴として残す
+      if let Some(old_content) = &old_content
+        && (*old_content != content || old_note.title != title)
+      {
+        RevisionService::record_revision(&tx, id, &old_note.title, old_content, false)
+          .map_err(|e| format!("履歴の保存に失敗しました: {}", e))?;
+      }
+
+      tx.execute(
+        "UPDATE notes SET title = ?, file_path = ?, preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![title, new_path_str, preview, id],
+      )
+      .map_err(|e| format!("ノートの更新に失敗しました: {}", e))?;
 
       // Update FTS index
-      conn
-        .execute(
-          "UPDATE notes_fts SET title = ?, content = ? WHERE id = ?",
-          params![title, content, id],
-        )
+      fts::update(&tx, id, &title, &content)
         .map_err(|e| format!("検索インデックスの更新に失敗しました: {}", e))?;
 
-      conn
+      LinkService::index_note(&tx, &self.base_path, id, &content)
+        .and_then(|_| {
+          LinkService::resolve_links_to(&tx, &self.base_path, id, &new_path_str, &title)
+        })
+        .map_err(|e| format!("リンクの索引更新に失敗しました: {}", e))?;
+      TagService::index_note(&tx, id, &content)
+        .map_err(|e| format!("タグの更新に失敗しました: {}", e))?;
+      PropertyService::index_note(&tx, id, &content)
+        .map_err(|e| format!("プロパティの索引更新に失敗しました: {}", e))?;
+
+      let updated_at: String = tx
         .query_row(
           "SELECT updated_at FROM notes WHERE id = ?",
           params![id],
           |row| row.get(0),
         )
-        .map_err(|e| format!("更新日時の取得に失敗しました: {}", e))?
-    };
+        .map_err(|e| format!("更新日時の取得に失敗しました: {}", e))?;
 
-    fs::write(&new_path, content.clone())
-      .map_err(|e| format!("ノートの更新に失敗しました: {}", e))?;
+      if old_path != new_path {
+        fs::rename(&old_path, &new_path)
+          .map_err(|e| format!("ノートファイルのリネームに失敗しました: {}", e))?;
+      }
+
+      let committed = atomic::write_file(&new_path, &content)
+        .map_err(|e| format!("ノートの更新に失敗しました: {}", e))
+        .and_then(|_| {
+          tx.commit()
+            .map_err(|e| format!("ノートの更新に失敗しました: {}", e))
+        });
+
+      // 失敗した場合はファイルも元に戻す
+      if let Err(e) = committed {
+        if let Some(old_content) = &old_content {
+          let _ = atomic::write_file(&new_path, old_content);
+        }
+        if old_path != new_path {
+          let _ = fs::rename(&new_path, &old_path);
+        }
+        return Err(e);
+      }
+
+      (new_path, updated_at)
+    };
+    let new_path_str = new_path.to_str().unwrap_or_default().to_string();
 
     // バックリンクの更新
-    self.update_backlinks(&old_path, &new_path, &old_note.title, &title)?;
+    self.update_backlinks(id, &old_path, &new_path, &old_note.title, &title)?;
 
     Ok(NoteWithContent {
       id: old_note.id,
@@ -441,12 +530,24 @@ impl NoteService {
       updated_at,
       parent_id: old_note.parent_id,
       file_path: new_path_str.to_string(),
+      properties: properties::parse_properties(&content).unwrap_or_default(),
       content,
       is_deleted: old_note.is_deleted,
       deleted_at: old_note.deleted_at,
     })
   }
 
+  /// front matter のプロパティを書き換える (本文はそのまま)
+  pub fn update_note_properties(
+    &self,
+    id: i64,
+    properties: NoteProperties,
+  ) -> Result<NoteWithContent, String> {
+    let note = self.get_note_by_id(id)?;
+    let content = properties::replace_front_matter(&note.content, &properties)?;
+    self.update_note(id, note.title, content)
+  }
+
   // ノートの削除 (論理削除 + trashフォルダに移動)
   pub fn delete_note(&self, id: i64) -> Result<(), String> {
     // ノート情報を取得
@@ -464,7 +565,7 @@ impl NoteService {
     let note_path = PathBuf::from(&file_path);
 
     // ファイルが存在する場合、trashフォルダに移動
-    if note_path.exists() {
+    let trash_path = if note_path.exists() {
       // trashフォルダのパスを作成
       let trash_base = self.base_path.join(".trash");
 
@@ -481,24 +582,37 @@ impl NoteService {
           .map_err(|e| format!("trashディレクトリの作成に失敗しました: {}", e))?;
       }
 
-      // ファイルを移動
-      fs::rename(&note_path, &trash_path)
-        .map_err(|e| format!("ファイルのtrashへの移動に失敗しました: {}", e))?;
-    }
+      Some(trash_path)
+    } else {
+      None
+    };
 
-    // データベースで論理削除
+    // データベースで論理削除し、ファイルの移動に失敗した場合は取り消す
     let conn = self.db.conn.lock().unwrap();
-    conn
-      .execute(
-        "UPDATE notes SET is_deleted = TRUE, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
-        params![id],
-      )
-      .map_err(|e| format!("ノートの削除に失敗しました: {}", e))?;
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+    tx.execute(
+      "UPDATE notes SET is_deleted = TRUE, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
+      params![id],
+    )
+    .map_err(|e| format!("ノートの削除に失敗しました: {}", e))?;
 
     // FTSから削除
-    conn
-      .execute("DELETE FROM notes_fts WHERE id = ?", params![id])
-      .ok();
+    fts::remove(&tx, id).ok();
+
+    // ファイルを移動
+    if let Some(trash_path) = &trash_path {
+      fs::rename(&note_path, trash_path)
+        .map_err(|e| format!("ファイルのtrashへの移動に失敗しました: {}", e))?;
+    }
+
+    if let Err(e) = tx.commit() {
+      if let Some(trash_path) = &trash_path {
+        let _ = fs::rename(trash_path, &note_path);
+      }
+      return Err(format!("ノートの削除に失敗しました: {}", e));
+    }
 
     Ok(())
   }
@@ -535,9 +649,7 @@ impl NoteService {
       .map_err(|e| format!("ノートの削除に失敗しました: {}", e))?;
 
     // FTSから削除
-    conn
-      .execute("DELETE FROM notes_fts WHERE id = ?", params![id])
-      .ok();
+    fts::remove(&conn, id).ok();
 
     Ok(())
   }
@@ -611,11 +723,12 @@ impl NoteService {
           })
           .unwrap_or_default();
 
-        conn
-          .execute(
-            "INSERT INTO notes_fts (id, title, content) VALUES (?, ?, ?)",
-            params![id, title, content],
-          )
+        fts::insert(&conn, id, &title, &content).ok();
+        // 削除中に作成されたノートとのリンクを解決し直す
+        LinkService::index_note(&conn, &self.base_path, id, &content)
+          .and_then(|_| {
+            LinkService::resolve_links_to(&conn, &self.base_path, id, &file_path, &title)
+          })
           .ok();
       }
     }
@@ -630,7 +743,7 @@ impl NoteService {
     let mut stmt = conn
       .prepare(
         "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at,
-        EXISTS(SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id WHERE nt.note_id = n.id AND t.name = 'お気に入り') as is_favorite
+        n.is_favorite, n.favorite_order
         FROM notes n WHERE n.is_deleted = TRUE ORDER BY n.deleted_at DESC",
       )
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
@@ -648,7 +761,7 @@ impl NoteService {
           is_deleted: row.get(7)?,
           deleted_at: row.get(8)?,
           is_favorite: row.get(9)?,
-          favorite_order: None,
+          favorite_order: row.get(10)?,
         })
       })
       .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
@@ -665,7 +778,7 @@ impl NoteService {
       conn
         .query_row(
           "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at,
-          EXISTS(SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id WHERE nt.note_id = n.id AND t.name = 'お気に入り') as is_favorite
+          n.is_favorite, n.favorite_order
           FROM notes n WHERE n.id = ?",
           params![id],
           |row| {
@@ -680,7 +793,7 @@ impl NoteService {
               is_deleted: row.get(7)?,
               deleted_at: row.get(8)?,
               is_favorite: row.get(9)?,
-              favorite_order: None,
+              favorite_order: row.get(10)?,
             })
           },
         )
@@ -705,40 +818,73 @@ impl NoteService {
     let file_name = old_path
       .file_name()
       .ok_or_else(|| "ファイル名の取得に失敗しました".to_string())?;
-    let new_path = new_folder_path.join(file_name);
-
-    if old_path != new_path {
-      if let Some(parent) = new_path.parent() {
-        fs::create_dir_all(parent)
-          .map_err(|e| format!("ディレクトリの作成に失敗しました: {}", e))?;
-      }
+    let new_path = if new_folder_path.join(file_name) == old_path {
+      old_path.clone()
+    } else {
+      // 移動先に同じ名前のノートがある場合は番号を付ける
+      let conn = self.db.conn.lock().unwrap();
+      filename::unique_path(
+        &new_folder_path,
+        &old_path
+          .file_stem()
+          .map(|s| s.to_string_lossy().to_string())
+          .unwrap_or_default(),
+        Some("md"),
+        |path| Self::is_path_taken(&conn, path),
+      )
+    };
 
-      fs::rename(&old_path, &new_path)
-        .map_err(|e| format!("ノートファイルの移動に失敗しました: {}", e))?;
+    if old_path != new_path
+      && let Some(parent) = new_path.parent()
+    {
+      fs::create_dir_all(parent).map_err(|e| format!("ディレクトリの作成に失敗しました: {}", e))?;
     }
 
     let new_path_str = new_path.to_str().unwrap_or_default().to_string();
 
     let updated_at = {
       let conn = self.db.conn.lock().unwrap();
-      conn
-        .execute(
-          "UPDATE notes SET parent_id = ?, file_path = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-          params![new_parent_id, new_path_str, id],
-        )
-        .map_err(|e| format!("ノートの移動に失敗しました: {}", e))?;
 
-      conn
+      // ファイルの移動に失敗した場合はデータベースへの変更も取り消す
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+
+      tx.execute(
+        "UPDATE notes SET parent_id = ?, file_path = ?, position = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![new_parent_id, new_path_str, id],
+      )
+      .map_err(|e| format!("ノートの移動に失敗しました: {}", e))?;
+
+      let updated_at: String = tx
         .query_row(
           "SELECT updated_at FROM notes WHERE id = ?",
           params![id],
           |row| row.get(0),
         )
-        .map_err(|e| format!("更新日時の取得に失敗しました: {}", e))?
+        .map_err(|e| format!("更新日時の取得に失敗しました: {}", e))?;
+
+      LinkService::resolve_links_to(&tx, &self.base_path, id, &new_path_str, &old_note.title)
+        .map_err(|e| format!("リンクの索引更新に失敗しました: {}", e))?;
+
+      if old_path != new_path {
+        fs::rename(&old_path, &new_path)
+          .map_err(|e| format!("ノートファイルの移動に失敗しました: {}", e))?;
+      }
+
+      // コミットに失敗した場合はファイルも元に戻す
+      if let Err(e) = tx.commit() {
+        if old_path != new_path {
+          let _ = fs::rename(&new_path, &old_path);
+        }
+        return Err(format!("ノートの移動に失敗しました: {}", e));
+      }
+
+      updated_at
     };
 
     // バックリンクの更新
-    self.update_backlinks(&old_path, &new_path, &old_note.title, &old_note.title)?;
+    self.update_backlinks(id, &old_path, &new_path, &old_note.title, &old_note.title)?;
 
     Ok(Note {
       id: old_note.id,
@@ -755,48 +901,50 @@ impl NoteService {
     })
   }
 
-  // お気に入りのトグル
-  pub fn toggle_favorite(&self, id: i64) -> Result<Note, String> {
-    let conn = self.db.conn.lock().unwrap();
-
-    // 'お気に入り'タグのIDを取得
-    let tag_id: i64 = conn
-      .query_row(
-        "SELECT id FROM tags WHERE name = 'お気に入り'",
-        [],
-        |row| row.get(0),
-      )
-      .map_err(|e| format!("'お気に入り'タグが見つかりません: {}", e))?;
+  // お気に入りに追加・解除する (追加したノートは末尾に並べる)
+  fn set_favorite(conn: &Connection, id: i64, favorite: bool) -> SqlResult<()> {
+    if favorite {
+      conn.execute(
+        "UPDATE notes SET is_favorite = TRUE,
+        favorite_order = (SELECT COALESCE(MAX(favorite_order) + 1, 0) FROM notes WHERE is_favorite = TRUE)
+        WHERE id = ? AND is_favorite = FALSE",
+        params![id],
+      )?;
+    } else {
+      conn.execute(
+        "UPDATE notes SET is_favorite = FALSE, favorite_order = NULL WHERE id = ?",
+        params![id],
+      )?;
+    }
+    Ok(())
+  }
 
-    // 現在のタグ付け状態を確認
-    let exists: bool = conn
+  fn is_favorite(conn: &Connection, id: i64) -> Result<bool, String> {
+    conn
       .query_row(
-        "SELECT EXISTS(SELECT 1 FROM note_tags WHERE note_id = ? AND tag_id = ?)",
-        params![id, tag_id],
+        "SELECT is_favorite FROM notes WHERE id = ?",
+        params![id],
         |row| row.get(0),
       )
-      .unwrap_or(false);
+      .map_err(|e| format!("ノートの取得に失敗しました: {}", e))
+  }
 
-    if exists {
-      // 削除
-      conn
-        .execute(
-          "DELETE FROM note_tags WHERE note_id = ? AND tag_id = ?",
-          params![id, tag_id],
-        )
-        .map_err(|e| format!("お気に入りの解除に失敗しました: {}", e))?;
-    } else {
-      // 追加
+  // お気に入りのトグル
+  pub fn toggle_favorite(&self, id: i64) -> Result<Note, String> {
+    let (is_favorite, favorite_order) = {
+      let conn = self.db.conn.lock().unwrap();
+      let favorite = !Self::is_favorite(&conn, id)?;
+      Self::set_favorite(&conn, id, favorite)
+        .map_err(|e| format!("お気に入りの更新に失敗しました: {}", e))?;
       conn
-        .execute(
-          "INSERT INTO note_tags (note_id, tag_id) VALUES (?, ?)",
-          params![id, tag_id],
+        .query_row(
+          "SELECT is_favorite, favorite_order FROM notes WHERE id = ?",
+          params![id],
+          |row| Ok((row.get(0)?, row.get(1)?)),
         )
-        .map_err(|e| format!("お気に入りの追加に失敗しました: {}", e))?;
-    }
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
+    };
 
-    // 更新されたノートを取得
-    drop(conn); // ロックを解放して get_note_by_id を呼ぶ
     let note_with_content = self.get_note_by_id(id)?;
 
     Ok(Note {
@@ -809,65 +957,39 @@ impl NoteService {
       preview: String::new(),
       is_deleted: note_with_content.is_deleted,
       deleted_at: note_with_content.deleted_at,
-      is_favorite: !exists, // Toggle result
-      favorite_order: None,
+      is_favorite,
+      favorite_order,
     })
   }
 
   // 複数ノートのお気に入りトグル
   pub fn toggle_favorite_notes(&self, ids: Vec<i64>) -> Result<(), String> {
     let conn = self.db.conn.lock().unwrap();
-
-    let tag_id: i64 = conn
-      .query_row(
-        "SELECT id FROM tags WHERE name = 'お気に入り'",
-        [],
-        |row| row.get(0),
-      )
-      .map_err(|e| format!("'お気に入り'タグが見つかりません: {}", e))?;
-
-    let mut stmt_check = conn
-      .prepare("SELECT EXISTS(SELECT 1 FROM note_tags WHERE note_id = ? AND tag_id = ?)")
-      .map_err(|e| e.to_string())?;
-    let mut stmt_delete = conn
-      .prepare("DELETE FROM note_tags WHERE note_id = ? AND tag_id = ?")
-      .map_err(|e| e.to_string())?;
-    let mut stmt_insert = conn
-      .prepare("INSERT INTO note_tags (note_id, tag_id) VALUES (?, ?)")
-      .map_err(|e| e.to_string())?;
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
 
     for id in ids {
-      let exists: bool = stmt_check
-        .query_row(params![id, tag_id], |row| row.get(0))
-        .unwrap_or(false);
-
-      if exists {
-        stmt_delete
-          .execute(params![id, tag_id])
-          .map_err(|e| e.to_string())?;
-      } else {
-        stmt_insert
-          .execute(params![id, tag_id])
-          .map_err(|e| e.to_string())?;
-      }
+      let favorite = !Self::is_favorite(&tx, id)?;
+      Self::set_favorite(&tx, id, favorite)
+        .map_err(|e| format!("お気に入りの更新に失敗しました: {}", e))?;
     }
 
-    Ok(())
+    tx.commit()
+      .map_err(|e| format!("お気に入りの更新に失敗しました: {}", e))
   }
 
-  // お気に入りノート一覧を取得
+  // お気に入りノート一覧を取得 (favorite_order の順)
   pub fn get_favorite_notes(&self) -> Result<Vec<Note>, String> {
     let conn = self.db.conn.lock().unwrap();
 
     let mut stmt = conn
       .prepare(
         "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at,
-        TRUE as is_favorite
+        n.is_favorite, n.favorite_order
         FROM notes n
-        JOIN note_tags nt ON n.id = nt.note_id
-        JOIN tags t ON nt.tag_id = t.id
-        WHERE t.name = 'お気に入り' AND n.is_deleted = FALSE
-        ORDER BY nt.created_at ASC",
+        WHERE n.is_favorite = TRUE AND n.is_deleted = FALSE
+        ORDER BY n.favorite_order ASC, n.id ASC",
       )
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
@@ -884,7 +1006,7 @@ impl NoteService {
           is_deleted: row.get(7)?,
           deleted_at: row.get(8)?,
           is_favorite: row.get(9)?,
-          favorite_order: None,
+          favorite_order: row.get(10)?,
         })
       })
       .map_err(|e| format!("お気に入りノートの取得に失敗しました: {}", e))?
@@ -894,6 +1016,71 @@ impl NoteService {
     Ok(notes)
   }
 
+  // ゴミ箱にあるものも含めたお気に入りの id (並び順)
+  fn favorite_ids(conn: &Connection) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+      .prepare("SELECT id FROM notes WHERE is_favorite = TRUE ORDER BY favorite_order ASC, id ASC")
+      .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    stmt
+      .query_map([], |row| row.get(0))
+      .map_err(|e| format!("お気に入りノートの取得に失敗しました: {}", e))?
+      .collect::<SqlResult<Vec<i64>>>()
+      .map_err(|e| format!("お気に入りノートの取得に失敗しました: {}", e))
+  }
+
+  fn write_favorite_order(conn: &Connection, ids: &[i64]) -> Result<(), String> {
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+    for (order, id) in ids.iter().enumerate() {
+      tx.execute(
+        "UPDATE notes SET favorite_order = ? WHERE id = ?",
+        params![order as i64, id],
+      )
+      .map_err(|e| format!("お気に入りの並び替えに失敗しました: {}", e))?;
+    }
+    tx.commit()
+      .map_err(|e| format!("お気に入りの並び替えに失敗しました: {}", e))
+  }
+
+  /// お気に入りを ids の順に並べ替える
+  ///
+  /// ids に含まれないお気に入りは、今の順番のまま後ろに並べる。
+  pub fn reorder_favorites(&self, ids: &[i64]) -> Result<(), String> {
+    let conn = self.db.conn.lock().unwrap();
+    let current = Self::favorite_ids(&conn)?;
+
+    for (i, id) in ids.iter().enumerate() {
+      if !current.contains(id) {
+        return Err(format!("お気に入りではないノートです: {}", id));
+      }
+      if ids[..i].contains(id) {
+        return Err(format!("同じノートが複数回指定されています: {}", id));
+      }
+    }
+
+    let order: Vec<i64> = ids
+      .iter()
+      .copied()
+      .chain(current.into_iter().filter(|id| !ids.contains(id)))
+      .collect();
+    Self::write_favorite_order(&conn, &order)
+  }
+
+  // お気に入りの並び順を更新 (order 番目に移動する)
+  pub fn update_favorite_order(&self, id: i64, order: i64) -> Result<(), String> {
+    let conn = self.db.conn.lock().unwrap();
+    let mut ids = Self::favorite_ids(&conn)?;
+    let index = ids
+      .iter()
+      .position(|favorite| *favorite == id)
+      .ok_or_else(|| format!("お気に入りではないノートです: {}", id))?;
+    ids.remove(index);
+    let order = order.clamp(0, ids.len() as i64) as usize;
+    ids.insert(order, id);
+    Self::write_favorite_order(&conn, &ids)
+  }
+
   // ノートのインポート
   pub fn import_note(
     &self,
@@ -961,12 +1148,6 @@ impl NoteService {
 
     Ok(imported_notes)
   }
-
-  // お気に入りの並び順を更新
-  pub fn update_favorite_order(&self, _id: i64, _order: i64) -> Result<(), String> {
-    // タグベースの実装では順序はサポートしない
-    Ok(())
-  }
 }
 
 #[cfg(test)]
@@ -1132,6 +1313,246 @@ mod tests {
 
     // FTS検索は英語の方が確実に動作する
     let results = service.search_notes("test").unwrap();
-    assert!(results.len() >= 1);
+    assert!(!results.is_empty());
+  }
+
+  #[test]
+  fn test_create_note_sanitizes_file_name() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let service = NoteService::new(db, base_path.clone());
+
+    // タイトルはそのまま、ファイル名だけ安全な名前になる
+    let note = service
+      .create_note("../会議: 9/1".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    assert_eq!(note.title, "../会議: 9/1");
+    assert_eq!(
+      PathBuf::from(&note.file_path),
+      base_path.join("_会議_ 9_1.md")
+    );
+
+    // 同じ名前のノートには番号が付く
+    let first = service
+      .create_note("メモ".to_string(), "1".to_string(), None, None)
+      .unwrap();
+    let second = service
+      .create_note("メモ".to_string(), "2".to_string(), None, None)
+      .unwrap();
+    assert_eq!(PathBuf::from(&first.file_path), base_path.join("メモ.md"));
+    assert_eq!(
+      PathBuf::from(&second.file_path),
+      base_path.join("メモ (2).md")
+    );
+    assert_eq!(second.title, "メモ");
+
+    // 番号付きのノートを保存し直してもファイル名は変わらない
+    let updated = service
+      .update_note(second.id, "メモ".to_string(), "更新".to_string())
+      .unwrap();
+    assert_eq!(updated.file_path, second.file_path);
+
+    // ノートディレクトリの外には作成できない
+    let result = service.create_note(
+      "外".to_string(),
+      "内容".to_string(),
+      None,
+      Some("../outside".to_string()),
+    );
+    assert!(result.is_err());
+    assert!(!temp_dir.path().join("outside").exists());
+  }
+
+  fn count_rows(db: &Database, query: &str) -> i64 {
+    let conn = db.conn.lock().unwrap();
+    conn.query_row(query, [], |row| row.get(0)).unwrap()
+  }
+
+  #[test]
+  fn test_create_note_rolls_back_on_write_failure() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let service = NoteService::new(db.clone(), base_path.clone());
+
+    atomic::set_fail_writes(true);
+    let result = service.create_note(
+      "失敗するノート".to_string(),
+      "[[リンク先]]".to_string(),
+      None,
+      None,
+    );
+    atomic::set_fail_writes(false);
+    assert!(result.is_err());
+
+    // データベースにもディスクにも何も残らない
+    assert_eq!(count_rows(&db, "SELECT COUNT(*) FROM notes"), 0);
+    assert_eq!(count_rows(&db, "SELECT COUNT(*) FROM notes_fts"), 0);
+    assert_eq!(count_rows(&db, "SELECT COUNT(*) FROM note_links"), 0);
+    assert_eq!(fs::read_dir(&base_path).unwrap().count(), 0);
+  }
+
+  #[test]
+  fn test_update_note_rolls_back_on_write_failure() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let service = NoteService::new(db.clone(), base_path.clone());
+
+    let note = service
+      .create_note(
+        "元のタイトル".to_string(),
+        "元の内容".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+
+    atomic::set_fail_writes(true);
+    let result = service.update_note(
+      note.id,
+      "新しいタイトル".to_string(),
+      "新しい内容".to_string(),
+    );
+    atomic::set_fail_writes(false);
+    assert!(result.is_err());
+
+    // ファイル名・内容・データベース・検索インデックス・履歴がすべて元のまま
+    let current = service.get_note_by_id(note.id).unwrap();
+    assert_eq!(current.title, "元のタイトル");
+    assert_eq!(current.file_path, note.file_path);
+    assert_eq!(current.content, "元の内容");
+    assert!(!base_path.join("新しいタイトル.md").exists());
+    assert_eq!(fs::read_dir(&base_path).unwrap().count(), 1);
+    assert_eq!(service.search_notes("新しい内容").unwrap().len(), 0);
+    assert_eq!(service.search_notes("元の内容").unwrap().len(), 1);
+    assert_eq!(count_rows(&db, "SELECT COUNT(*) FROM note_revisions"), 0);
+  }
+
+  #[test]
+  fn test_move_note_rolls_back_on_failure() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let service = NoteService::new(db.clone(), base_path.clone());
+
+    let note = service
+      .create_note("移動".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    let folder_path = base_path.join("移動先");
+    fs::create_dir_all(&folder_path).unwrap();
+    let folder_id = {
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute(
+          "INSERT INTO folders (name, folder_path) VALUES ('移動先', ?)",
+          params![folder_path.to_str().unwrap()],
+        )
+        .unwrap();
+      conn.last_insert_rowid()
+    };
+
+    // データベースの更新に失敗した場合はファイルを動かさない
+    {
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute_batch(
+          "CREATE TEMP TRIGGER fail_move BEFORE UPDATE OF file_path ON notes
+          BEGIN SELECT RAISE(ABORT, 'injected failure'); END;",
+        )
+        .unwrap();
+    }
+    assert!(service.move_note(note.id, Some(folder_id)).is_err());
+    assert!(Path::new(&note.file_path).exists());
+    assert!(!folder_path.join("移動.md").exists());
+    {
+      let conn = db.conn.lock().unwrap();
+      conn.execute_batch("DROP TRIGGER fail_move").unwrap();
+    }
+
+    // ファイルの移動に失敗した場合はデータベースへの変更を取り消す
+    let moved_away = temp_dir.path().join("退避.md");
+    fs::rename(&note.file_path, &moved_away).unwrap();
+    assert!(service.move_note(note.id, Some(folder_id)).is_err());
+    fs::rename(&moved_away, &note.file_path).unwrap();
+
+    let current = service.get_note_by_id(note.id).unwrap();
+    assert_eq!(current.file_path, note.file_path);
+    assert_eq!(current.parent_id, None);
+
+    let moved = service.move_note(note.id, Some(folder_id)).unwrap();
+    assert_eq!(moved.parent_id, Some(folder_id));
+    assert!(folder_path.join("移動.md").exists());
+  }
+
+  #[test]
+  fn test_delete_note_rolls_back_on_failure() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let service = NoteService::new(db.clone(), base_path.clone());
+
+    let note = service
+      .create_note("削除".to_string(), "消す内容".to_string(), None, None)
+      .unwrap();
+
+    // trash に同じ名前の空でないディレクトリがあると移動に失敗する
+    let blocker = base_path.join(".trash").join("削除.md");
+    fs::create_dir_all(blocker.join("中身")).unwrap();
+    assert!(service.delete_note(note.id).is_err());
+
+    let current = service.get_note_by_id(note.id).unwrap();
+    assert!(!current.is_deleted);
+    assert_eq!(current.content, "消す内容");
+    assert_eq!(service.search_notes("消す内容").unwrap().len(), 1);
+
+    fs::remove_dir_all(&blocker).unwrap();
+    service.delete_note(note.id).unwrap();
+    assert!(!Path::new(&note.file_path).exists());
+    assert!(blocker.exists());
+  }
+
+  #[test]
+  fn test_favorite_order() {
+    let (db, temp_dir) = setup_test_db();
+    let service = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let ids: Vec<i64> = ["A", "B", "C"]
+      .iter()
+      .map(|title| {
+        service
+          .create_note(title.to_string(), String::new(), None, None)
+          .unwrap()
+          .id
+      })
+      .collect();
+    let favorite_ids = |service: &NoteService| -> Vec<i64> {
+      service
+        .get_favorite_notes()
+        .unwrap()
+        .iter()
+        .map(|n| n.id)
+        .collect()
+    };
+
+    // 追加した順に末尾へ並ぶ
+    let toggled = service.toggle_favorite(ids[1]).unwrap();
+    assert!(toggled.is_favorite);
+    service.toggle_favorite_notes(vec![ids[2], ids[0]]).unwrap();
+    assert_eq!(favorite_ids(&service), vec![ids[1], ids[2], ids[0]]);
+
+    service.reorder_favorites(&[ids[0]]).unwrap();
+    assert_eq!(favorite_ids(&service), vec![ids[0], ids[1], ids[2]]);
+    service.update_favorite_order(ids[0], 10).unwrap();
+    assert_eq!(favorite_ids(&service), vec![ids[1], ids[2], ids[0]]);
+
+    // get_all_notes と同じ状態を返す
+    let all = service.get_all_notes().unwrap();
+    let first = all.iter().find(|n| n.id == ids[1]).unwrap();
+    assert!(first.is_favorite);
+    assert_eq!(first.favorite_order, Some(0));
+
+    let untoggled = service.toggle_favorite(ids[1]).unwrap();
+    assert!(!untoggled.is_favorite);
+    assert_eq!(untoggled.favorite_order, None);
+    assert!(service.reorder_favorites(&[ids[1]]).is_err());
+    assert!(service.reorder_favorites(&[ids[0], ids[0]]).is_err());
+    assert_eq!(favorite_ids(&service), vec![ids[2], ids[0]]);
   }
 }
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use rusqlite::{Connection, Result as SqlResult, params};
+use serde_yaml::{Mapping, Value};
+
+use crate::db::Database;
+use crate::db::models::{NoteProperties, NoteProperty};
+use crate::services::tags::normalize_tag_name;
+
+/// 先頭の YAML front matter (`---` で囲まれた部分) と本文に分ける
+///
+/// front matter がなければ None と本文全体を返す。
+pub fn split_front_matter(content: &str) -> (Option<&str>, &str) {
+  let Some(rest) = content
+    .strip_prefix("---\n")
+    .or_else(|| content.strip_prefix("---\r\n"))
+  else {
+    return (None, content);
+  };
+
+  let mut offset = 0;
+  for line in rest.split_inclusive('\n') {
+    let trimmed = line.trim_end_matches(['\r', '\n']);
+    if trimmed == "---" || trimmed == "..." {
+      return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+    }
+    offset += line.len();
+  }
+  // 閉じる行がなければ front matter ではない
+  (None, content)
+}
+
+// 文字列か文字列のリストを受け付ける (tags: a でも tags: [a, b] でもよい)
+fn string_list(value: &Value) -> Vec<String> {
+  match value {
+    Value::Sequence(items) => items.iter().filter_map(scalar_to_string).collect(),
+    other => scalar_to_string(other).into_iter().collect(),
+  }
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+  match value {
+    Value::String(s) => Some(s.clone()),
+    Value::Bool(b) => Some(b.to_string()),
+    Value::Number(n) => Some(n.to_string()),
+    _ => None,
+  }
+}
+
+/// 本文の front matter からプロパティを読み取る
+///
+/// front matter がなければ空のプロパティを返す。
+pub fn parse_properties(content: &str) -> Result<NoteProperties, String> {
+  let (Some(yaml), _) = split_front_matter(content) else {
+    return Ok(NoteProperties::default());
+  };
+  let mapping = match serde_yaml::from_str::<Value>(yaml)
+    .map_err(|e| format!("front matter の解析に失敗しました: {}", e))?
+  {
+    Value::Mapping(mapping) => mapping,
+    Value::Null => return Ok(NoteProperties::default()),
+    _ => return Err("front matter はキーと値の組で書いてください".to_string()),
+  };
+
+  let mut properties = NoteProperties::default();
+  for (key, value) in mapping {
+    let Some(key) = scalar_to_string(&key) else {
+      continue;
+    };
+    match key.as_str() {
+      "title" => properties.title = scalar_to_string(&value),
+      "tags" => {
+        properties.tags = string_list(&value)
+          .iter()
+          .filter_map(|tag| normalize_tag_name(tag).ok())
+          .collect();
+      }
+      "aliases" => properties.aliases = string_list(&value),
+      "created" => properties.created = scalar_to_string(&value),
+      _ => properties.custom.push(NoteProperty {
+        key,
+        value: serde_json::to_value(&value)
+          .map_err(|e| format!("front matter の解析に失敗しました: {}", e))?,
+      }),
+    }
+  }
+  Ok(properties)
+}
+
+/// プロパティを front matter の文字列にする (空なら空文字列)
+pub fn render_front_matter(properties: &NoteProperties) -> Result<String, String> {
+  let mut mapping = Mapping::new();
+  if let Some(title) = &properties.title {
+    mapping.insert("title".into(), title.as_str().into());
+  }
+  if !properties.aliases.is_empty() {
+    mapping.insert(
+      "aliases".into(),
+      Value::Sequence(
+        properties
+          .aliases
+          .iter()
+          .map(|a| a.as_str().into())
+          .collect(),
+      ),
+    );
+  }
+  if !properties.tags.is_empty() {
+    mapping.insert(
+      "tags".into(),
+      Value::Sequence(properties.tags.iter().map(|t| t.as_str().into()).collect()),
+    );
+  }
+  if let Some(created) = &properties.created {
+    mapping.insert("created".into(), created.as_str().into());
+  }
+  for property in &properties.custom {
+    let value = serde_yaml::to_value(&property.value)
+      .map_err(|e| format!("front matter の作成に失敗しました: {}", e))?;
+    mapping.insert(property.key.as_str().into(), value);
+  }
+
+  if mapping.is_empty() {
+    return Ok(String::new());
+  }
+  let yaml = serde_yaml::to_string(&mapping)
+    .map_err(|e| format!("front matter の作成に失敗しました: {}", e))?;
+  Ok(format!("---\n{}---\n", yaml))
+}
+
+/// 本文の front matter をプロパティで置き換える (本文はそのまま)
+pub fn replace_front_matter(content: &str, properties: &NoteProperties) -> Result<String, String> {
+  let (_, body) = split_front_matter(content);
+  Ok(format!("{}{}", render_front_matter(properties)?, body))
+}
+
+// note_properties に保存する (キー, 値) の組 (リストは要素ごと、入れ子の値は JSON にする)
+fn property_rows(properties: &NoteProperties) -> Vec<(String, String)> {
+  let mut rows = Vec::new();
+  if let Some(title) = &properties.title {
+    rows.push(("title".to_string(), title.clone()));
+  }
+  for tag in &properties.tags {
+    rows.push(("tags".to_string(), tag.clone()));
+  }
+  for alias in &properties.aliases {
+    rows.push(("aliases".to_string(), alias.clone()));
+  }
+  if let Some(created) = &properties.created {
+    rows.push(("created".to_string(), created.clone()));
+  }
+  for property in &properties.custom {
+    let values = match &property.value {
+      serde_json::Value::Array(items) => items.iter().collect(),
+      value => vec![value],
+    };
+    for value in values {
+      let text = match value {
+        serde_json::Value::Null => continue,
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+      };
+      rows.push((property.key.clone(), text));
+    }
+  }
+  rows
+}
+
+pub struct PropertyService {
+  db: Arc<Database>,
+}
+
+impl PropertyService {
+  pub fn new(db: Arc<Database>) -> Self {
+    PropertyService { db }
+  }
+
+  /// ノートの front matter を note_properties に反映する
+  ///
+  /// 解析できない front matter はプロパティなしとして扱う (ノートの保存は止めない)。
+  pub fn index_note(conn: &Connection, note_id: i64, content: &str) -> SqlResult<()> {
+    let properties = parse_properties(content).unwrap_or_default();
+    conn.execute(
+      "DELETE FROM note_properties WHERE note_id = ?",
+      params![note_id],
+    )?;
+    let mut stmt = conn
+      .prepare("INSERT INTO note_properties (note_id, key, value, position) VALUES (?, ?, ?, ?)")?;
+    for (position, (key, value)) in property_rows(&properties).into_iter().enumerate() {
+      stmt.execute(params![note_id, key, value, position as i64])?;
+    }
+    conn.execute(
+      "UPDATE notes SET properties_indexed = TRUE WHERE id = ?",
+      params![note_id],
+    )?;
+    Ok(())
+  }
+
+  /// まだプロパティを読み取っていないノートを読み取る
+  pub fn ensure_index(&self) -> Result<usize, String> {
+    let pending: Vec<(i64, String)> = {
+      let conn = self.db.conn.lock().unwrap();
+      let mut stmt = conn
+        .prepare(
+          "SELECT id, file_path FROM notes WHERE properties_indexed = FALSE AND is_deleted = FALSE AND file_path IS NOT NULL",
+        )
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
+        .collect::<SqlResult<Vec<(i64, String)>>>()
+        .map_err(|e| format!("ノートの取得に失敗しました: {}", e))?
+    };
+
+    let mut indexed = 0;
+    for (id, file_path) in pending {
+      let Ok(content) = std::fs::read_to_string(&file_path) else {
+        continue;
+      };
+      let conn = self.db.conn.lock().unwrap();
+      Self::index_note(&conn, id, &content)
+        .map_err(|e| format!("プロパティの索引更新に失敗しました: {}", e))?;
+      indexed += 1;
+    }
+
+    Ok(indexed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::db::models::SearchSort;
+  use crate::services::{NoteService, SearchService};
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_parse_and_render_front_matter() {
+    let content = "---\ntitle: 会議\ntags: [\"#work/a\", done]\naliases: mtg\ncreated: 2024-01-02\nstatus: done\npriority: 2\n---\n本文\n";
+    let properties = parse_properties(content).unwrap();
+    assert_eq!(properties.title.as_deref(), Some("会議"));
+    assert_eq!(properties.tags, vec!["work/a", "done"]);
+    assert_eq!(properties.aliases, vec!["mtg"]);
+    assert_eq!(properties.created.as_deref(), Some("2024-01-02"));
+    assert_eq!(properties.custom.len(), 2);
+    assert_eq!(properties.custom[0].key, "status");
+    assert_eq!(properties.custom[1].value, serde_json::json!(2));
+
+    // 書き出して読み直すと同じになり、本文は変わらない
+    let rewritten = replace_front_matter(content, &properties).unwrap();
+    assert!(rewritten.ends_with("---\n本文\n"));
+    assert_eq!(parse_properties(&rewritten).unwrap(), properties);
+
+    // front matter のない本文や閉じていないもの
+    assert_eq!(split_front_matter("本文").0, None);
+    assert_eq!(split_front_matter("---\nkey: value\n").0, None);
+    assert_eq!(
+      replace_front_matter("本文", &NoteProperties::default()).unwrap(),
+      "本文"
+    );
+    assert!(parse_properties("---\n- a\n---\n").is_err());
+  }
+
+  #[test]
+  fn test_properties_are_indexed_and_searchable() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let done = notes
+      .create_note(
+        "Done".to_string(),
+        "---\nstatus: Done\ntags: [project]\n---\n本文".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    let todo = notes
+      .create_note(
+        "Todo".to_string(),
+        "---\nstatus: todo\n---\n本文".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    notes
+      .create_note("Plain".to_string(), "本文".to_string(), None, None)
+      .unwrap();
+
+    let saved = notes.get_note_by_id(done.id).unwrap();
+    assert_eq!(saved.properties.tags, vec!["project"]);
+    assert_eq!(saved.properties.custom[0].key, "status");
+
+    let search = SearchService::new(db.clone());
+    let ids = |query: &str| -> Vec<i64> {
+      let mut ids: Vec<i64> = search
+        .search(query, SearchSort::Relevance)
+        .unwrap()
+        .into_iter()
+        .map(|hit| hit.note.id)
+        .collect();
+      ids.sort();
+      ids
+    };
+    assert_eq!(ids("prop:status=done"), vec![done.id]);
+    assert_eq!(ids("prop:status"), vec![done.id, todo.id]);
+    assert_eq!(ids("-prop:status=done prop:status"), vec![todo.id]);
+    // front matter の tags もタグとして付く
+    assert_eq!(ids("tag:project"), vec![done.id]);
+
+    // プロパティを書き換えても本文は変わらない
+    let mut properties = saved.properties.clone();
+    properties.custom[0].value = serde_json::json!("todo");
+    let updated = notes.update_note_properties(done.id, properties).unwrap();
+    assert!(updated.content.ends_with("---\n本文"));
+    assert_eq!(ids("prop:status=todo"), vec![done.id, todo.id]);
+  }
+}
@@ -0,0 +1,534 @@
+use crate::db::models::SearchField;
+use chrono::{Days, NaiveDate};
+
+/// 検索クエリの構文木
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryNode {
+  /// 本文・タイトルの検索語 (field が None なら両方)
+  Text {
+    field: Option<SearchField>,
+    text: String,
+    prefix: bool,
+  },
+  /// タグ (`tag:work/*` のように * を付けると子孫のタグも含める)
+  Tag {
+    name: String,
+    descendants: bool,
+  },
+  /// フォルダ (サブフォルダを含む)
+  Folder(String),
+  /// front matter のプロパティ (`prop:status=done`。値がなければキーがあるもの)
+  Property {
+    key: String,
+    value: Option<String>,
+  },
+  Favorite,
+  Date {
+    field: DateField,
+    range: DateRange,
+  },
+  Not(Box<QueryNode>),
+  And(Vec<QueryNode>),
+  Or(Vec<QueryNode>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+  Created,
+  Updated,
+}
+
+/// 日付の範囲 (両端を含む。None は上限・下限なし)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateRange {
+  pub start: Option<NaiveDate>,
+  pub end: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  LParen,
+  RParen,
+  Or,
+  Not,
+  Term {
+    field: Option<String>,
+    value: String,
+    prefix: bool,
+  },
+}
+
+const FIELDS: [&str; 8] = [
+  "title", "content", "tag", "folder", "prop", "is", "created", "updated",
+];
+
+fn is_term_end(c: char) -> bool {
+  c.is_whitespace() || c == '(' || c == ')'
+}
+
+// 引用符で囲まれた語を読む (開きの引用符は読み終えている前提)
+fn read_quoted(chars: &[char], pos: &mut usize) -> Result<String, String> {
+  let start = *pos;
+  while *pos < chars.len() && chars[*pos] != '"' {
+    *pos += 1;
+  }
+  if *pos >= chars.len() {
+    let rest: String = chars[start..].iter().collect();
+    return Err(format!("閉じられていない引用符があります: \"{}", rest));
+  }
+  let value = chars[start..*pos].iter().collect();
+  *pos += 1;
+  Ok(value)
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+  let chars: Vec<char> = query.chars().collect();
+  let mut tokens = Vec::new();
+  let mut pos = 0;
+
+  while pos < chars.len() {
+    let c = chars[pos];
+    if c.is_whitespace() {
+      pos += 1;
+      continue;
+    }
+    match c {
+      '(' => {
+        tokens.push(Token::LParen);
+        pos += 1;
+        continue;
+      }
+      ')' => {
+        tokens.push(Token::RParen);
+        pos += 1;
+        continue;
+      }
+      '-' => {
+        if pos + 1 >= chars.len() || chars[pos + 1].is_whitespace() || chars[pos + 1] == ')' {
+          return Err("「-」の後に除外する検索語がありません".to_string());
+        }
+        tokens.push(Token::Not);
+        pos += 1;
+        continue;
+      }
+      _ => {}
+    }
+
+    // field:value の field 部分 (既知のフィールド名の場合のみ)
+    let mut field = None;
+    if let Some(colon) = chars[pos..]
+      .iter()
+      .position(|c| *c == ':' || is_term_end(*c))
+      .filter(|i| chars[pos + i] == ':')
+    {
+      let name: String = chars[pos..pos + colon].iter().collect();
+      let name = name.to_lowercase();
+      if FIELDS.contains(&name.as_str()) {
+        field = Some(name);
+        pos += colon + 1;
+      }
+    }
+
+    let (value, quoted) = if pos < chars.len() && chars[pos] == '"' {
+      pos += 1;
+      (read_quoted(&chars, &mut pos)?, true)
+    } else {
+      let start = pos;
+      while pos < chars.len() && !is_term_end(chars[pos]) {
+        pos += 1;
+      }
+      (chars[start..pos].iter().collect::<String>(), false)
+    };
+
+    // 前方一致: 引用符なしなら末尾の *、引用符ありなら閉じ引用符の直後の *
+    let (value, prefix) = if quoted {
+      let prefix = pos < chars.len() && chars[pos] == '*';
+      if prefix {
+        pos += 1;
+      }
+      (value, prefix)
+    } else {
+      match value.strip_suffix('*') {
+        Some(stripped) if !stripped.is_empty() || field.is_some() => (stripped.to_string(), true),
+        _ => (value, false),
+      }
+    };
+
+    if field.is_none() && !quoted {
+      match value.as_str() {
+        "OR" => {
+          tokens.push(Token::Or);
+          continue;
+        }
+        "AND" => continue,
+        _ => {}
+      }
+    }
+
+    tokens.push(Token::Term {
+      field,
+      value,
+      prefix,
+    });
+  }
+
+  Ok(tokens)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+  NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .map_err(|_| format!("日付は YYYY-MM-DD の形式で指定してください: {}", value))
+}
+
+// >2026-01-01, <=2026-01-31, 2026-01-01, 2026-01-01..2026-01-31 などを範囲にする
+fn parse_date_range(value: &str) -> Result<DateRange, String> {
+  if let Some(rest) = value.strip_prefix(">=") {
+    return Ok(DateRange {
+      start: Some(parse_date(rest)?),
+      end: None,
+    });
+  }
+  if let Some(rest) = value.strip_prefix("<=") {
+    return Ok(DateRange {
+      start: None,
+      end: Some(parse_date(rest)?),
+    });
+  }
+  if let Some(rest) = value.strip_prefix('>') {
+    let date = parse_date(rest)?;
+    return Ok(DateRange {
+      start: date.checked_add_days(Days::new(1)),
+      end: None,
+    });
+  }
+  if let Some(rest) = value.strip_prefix('<') {
+    let date = parse_date(rest)?;
+    return Ok(DateRange {
+      start: None,
+      end: date.checked_sub_days(Days::new(1)),
+    });
+  }
+  if let Some((start, end)) = value.split_once("..") {
+    let range = DateRange {
+      start: (!start.is_empty()).then(|| parse_date(start)).transpose()?,
+      end: (!end.is_empty()).then(|| parse_date(end)).transpose()?,
+    };
+    if range.start.is_none() && range.end.is_none() {
+      return Err("日付の範囲が空です".to_string());
+    }
+    if let (Some(start), Some(end)) = (range.start, range.end)
+      && start > end
+    {
+      return Err(format!("日付の範囲が逆になっています: {}", value));
+    }
+    return Ok(range);
+  }
+  let date = parse_date(value)?;
+  Ok(DateRange {
+    start: Some(date),
+    end: Some(date),
+  })
+}
+
+fn term_to_node(field: Option<String>, value: String, prefix: bool) -> Result<QueryNode, String> {
+  let Some(field) = field else {
+    if value.is_empty() {
+      return Err("空の検索語があります".to_string());
+    }
+    return Ok(QueryNode::Text {
+      field: None,
+      text: value,
+      prefix,
+    });
+  };
+
+  if value.is_empty() {
+    return Err(format!("{}: の後に値がありません", field));
+  }
+
+  match field.as_str() {
+    "title" => Ok(QueryNode::Text {
+      field: Some(SearchField::Title),
+      text: value,
+      prefix,
+    }),
+    "content" => Ok(QueryNode::Text {
+      field: Some(SearchField::Content),
+      text: value,
+      prefix,
+    }),
+    "tag" => {
+      let name = value.trim_end_matches('/');
+      if name.is_empty() {
+        return Err(format!("{}: の後に値がありません", field));
+      }
+      Ok(QueryNode::Tag {
+        name: name.to_string(),
+        descendants: prefix,
+      })
+    }
+    "folder" => Ok(QueryNode::Folder(value)),
+    "prop" => {
+      let (key, value) = match value.split_once('=') {
+        Some((key, value)) => (key.trim(), Some(value.trim().to_string())),
+        None => (value.trim(), None),
+      };
+      if key.is_empty() {
+        return Err(format!("{}: の後にキーがありません", field));
+      }
+      Ok(QueryNode::Property {
+        key: key.to_string(),
+        value,
+      })
+    }
+    "is" if value == "favorite" => Ok(QueryNode::Favorite),
+    "is" => Err(format!("不明な条件です: is:{}", value)),
+    "created" => Ok(QueryNode::Date {
+      field: DateField::Created,
+      range: parse_date_range(&value)?,
+    }),
+    "updated" => Ok(QueryNode::Date {
+      field: DateField::Updated,
+      range: parse_date_range(&value)?,
+    }),
+    _ => unreachable!(),
+  }
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  pos: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.pos).cloned();
+    self.pos += 1;
+    token
+  }
+
+  // or := and ("OR" and)*
+  fn parse_or(&mut self) -> Result<QueryNode, String> {
+    if self.peek() == Some(&Token::Or) {
+      return Err("OR の前に検索語がありません".to_string());
+    }
+    let mut nodes = vec![self.parse_and()?];
+    while self.peek() == Some(&Token::Or) {
+      self.pos += 1;
+      if matches!(self.peek(), None | Some(Token::Or) | Some(Token::RParen)) {
+        return Err("OR の後に検索語がありません".to_string());
+      }
+      nodes.push(self.parse_and()?);
+    }
+    Ok(if nodes.len() == 1 {
+      nodes.remove(0)
+    } else {
+      QueryNode::Or(nodes)
+    })
+  }
+
+  // and := unary+ (空白区切りは AND)
+  fn parse_and(&mut self) -> Result<QueryNode, String> {
+    let mut nodes = Vec::new();
+    while !matches!(self.peek(), None | Some(Token::Or) | Some(Token::RParen)) {
+      nodes.push(self.parse_unary()?);
+    }
+    match nodes.len() {
+      0 => Err("括弧の中が空です".to_string()),
+      1 => Ok(nodes.remove(0)),
+      _ => Ok(QueryNode::And(nodes)),
+    }
+  }
+
+  fn parse_unary(&mut self) -> Result<QueryNode, String> {
+    if self.peek() == Some(&Token::Not) {
+      self.pos += 1;
+      return Ok(QueryNode::Not(Box::new(self.parse_unary()?)));
+    }
+    self.parse_primary()
+  }
+
+  fn parse_primary(&mut self) -> Result<QueryNode, String> {
+    match self.next() {
+      Some(Token::LParen) => {
+        let node = self.parse_or()?;
+        if self.next() != Some(Token::RParen) {
+          return Err("閉じられていない括弧があります".to_string());
+        }
+        Ok(node)
+      }
+      Some(Token::Term {
+        field,
+        value,
+        prefix,
+      }) => term_to_node(field, value, prefix),
+      _ => Err("検索語がありません".to_string()),
+    }
+  }
+}
+
+/// 検索クエリを構文木にする (条件がなければ None)
+///
+/// 空白区切りは AND、`OR` で論理和、`-` で除外、`( )` でグループ化する。
+/// `"..."` はフレーズ、末尾の `*` は前方一致。`title:` `content:` `tag:` `folder:`
+/// `is:favorite` `created:` `updated:` で対象を絞り込める。`tag:work/*` はタグ work と
+/// その子孫のタグが付いたノート。
+pub fn parse_query(query: &str) -> Result<Option<QueryNode>, String> {
+  let tokens = tokenize(query)?;
+  if tokens.is_empty() {
+    return Ok(None);
+  }
+
+  let mut parser = Parser { tokens, pos: 0 };
+  if parser.peek() == Some(&Token::RParen) {
+    return Err("対応する「(」がない「)」があります".to_string());
+  }
+  let node = parser.parse_or()?;
+  if parser.pos < parser.tokens.len() {
+    return Err("対応する「(」がない「)」があります".to_string());
+  }
+  Ok(Some(node))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(value: &str) -> QueryNode {
+    QueryNode::Text {
+      field: None,
+      text: value.to_string(),
+      prefix: false,
+    }
+  }
+
+  fn date(value: &str) -> Option<NaiveDate> {
+    Some(NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap())
+  }
+
+  #[test]
+  fn test_parse_query() {
+    assert_eq!(parse_query("  ").unwrap(), None);
+    assert_eq!(
+      parse_query("会議 \"週次 定例\" OR -メモ").unwrap(),
+      Some(QueryNode::Or(vec![
+        QueryNode::And(vec![text("会議"), text("週次 定例")]),
+        QueryNode::Not(Box::new(text("メモ"))),
+      ]))
+    );
+    assert_eq!(
+      parse_query("(a OR b) title:rel* -tag:done").unwrap(),
+      Some(QueryNode::And(vec![
+        QueryNode::Or(vec![text("a"), text("b")]),
+        QueryNode::Text {
+          field: Some(SearchField::Title),
+          text: "rel".to_string(),
+          prefix: true,
+        },
+        QueryNode::Not(Box::new(QueryNode::Tag {
+          name: "done".to_string(),
+          descendants: false,
+        })),
+      ]))
+    );
+    assert_eq!(
+      parse_query("-(a OR b)").unwrap(),
+      Some(QueryNode::Not(Box::new(QueryNode::Or(vec![
+        text("a"),
+        text("b")
+      ]))))
+    );
+    assert_eq!(
+      parse_query("folder:\"仕事/会議 録\" is:favorite").unwrap(),
+      Some(QueryNode::And(vec![
+        QueryNode::Folder("仕事/会議 録".to_string()),
+        QueryNode::Favorite,
+      ]))
+    );
+    assert_eq!(
+      parse_query("prop:status=done prop:\"due = 2024-01-01\" prop:aliases").unwrap(),
+      Some(QueryNode::And(vec![
+        QueryNode::Property {
+          key: "status".to_string(),
+          value: Some("done".to_string()),
+        },
+        QueryNode::Property {
+          key: "due".to_string(),
+          value: Some("2024-01-01".to_string()),
+        },
+        QueryNode::Property {
+          key: "aliases".to_string(),
+          value: None,
+        },
+      ]))
+    );
+    assert!(parse_query("prop:=done").is_err());
+    // 不明なフィールドや語中の - はただの検索語
+    assert_eq!(
+      parse_query("https://example.com foo-bar").unwrap(),
+      Some(QueryNode::And(vec![
+        text("https://example.com"),
+        text("foo-bar")
+      ]))
+    );
+  }
+
+  #[test]
+  fn test_parse_date_ranges() {
+    let range = |query: &str| match parse_query(query).unwrap() {
+      Some(QueryNode::Date { range, .. }) => range,
+      other => panic!("unexpected: {:?}", other),
+    };
+    assert_eq!(
+      range("updated:>2026-01-01"),
+      DateRange {
+        start: date("2026-01-02"),
+        end: None
+      }
+    );
+    assert_eq!(
+      range("created:<2026-01-01"),
+      DateRange {
+        start: None,
+        end: date("2025-12-31")
+      }
+    );
+    assert_eq!(
+      range("created:2026-01-01..2026-01-31"),
+      DateRange {
+        start: date("2026-01-01"),
+        end: date("2026-01-31")
+      }
+    );
+    assert_eq!(
+      range("updated:2026-03-01"),
+      DateRange {
+        start: date("2026-03-01"),
+        end: date("2026-03-01")
+      }
+    );
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    for query in [
+      "\"閉じていない",
+      "(a OR b",
+      "a)",
+      "OR a",
+      "a OR",
+      "a -",
+      "()",
+      "tag:",
+      "is:pinned",
+      "updated:>2026/01/01",
+      "created:2026-02-01..2026-01-01",
+    ] {
+      assert!(parse_query(query).is_err(), "{} should be an error", query);
+    }
+  }
+}
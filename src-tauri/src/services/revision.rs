@@ -0,0 +1,298 @@
+use crate::db::Database;
+use crate::db::models::{
+  DiffLine, DiffLineKind, NoteRevision, NoteRevisionWithContent, NoteWithContent,
+};
+use crate::services::NoteService;
+use rusqlite::{Connection, Result as SqlResult, params};
+use similar::{ChangeTag, TextDiff};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 同じノートの履歴はこの間隔 (分) より短い間隔では記録しない
+const REVISION_INTERVAL_MINUTES: i64 = 5;
+
+/// ノートごとに保持する履歴の最大数
+const MAX_REVISIONS_PER_NOTE: i64 = 100;
+
+pub struct RevisionService {
+  db: Arc<Database>,
+  base_path: PathBuf,
+}
+
+impl RevisionService {
+  pub fn new(db: Arc<Database>, base_path: PathBuf) -> Self {
+    RevisionService { db, base_path }
+  }
+
+  /// 上書きされる前のノートの内容を履歴として記録する
+  ///
+  /// `force` が false の場合、直近の履歴が `REVISION_INTERVAL_MINUTES` 以内に
+  /// 記録されていれば何もしない (自動保存のたびに履歴が増えないようにするため)。
+  pub fn record_revision(
+    conn: &Connection,
+    note_id: i64,
+    title: &str,
+    content: &str,
+    force: bool,
+  ) -> SqlResult<()> {
+    if !force {
+      let recent: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM note_revisions WHERE note_id = ? AND created_at > DATETIME('now', ?))",
+        params![note_id, format!("-{} minutes", REVISION_INTERVAL_MINUTES)],
+        |row| row.get(0),
+      )?;
+      if recent {
+        return Ok(());
+      }
+    }
+
+    // 直前の履歴と同じ内容なら記録しない
+    let unchanged: bool = conn.query_row(
+      "SELECT EXISTS(
+        SELECT 1 FROM (SELECT title, content FROM note_revisions WHERE note_id = ? ORDER BY id DESC LIMIT 1)
+        WHERE title = ? AND content = ?
+      )",
+      params![note_id, title, content],
+      |row| row.get(0),
+    )?;
+    if unchanged {
+      return Ok(());
+    }
+
+    conn.execute(
+      "INSERT INTO note_revisions (note_id, title, content) VALUES (?, ?, ?)",
+      params![note_id, title, content],
+    )?;
+
+    conn.execute(
+      "DELETE FROM note_revisions WHERE note_id = ? AND id NOT IN (
+        SELECT id FROM note_revisions WHERE note_id = ? ORDER BY id DESC LIMIT ?
+      )",
+      params![note_id, note_id, MAX_REVISIONS_PER_NOTE],
+    )?;
+
+    Ok(())
+  }
+
+  // ノートの履歴一覧を取得 (新しい順)
+  pub fn get_revisions(&self, note_id: i64) -> Result<Vec<NoteRevision>, String> {
+    let conn = self.db.conn.lock().unwrap();
+    let mut stmt = conn
+      .prepare(
+        "SELECT id, note_id, title, created_at, LENGTH(content)
+         FROM note_revisions WHERE note_id = ? ORDER BY id DESC",
+      )
+      .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let revisions = stmt
+      .query_map(params![note_id], |row| {
+        Ok(NoteRevision {
+          id: row.get(0)?,
+          note_id: row.get(1)?,
+          title: row.get(2)?,
+          created_at: row.get(3)?,
+          char_count: row.get(4)?,
+        })
+      })
+      .map_err(|e| format!("履歴の取得に失敗しました: {}", e))?
+      .collect::<SqlResult<Vec<NoteRevision>>>()
+      .map_err(|e| format!("履歴の取得に失敗しました: {}", e))?;
+
+    Ok(revisions)
+  }
+
+  // 履歴をidで取得
+  pub fn get_revision(&self, id: i64) -> Result<NoteRevisionWithContent, String> {
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .query_row(
+        "SELECT id, note_id, title, content, created_at FROM note_revisions WHERE id = ?",
+        params![id],
+        |row| {
+          Ok(NoteRevisionWithContent {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            title: row.get(2)?,
+            content: row.get(3)?,
+            created_at: row.get(4)?,
+          })
+        },
+      )
+      .map_err(|e| format!("履歴の取得に失敗しました: {}", e))
+  }
+
+  // 2つの履歴の差分を行単位で取得
+  // to_id が None の場合は現在のノートの内容と比較する
+  pub fn diff_revisions(&self, from_id: i64, to_id: Option<i64>) -> Result<Vec<DiffLine>, String> {
+    let from = self.get_revision(from_id)?;
+    let to_content = match to_id {
+      Some(id) => {
+        let to = self.get_revision(id)?;
+        if to.note_id != from.note_id {
+          return Err("異なるノートの履歴は比較できません".to_string());
+        }
+        to.content
+      }
+      None => self.note_service().get_note_by_id(from.note_id)?.content,
+    };
+
+    let diff = TextDiff::from_lines(&from.content, &to_content);
+    let lines = diff
+      .iter_all_changes()
+      .map(|change| DiffLine {
+        kind: match change.tag() {
+          ChangeTag::Equal => DiffLineKind::Equal,
+          ChangeTag::Insert => DiffLineKind::Insert,
+          ChangeTag::Delete => DiffLineKind::Delete,
+        },
+        content: change.value().trim_end_matches(['\r', '\n']).to_string(),
+      })
+      .collect();
+
+    Ok(lines)
+  }
+
+  // 履歴からノートを復元
+  // 通常の更新処理を通すため、検索インデックスやプレビュー、バックリンクも更新される
+  pub fn restore_revision(&self, id: i64) -> Result<NoteWithContent, String> {
+    let revision = self.get_revision(id)?;
+    let note_service = self.note_service();
+    let current = note_service.get_note_by_id(revision.note_id)?;
+
+    // 復元前の内容も履歴に残し、復元を取り消せるようにする
+    {
+      let conn = self.db.conn.lock().unwrap();
+      Self::record_revision(&conn, current.id, &current.title, &current.content, true)
+        .map_err(|e| format!("履歴の保存に失敗しました: {}", e))?;
+    }
+
+    note_service.update_note(revision.note_id, revision.title, revision.content)
+  }
+
+  fn note_service(&self) -> NoteService {
+    NoteService::new(self.db.clone(), self.base_path.clone())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_update_note_records_revision() {
+    let (db, temp_dir) = setup_test_db();
+    let note_service = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let service = RevisionService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let note = note_service
+      .create_note(
+        "履歴テスト".to_string(),
+        "最初の内容".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    note_service
+      .update_note(
+        note.id,
+        "履歴テスト".to_string(),
+        "二番目の内容".to_string(),
+      )
+      .unwrap();
+    // 直後の保存は間隔が短いため記録されない
+    note_service
+      .update_note(
+        note.id,
+        "履歴テスト".to_string(),
+        "三番目の内容".to_string(),
+      )
+      .unwrap();
+
+    let revisions = service.get_revisions(note.id).unwrap();
+    assert_eq!(revisions.len(), 1);
+
+    let revision = service.get_revision(revisions[0].id).unwrap();
+    assert_eq!(revision.content, "最初の内容");
+  }
+
+  #[test]
+  fn test_diff_revisions() {
+    let (db, temp_dir) = setup_test_db();
+    let note_service = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let service = RevisionService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let note = note_service
+      .create_note("差分".to_string(), "a\nb\nc".to_string(), None, None)
+      .unwrap();
+    note_service
+      .update_note(note.id, "差分".to_string(), "a\nB\nc".to_string())
+      .unwrap();
+
+    let revisions = service.get_revisions(note.id).unwrap();
+    let diff = service.diff_revisions(revisions[0].id, None).unwrap();
+
+    let deleted: Vec<_> = diff
+      .iter()
+      .filter(|l| matches!(l.kind, DiffLineKind::Delete))
+      .map(|l| l.content.as_str())
+      .collect();
+    let inserted: Vec<_> = diff
+      .iter()
+      .filter(|l| matches!(l.kind, DiffLineKind::Insert))
+      .map(|l| l.content.as_str())
+      .collect();
+    assert_eq!(deleted, vec!["b"]);
+    assert_eq!(inserted, vec!["B"]);
+  }
+
+  #[test]
+  fn test_restore_revision() {
+    let (db, temp_dir) = setup_test_db();
+    let note_service = NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let service = RevisionService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    let note = note_service
+      .create_note(
+        "元のタイトル".to_string(),
+        "元の内容".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    note_service
+      .update_note(
+        note.id,
+        "新しいタイトル".to_string(),
+        "誤って貼り付けた内容".to_string(),
+      )
+      .unwrap();
+
+    let revisions = service.get_revisions(note.id).unwrap();
+    let restored = service.restore_revision(revisions[0].id).unwrap();
+    assert_eq!(restored.title, "元のタイトル");
+    assert_eq!(restored.content, "元の内容");
+
+    // 復元前の内容も履歴に残っている
+    let revisions = service.get_revisions(note.id).unwrap();
+    assert_eq!(revisions.len(), 2);
+    let latest = service.get_revision(revisions[0].id).unwrap();
+    assert_eq!(latest.content, "誤って貼り付けた内容");
+
+    // 検索インデックスも更新されている
+    let results = note_service.search_notes("元の内容").unwrap();
+    assert_eq!(results.len(), 1);
+  }
+}
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult, Row, params};
+
+use crate::db::Database;
+use crate::db::models::{SavedSearch, SearchHit, SearchSort};
+use crate::services::SearchService;
+use crate::services::query::parse_query;
+
+const SAVED_SEARCH_COLUMNS: &str = "id, name, query, icon, sort, created_at, updated_at";
+
+fn sort_to_str(sort: SearchSort) -> &'static str {
+  match sort {
+    SearchSort::Relevance => "relevance",
+    SearchSort::Updated => "updated",
+    SearchSort::Created => "created",
+  }
+}
+
+fn sort_from_str(value: &str) -> SearchSort {
+  match value {
+    "updated" => SearchSort::Updated,
+    "created" => SearchSort::Created,
+    _ => SearchSort::Relevance,
+  }
+}
+
+fn row_to_saved_search(row: &Row) -> SqlResult<SavedSearch> {
+  let sort: String = row.get(4)?;
+  Ok(SavedSearch {
+    id: row.get(0)?,
+    name: row.get(1)?,
+    query: row.get(2)?,
+    icon: row.get(3)?,
+    sort: sort_from_str(&sort),
+    created_at: row.get(5)?,
+    updated_at: row.get(6)?,
+  })
+}
+
+// 名前が空でなく、クエリが解釈できることを確認する
+fn validate(name: &str, query: &str) -> Result<(), String> {
+  if name.trim().is_empty() {
+    return Err("保存済み検索の名前を入力してください".to_string());
+  }
+  parse_query(query)?;
+  Ok(())
+}
+
+pub struct SavedSearchService {
+  db: Arc<Database>,
+}
+
+impl SavedSearchService {
+  pub fn new(db: Arc<Database>) -> Self {
+    Self { db }
+  }
+
+  fn find(conn: &Connection, id: i64) -> Result<SavedSearch, String> {
+    conn
+      .query_row(
+        &format!(
+          "SELECT {} FROM saved_searches WHERE id = ?",
+          SAVED_SEARCH_COLUMNS
+        ),
+        params![id],
+        row_to_saved_search,
+      )
+      .optional()
+      .map_err(|e| format!("保存済み検索の取得に失敗しました: {}", e))?
+      .ok_or_else(|| format!("保存済み検索が見つかりません: {}", id))
+  }
+
+  pub fn create_saved_search(
+    &self,
+    name: String,
+    query: String,
+    icon: Option<String>,
+    sort: Option<SearchSort>,
+  ) -> Result<SavedSearch, String> {
+    validate(&name, &query)?;
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .execute(
+        "INSERT INTO saved_searches (name, query, icon, sort) VALUES (?, ?, ?, ?)",
+        params![
+          name.trim(),
+          query,
+          icon,
+          sort_to_str(sort.unwrap_or_default())
+        ],
+      )
+      .map_err(|e| format!("保存済み検索の作成に失敗しました: {}", e))?;
+    Self::find(&conn, conn.last_insert_rowid())
+  }
+
+  pub fn get_saved_searches(&self) -> Result<Vec<SavedSearch>, String> {
+    let conn = self.db.conn.lock().unwrap();
+    let mut stmt = conn
+      .prepare(&format!(
+        "SELECT {} FROM saved_searches ORDER BY name COLLATE NOCASE, id",
+        SAVED_SEARCH_COLUMNS
+      ))
+      .map_err(|e| format!("保存済み検索の取得に失敗しました: {}", e))?;
+    stmt
+      .query_map([], row_to_saved_search)
+      .map_err(|e| format!("保存済み検索の取得に失敗しました: {}", e))?
+      .collect::<SqlResult<Vec<SavedSearch>>>()
+      .map_err(|e| format!("保存済み検索の取得に失敗しました: {}", e))
+  }
+
+  pub fn update_saved_search(
+    &self,
+    id: i64,
+    name: String,
+    query: String,
+    icon: Option<String>,
+    sort: Option<SearchSort>,
+  ) -> Result<SavedSearch, String> {
+    validate(&name, &query)?;
+    let conn = self.db.conn.lock().unwrap();
+    let updated = conn
+      .execute(
+        "UPDATE saved_searches SET name = ?, query = ?, icon = ?, sort = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![
+          name.trim(),
+          query,
+          icon,
+          sort_to_str(sort.unwrap_or_default()),
+          id
+        ],
+      )
+      .map_err(|e| format!("保存済み検索の更新に失敗しました: {}", e))?;
+    if updated == 0 {
+      return Err(format!("保存済み検索が見つかりません: {}", id));
+    }
+    Self::find(&conn, id)
+  }
+
+  pub fn delete_saved_search(&self, id: i64) -> Result<(), String> {
+    let conn = self.db.conn.lock().unwrap();
+    conn
+      .execute("DELETE FROM saved_searches WHERE id = ?", params![id])
+      .map_err(|e| format!("保存済み検索の削除に失敗しました: {}", e))?;
+    Ok(())
+  }
+
+  /// 保存済み検索を実行する
+  pub fn run_saved_search(&self, id: i64) -> Result<Vec<SearchHit>, String> {
+    let saved = {
+      let conn = self.db.conn.lock().unwrap();
+      Self::find(&conn, id)?
+    };
+    SearchService::new(self.db.clone()).search(&saved.query, saved.sort)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::services::NoteService;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_saved_search_crud_and_run() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let service = SavedSearchService::new(db.clone());
+
+    let release = notes
+      .create_note(
+        "Release plan".to_string(),
+        "Schedule".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    notes
+      .create_note("Other".to_string(), "Nothing".to_string(), None, None)
+      .unwrap();
+
+    let saved = service
+      .create_saved_search(
+        " リリース ".to_string(),
+        "title:release".to_string(),
+        Some("🔍".to_string()),
+        Some(SearchSort::Updated),
+      )
+      .unwrap();
+    assert_eq!(saved.name, "リリース");
+    assert_eq!(saved.sort, SearchSort::Updated);
+
+    let hits = service.run_saved_search(saved.id).unwrap();
+    assert_eq!(
+      hits.iter().map(|h| h.note.id).collect::<Vec<_>>(),
+      vec![release.id]
+    );
+
+    let updated = service
+      .update_saved_search(
+        saved.id,
+        "その他".to_string(),
+        "other".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    assert_eq!(updated.query, "other");
+    assert_eq!(updated.sort, SearchSort::Relevance);
+    assert_eq!(service.get_saved_searches().unwrap().len(), 1);
+
+    // 解釈できないクエリや空の名前は保存しない
+    assert!(
+      service
+        .create_saved_search("x".to_string(), "(a".to_string(), None, None)
+        .is_err()
+    );
+    assert!(
+      service
+        .create_saved_search(" ".to_string(), "a".to_string(), None, None)
+        .is_err()
+    );
+
+    service.delete_saved_search(saved.id).unwrap();
+    assert!(service.get_saved_searches().unwrap().is_empty());
+    assert!(service.run_saved_search(saved.id).is_err());
+  }
+}
@@ -0,0 +1,783 @@
+use crate::db::Database;
+use crate::db::models::{MatchRange, Note, SearchField, SearchHit, SearchSort};
+use crate::services::fts;
+use crate::services::query::{DateField, QueryNode, parse_query};
+use rusqlite::{Result as SqlResult, ToSql};
+use std::sync::Arc;
+
+/// bm25 でのタイトル列の重み (本文は 1.0)
+const TITLE_WEIGHT: f64 = 10.0;
+
+/// trigram トークナイザで検索できる最短の文字数 (これより短い語は LIKE で探す)
+const MIN_TRIGRAM_CHARS: usize = 3;
+
+/// スニペットの最大文字数と、最初の一致箇所より前に含める文字数
+const SNIPPET_CHARS: usize = 80;
+const SNIPPET_CONTEXT_CHARS: usize = 20;
+
+pub struct SearchService {
+  db: Arc<Database>,
+}
+
+// 構文木を SQL にしたもの
+//
+// トップレベルの AND 条件のうち検索語だけでできたものは FTS のクエリにまとめ
+// (スコアに使う)、それ以外は WHERE 句の条件にする。
+#[derive(Default)]
+struct CompiledQuery {
+  fts: Option<String>,
+  conditions: Vec<String>,
+  params: Vec<Box<dyn ToSql>>,
+  // ハイライトする語 (fold 済み、除外条件の中のものは含まない)
+  terms: Vec<(Option<SearchField>, String)>,
+}
+
+// FTS5 の文字列として引用符で囲む (" は重ねてエスケープする)
+fn quote_fts(text: &str) -> String {
+  format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+// LIKE のワイルドカードをエスケープする (ESCAPE '\' と組み合わせて使う)
+pub(crate) fn escape_like(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace('%', "\\%")
+    .replace('_', "\\_")
+}
+
+struct Compiler {
+  // notes_search が trigram で索引されているか (されていなければ検索語はすべて LIKE で探す)
+  trigram: bool,
+}
+
+impl Compiler {
+  // 否定や短い語を含まない検索語だけの式は FTS のクエリにできる
+  fn is_fts_only(&self, node: &QueryNode) -> bool {
+    match node {
+      QueryNode::Text { text, .. } => {
+        self.trigram && fts::fold(text).chars().count() >= MIN_TRIGRAM_CHARS
+      }
+      QueryNode::And(nodes) | QueryNode::Or(nodes) => nodes.iter().all(|n| self.is_fts_only(n)),
+      _ => false,
+    }
+  }
+
+  fn to_fts(&self, node: &QueryNode) -> String {
+    match node {
+      QueryNode::Text {
+        field,
+        text,
+        prefix,
+      } => {
+        let column = match field {
+          Some(SearchField::Title) => "title : ",
+          Some(SearchField::Content) => "content : ",
+          None => "",
+        };
+        let star = if *prefix { " *" } else { "" };
+        format!("{}{}{}", column, quote_fts(&fts::fold(text)), star)
+      }
+      QueryNode::And(nodes) => format!(
+        "({})",
+        nodes
+          .iter()
+          .map(|n| self.to_fts(n))
+          .collect::<Vec<_>>()
+          .join(" AND ")
+      ),
+      QueryNode::Or(nodes) => format!(
+        "({})",
+        nodes
+          .iter()
+          .map(|n| self.to_fts(n))
+          .collect::<Vec<_>>()
+          .join(" OR ")
+      ),
+      _ => unreachable!("FTS にできない条件です"),
+    }
+  }
+
+  fn to_sql(&self, node: &QueryNode, params: &mut Vec<Box<dyn ToSql>>) -> String {
+    if self.is_fts_only(node) {
+      params.push(Box::new(self.to_fts(node)));
+      return "n.id IN (SELECT rowid FROM notes_search WHERE notes_search MATCH ?)".to_string();
+    }
+
+    match node {
+      // 短い語は索引を使わずに部分一致で探す (前方一致の指定は部分一致に含まれる)
+      QueryNode::Text { field, text, .. } => {
+        let columns: &[&str] = match field {
+          Some(SearchField::Title) => &["title"],
+          Some(SearchField::Content) => &["content"],
+          None => &["title", "content"],
+        };
+        let pattern = format!("%{}%", escape_like(&fts::fold(text)));
+        let conditions: Vec<String> = columns
+          .iter()
+          .map(|column| {
+            params.push(Box::new(pattern.clone()));
+            format!("{} LIKE ? ESCAPE '\\'", column)
+          })
+          .collect();
+        format!(
+          "n.id IN (SELECT rowid FROM notes_search WHERE {})",
+          conditions.join(" OR ")
+        )
+      }
+      QueryNode::Tag { name, descendants } => {
+        params.push(Box::new(name.clone()));
+        let condition = if *descendants {
+          params.push(Box::new(format!("{}/%", escape_like(name))));
+          "(t.name = ? OR t.name LIKE ? ESCAPE '\\')"
+        } else {
+          "t.name = ?"
+        };
+        format!(
+          "EXISTS(SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id WHERE nt.note_id = n.id AND {})",
+          condition
+        )
+      }
+      QueryNode::Folder(path) => {
+        // ルートからのフォルダ名のパスに path が連続した区切りとして含まれるフォルダとその配下
+        params.push(Box::new(format!(
+          "%/{}/%",
+          escape_like(path.trim_matches('/'))
+        )));
+        // parent_id が NULL でも NOT で反転できるように IS NOT NULL を付ける
+        "(n.parent_id IS NOT NULL AND n.parent_id IN (
+          WITH RECURSIVE folder_paths(id, path) AS (
+            SELECT id, name FROM folders WHERE parent_id IS NULL
+            UNION ALL
+            SELECT f.id, fp.path || '/' || f.name FROM folders f JOIN folder_paths fp ON f.parent_id = fp.id
+          )
+          SELECT id FROM folder_paths WHERE '/' || path || '/' LIKE ? ESCAPE '\\'
+        ))"
+          .to_string()
+      }
+      QueryNode::Property { key, value } => {
+        params.push(Box::new(key.clone()));
+        let condition = match value {
+          Some(value) => {
+            params.push(Box::new(value.clone()));
+            " AND p.value = ? COLLATE NOCASE"
+          }
+          None => "",
+        };
+        format!(
+          "EXISTS(SELECT 1 FROM note_properties p WHERE p.note_id = n.id AND p.key = ?{})",
+          condition
+        )
+      }
+      QueryNode::Favorite => "n.is_favorite = TRUE".to_string(),
+      QueryNode::Date { field, range } => {
+        let column = match field {
+          DateField::Created => "n.created_at",
+          DateField::Updated => "n.updated_at",
+        };
+        // 日時は UTC で保存されているため、ローカル時刻の日付に直してから比べる
+        let mut conditions = Vec::new();
+        if let Some(start) = range.start {
+          conditions.push(format!("DATE({}, 'localtime') >= ?", column));
+          params.push(Box::new(start.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(end) = range.end {
+          conditions.push(format!("DATE({}, 'localtime') <= ?", column));
+          params.push(Box::new(end.format("%Y-%m-%d").to_string()));
+        }
+        if conditions.is_empty() {
+          // 範囲の端が表せない日付 (9999-12-31 より後など) は一致なし
+          return "FALSE".to_string();
+        }
+        format!("({})", conditions.join(" AND "))
+      }
+      QueryNode::Not(inner) => format!("NOT ({})", self.to_sql(inner, params)),
+      QueryNode::And(nodes) => format!(
+        "({})",
+        nodes
+          .iter()
+          .map(|n| self.to_sql(n, params))
+          .collect::<Vec<_>>()
+          .join(" AND ")
+      ),
+      QueryNode::Or(nodes) => format!(
+        "({})",
+        nodes
+          .iter()
+          .map(|n| self.to_sql(n, params))
+          .collect::<Vec<_>>()
+          .join(" OR ")
+      ),
+    }
+  }
+
+  fn compile(&self, node: Option<QueryNode>) -> CompiledQuery {
+    let mut compiled = CompiledQuery::default();
+    let conjuncts = match node {
+      None => Vec::new(),
+      Some(QueryNode::And(nodes)) => nodes,
+      Some(node) => vec![node],
+    };
+
+    let mut fts_parts = Vec::new();
+    for node in &conjuncts {
+      collect_terms(node, &mut compiled.terms);
+      if self.is_fts_only(node) {
+        fts_parts.push(self.to_fts(node));
+      } else {
+        let condition = self.to_sql(node, &mut compiled.params);
+        compiled.conditions.push(condition);
+      }
+    }
+    if !fts_parts.is_empty() {
+      compiled.fts = Some(fts_parts.join(" AND "));
+    }
+    compiled
+  }
+}
+
+fn collect_terms(node: &QueryNode, terms: &mut Vec<(Option<SearchField>, String)>) {
+  match node {
+    QueryNode::Text { field, text, .. } => terms.push((*field, fts::fold(text))),
+    QueryNode::And(nodes) | QueryNode::Or(nodes) => {
+      for node in nodes {
+        collect_terms(node, terms);
+      }
+    }
+    _ => {}
+  }
+}
+
+// text の中で terms が現れる範囲 (元の文字列での文字単位、重なりはまとめる)
+fn find_matches(
+  text: &str,
+  field: SearchField,
+  terms: &[(Option<SearchField>, String)],
+) -> Vec<MatchRange> {
+  let (folded, offsets) = fts::fold_with_offsets(text);
+  let text_len = text.chars().count();
+  let original = |i: usize| offsets.get(i).copied().unwrap_or(text_len);
+
+  let mut ranges: Vec<(usize, usize)> = Vec::new();
+  for (term_field, term) in terms {
+    if term_field.is_some_and(|f| f != field) {
+      continue;
+    }
+    let term: Vec<char> = term.chars().collect();
+    if term.is_empty() || term.len() > folded.len() {
+      continue;
+    }
+    for start in 0..=folded.len() - term.len() {
+      if folded[start..start + term.len()] == term[..] {
+        ranges.push((original(start), original(start + term.len())));
+      }
+    }
+  }
+
+  ranges.sort();
+  let mut merged: Vec<MatchRange> = Vec::new();
+  for (start, end) in ranges {
+    match merged.last_mut() {
+      Some(last) if start <= last.end => last.end = last.end.max(end),
+      _ => merged.push(MatchRange { field, start, end }),
+    }
+  }
+  merged
+}
+
+fn push_escaped(html: &mut String, c: char) {
+  match c {
+    '&' => html.push_str("&amp;"),
+    '<' => html.push_str("&lt;"),
+    '>' => html.push_str("&gt;"),
+    '"' => html.push_str("&quot;"),
+    '\'' => html.push_str("&#39;"),
+    c => html.push(c),
+  }
+}
+
+// chars[start..end] を HTML にする (一致箇所は <mark> で囲み、それ以外はエスケープ)
+fn to_highlight_html(chars: &[char], matches: &[MatchRange], start: usize, end: usize) -> String {
+  let inside = |i: usize| matches.iter().any(|m| m.start <= i && i < m.end);
+  let mut html = String::new();
+  for (i, c) in chars.iter().enumerate().take(end).skip(start) {
+    if inside(i) && (i == start || !inside(i - 1)) {
+      html.push_str("<mark>");
+    }
+    push_escaped(&mut html, *c);
+    if inside(i) && (i + 1 == end || !inside(i + 1)) {
+      html.push_str("</mark>");
+    }
+  }
+  html
+}
+
+// 最初の一致箇所の周辺を切り出したスニペット (一致がなければ None)
+fn make_snippet(content: &str, matches: &[MatchRange]) -> Option<String> {
+  let first = matches.first()?;
+  let chars: Vec<char> = content.chars().collect();
+  let start = first.start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+  let end = (start + SNIPPET_CHARS).min(chars.len());
+
+  let mut snippet = String::new();
+  if start > 0 {
+    snippet.push('…');
+  }
+  snippet.push_str(&to_highlight_html(&chars, matches, start, end));
+  if end < chars.len() {
+    snippet.push('…');
+  }
+  Some(snippet)
+}
+
+fn escape_html(text: &str) -> String {
+  let mut html = String::with_capacity(text.len());
+  for c in text.chars() {
+    push_escaped(&mut html, c);
+  }
+  html
+}
+
+impl SearchService {
+  pub fn new(db: Arc<Database>) -> Self {
+    SearchService { db }
+  }
+
+  /// 既存ノートの正規化済み検索インデックスを作成する (起動時に呼ぶ)
+  pub fn ensure_index(&self) -> Result<(), String> {
+    let conn = self.db.conn.lock().unwrap();
+    fts::ensure_search_index(&conn)
+      .map(|_| ())
+      .map_err(|e| format!("検索インデックスの作成に失敗しました: {}", e))
+  }
+
+  /// ノートを検索し、スコア・スニペット・一致箇所付きで返す
+  ///
+  /// 検索語がある場合は `sort` が Relevance なら関連度順、それ以外は日付の新しい順。
+  /// 検索語がない場合 (タグなどの条件のみ) は Relevance でも更新日時順になる。
+  /// クエリの構文は `query::parse_query` を参照。構文が正しくなければエラーを返す。
+  ///
+  /// 全角/半角、カタカナ/ひらがな、英字の大文字/小文字は区別しない。
+  /// 3 文字未満の語は trigram の索引では探せないため部分一致で探す (スコアには含まれない)。
+  pub fn search(&self, query: &str, sort: SearchSort) -> Result<Vec<SearchHit>, String> {
+    let node = parse_query(query)?;
+
+    let conn = self.db.conn.lock().unwrap();
+    let trigram = fts::uses_trigram(&conn)
+      .map_err(|e| format!("検索インデックスの確認に失敗しました: {}", e))?;
+    let compiled = Compiler { trigram }.compile(node);
+    let has_fts = compiled.fts.is_some();
+
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+    let mut conditions = vec!["n.is_deleted = FALSE".to_string()];
+
+    let (score, from) = if let Some(fts_query) = compiled.fts {
+      conditions.push("notes_search MATCH ?".to_string());
+      params.push(Box::new(fts_query));
+      (
+        format!("-bm25(notes_search, {TITLE_WEIGHT}, 1.0)"),
+        "notes_search JOIN notes n ON n.id = notes_search.rowid",
+      )
+    } else {
+      ("0.0".to_string(), "notes n")
+    };
+    conditions.extend(compiled.conditions);
+    params.extend(compiled.params);
+
+    let order_by = match sort {
+      SearchSort::Relevance if has_fts => {
+        format!("bm25(notes_search, {TITLE_WEIGHT}, 1.0), n.updated_at DESC")
+      }
+      SearchSort::Relevance | SearchSort::Updated => "n.updated_at DESC".to_string(),
+      SearchSort::Created => "n.created_at DESC".to_string(),
+    };
+
+    // 表示とハイライトには notes_fts にある元の文字列を使う
+    let sql = format!(
+      "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at,
+      n.is_favorite, {}, f.content, n.favorite_order
+      FROM {} LEFT JOIN notes_fts f ON f.rowid = n.id
+      WHERE {} ORDER BY {}",
+      score,
+      from,
+      conditions.join(" AND "),
+      order_by
+    );
+
+    let mut stmt = conn
+      .prepare(&sql)
+      .map_err(|e| format!("検索クエリの準備に失敗しました: {}", e))?;
+
+    let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let terms = compiled.terms;
+    let hits = stmt
+      .query_map(param_refs.as_slice(), |row| {
+        let note = Note {
+          id: row.get(0)?,
+          title: row.get(1)?,
+          created_at: row.get(2)?,
+          updated_at: row.get(3)?,
+          parent_id: row.get(4)?,
+          file_path: row.get(5)?,
+          preview: row.get(6)?,
+          is_deleted: row.get(7)?,
+          deleted_at: row.get(8)?,
+          is_favorite: row.get(9)?,
+          favorite_order: row.get(12)?,
+        };
+        let score: f64 = row.get(10)?;
+        let content: Option<String> = row.get(11)?;
+
+        let title_matches = find_matches(&note.title, SearchField::Title, &terms);
+        let content_matches = content
+          .as_deref()
+          .map(|c| find_matches(c, SearchField::Content, &terms))
+          .unwrap_or_default();
+
+        let title_chars: Vec<char> = note.title.chars().collect();
+        let title_highlight = to_highlight_html(&title_chars, &title_matches, 0, title_chars.len());
+        let snippet = content
+          .as_deref()
+          .and_then(|c| make_snippet(c, &content_matches))
+          .unwrap_or_else(|| escape_html(&note.preview));
+
+        Ok(SearchHit {
+          note,
+          score,
+          title_highlight,
+          snippet,
+          matches: title_matches.into_iter().chain(content_matches).collect(),
+        })
+      })
+      .map_err(|e| format!("検索に失敗しました: {}", e))?
+      .collect::<SqlResult<Vec<SearchHit>>>()
+      .map_err(|e| format!("検索に失敗しました: {}", e))?;
+
+    Ok(hits)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::services::{FolderService, NoteService};
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  #[test]
+  fn test_search_ranks_title_matches_first() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let service = SearchService::new(db.clone());
+
+    let body = notes
+      .create_note(
+        "Meeting".to_string(),
+        "Notes about the <release> plan".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    let title = notes
+      .create_note(
+        "Release plan".to_string(),
+        "Schedule".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    notes
+      .create_note("Other".to_string(), "Nothing".to_string(), None, None)
+      .unwrap();
+
+    let hits = service.search("release", SearchSort::Relevance).unwrap();
+    assert_eq!(
+      hits.iter().map(|h| h.note.id).collect::<Vec<_>>(),
+      vec![title.id, body.id]
+    );
+    assert!(hits[0].score > hits[1].score);
+    assert_eq!(hits[0].title_highlight, "<mark>Release</mark> plan");
+    assert_eq!(
+      hits[0].matches,
+      vec![MatchRange {
+        field: SearchField::Title,
+        start: 0,
+        end: 7,
+      }]
+    );
+
+    // スニペットは本文をエスケープしたうえで一致箇所を <mark> で囲む
+    assert!(hits[1].snippet.contains("&lt;<mark>release</mark>&gt;"));
+    assert_eq!(
+      hits[1].matches,
+      vec![MatchRange {
+        field: SearchField::Content,
+        start: 17,
+        end: 24,
+      }]
+    );
+
+    // 日付順では後から作成したノートが先になる
+    let hits = service.search("release", SearchSort::Created).unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits[0].note.created_at >= hits[1].note.created_at);
+  }
+
+  #[test]
+  fn test_search_match_offsets_are_in_chars() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let service = SearchService::new(db.clone());
+
+    notes
+      .create_note(
+        "日本語".to_string(),
+        "今日は会議の議事録を書いた".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+
+    let hits = service.search("議事録", SearchSort::Relevance).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+      hits[0].matches,
+      vec![MatchRange {
+        field: SearchField::Content,
+        start: 6,
+        end: 9,
+      }]
+    );
+    assert!(hits[0].snippet.contains("<mark>議事録</mark>"));
+  }
+
+  #[test]
+  fn test_search_without_words_filters_only() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let service = SearchService::new(db.clone());
+
+    let note = notes
+      .create_note("タグ付き".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    notes
+      .create_note("タグなし".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute("INSERT INTO tags (name) VALUES ('work')", [])
+        .unwrap();
+      conn
+        .execute(
+          "INSERT INTO note_tags (note_id, tag_id) VALUES (?, last_insert_rowid())",
+          [note.id],
+        )
+        .unwrap();
+    }
+
+    let hits = service.search("tag:work", SearchSort::Relevance).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].note.id, note.id);
+    assert_eq!(hits[0].score, 0.0);
+    assert!(hits[0].matches.is_empty());
+  }
+
+  fn search_ids(service: &SearchService, query: &str) -> Vec<i64> {
+    let mut ids: Vec<i64> = service
+      .search(query, SearchSort::Relevance)
+      .unwrap()
+      .into_iter()
+      .map(|h| h.note.id)
+      .collect();
+    ids.sort();
+    ids
+  }
+
+  #[test]
+  fn test_search_boolean_query() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    let notes = NoteService::new(db.clone(), base_path.clone());
+    let folders = FolderService::new(db.clone(), base_path.clone());
+    let service = SearchService::new(db.clone());
+
+    let work = folders
+      .create_folder("仕事".to_string(), None, None)
+      .unwrap();
+    let meeting = folders
+      .create_folder("会議".to_string(), Some(work.id), Some("仕事".to_string()))
+      .unwrap();
+
+    let a = notes
+      .create_note(
+        "週次定例".to_string(),
+        "release schedule and budget".to_string(),
+        Some(meeting.id),
+        Some("仕事/会議".to_string()),
+      )
+      .unwrap();
+    let b = notes
+      .create_note(
+        "budget".to_string(),
+        "draft release notes".to_string(),
+        Some(work.id),
+        Some("仕事".to_string()),
+      )
+      .unwrap();
+    let c = notes
+      .create_note("日記".to_string(), "release party".to_string(), None, None)
+      .unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute("INSERT INTO tags (name) VALUES ('done')", [])
+        .unwrap();
+      conn
+        .execute(
+          "INSERT INTO note_tags (note_id, tag_id) VALUES (?, last_insert_rowid())",
+          [b.id],
+        )
+        .unwrap();
+      conn
+        .execute("INSERT INTO tags (name) VALUES ('done/later')", [])
+        .unwrap();
+      conn
+        .execute(
+          "INSERT INTO note_tags (note_id, tag_id) VALUES (?, last_insert_rowid())",
+          [a.id],
+        )
+        .unwrap();
+      conn
+        .execute(
+          "UPDATE notes SET created_at = '2025-12-31 10:00:00', updated_at = '2026-01-15 09:00:00' WHERE id = ?",
+          [c.id],
+        )
+        .unwrap();
+    }
+
+    assert_eq!(search_ids(&service, "release"), vec![a.id, b.id, c.id]);
+    assert_eq!(search_ids(&service, "\"release schedule\""), vec![a.id]);
+    assert_eq!(search_ids(&service, "party OR draft"), vec![b.id, c.id]);
+    assert_eq!(search_ids(&service, "release -budget"), vec![c.id]);
+    assert_eq!(search_ids(&service, "title:budget"), vec![b.id]);
+    assert_eq!(search_ids(&service, "content:budget"), vec![a.id]);
+    assert_eq!(search_ids(&service, "sched*"), vec![a.id]);
+
+    // folder: はサブフォルダを含み、パスでも指定できる
+    assert_eq!(search_ids(&service, "folder:仕事"), vec![a.id, b.id]);
+    assert_eq!(search_ids(&service, "folder:仕事/会議"), vec![a.id]);
+    assert_eq!(search_ids(&service, "folder:会議"), vec![a.id]);
+    assert_eq!(search_ids(&service, "release -folder:仕事"), vec![c.id]);
+
+    assert_eq!(search_ids(&service, "folder:仕事 -tag:done"), vec![a.id]);
+    assert_eq!(search_ids(&service, "tag:done OR party"), vec![b.id, c.id]);
+    // tag:done/* は子孫のタグ (done/later) も含む
+    assert_eq!(search_ids(&service, "tag:done/*"), vec![a.id, b.id]);
+    assert_eq!(search_ids(&service, "tag:done/later"), vec![a.id]);
+
+    assert_eq!(search_ids(&service, "created:<2026-01-01"), vec![c.id]);
+    assert_eq!(
+      search_ids(&service, "updated:2026-01-01..2026-01-31 release"),
+      vec![c.id]
+    );
+    assert_eq!(
+      search_ids(&service, "updated:>2026-01-15"),
+      vec![a.id, b.id]
+    );
+
+    assert!(service.search("(release", SearchSort::Relevance).is_err());
+  }
+
+  #[test]
+  fn test_search_japanese_and_short_queries() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let service = SearchService::new(db.clone());
+
+    let a = notes
+      .create_note(
+        "ミーティング議事録".to_string(),
+        "ＡＰＩの設計について話した。次回は木曜".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    let b = notes
+      .create_note(
+        "買い物メモ".to_string(),
+        "ﾀﾏｺﾞ、牛乳、API key の更新".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    let c = notes
+      .create_note(
+        "English notes".to_string(),
+        "Go is a language".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+
+    // カタカナ/ひらがな、全角/半角の違いを無視する
+    assert_eq!(search_ids(&service, "みーてぃんぐ"), vec![a.id]);
+    assert_eq!(search_ids(&service, "api"), vec![a.id, b.id]);
+    assert_eq!(search_ids(&service, "たまご"), vec![b.id]);
+    assert_eq!(search_ids(&service, "ﾐｰﾃｨﾝｸﾞ"), vec![a.id]);
+
+    // 3 文字未満の語
+    assert_eq!(search_ids(&service, "議事"), vec![a.id]);
+    assert_eq!(search_ids(&service, "メモ"), vec![b.id]);
+    assert_eq!(search_ids(&service, "go"), vec![c.id]);
+    assert_eq!(search_ids(&service, "木"), vec![a.id]);
+    assert_eq!(search_ids(&service, "api -牛乳"), vec![a.id]);
+    assert_eq!(search_ids(&service, "title:メモ OR 木"), vec![a.id, b.id]);
+
+    // 一致箇所は元の文字列の位置で、スニペットは元の表記のまま
+    let hits = service
+      .search("てぃんぐ 設計", SearchSort::Relevance)
+      .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].title_highlight, "ミー<mark>ティング</mark>議事録");
+    assert_eq!(
+      hits[0].snippet,
+      "ＡＰＩの<mark>設計</mark>について話した。次回は木曜"
+    );
+    assert!(hits[0].score > 0.0);
+
+    // 短い語だけのクエリでもハイライトされる
+    let hits = service.search("ＡＰ", SearchSort::Relevance).unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().all(|h| h.snippet.contains("<mark>")));
+  }
+
+  #[test]
+  fn test_ensure_index_rebuilds_search_index() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let service = SearchService::new(db.clone());
+
+    let note = notes
+      .create_note("カタカナ".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      conn.execute("DELETE FROM notes_search", []).unwrap();
+    }
+    assert!(search_ids(&service, "かたかな").is_empty());
+
+    service.ensure_index().unwrap();
+    assert_eq!(search_ids(&service, "かたかな"), vec![note.id]);
+  }
+}
@@ -1,8 +1,229 @@
 use crate::db::Database;
-use crate::db::models::{Note, Tag};
-use rusqlite::{Result as SqlResult, params};
+use crate::db::models::{Note, NoteTag, Tag, TagWithStats};
+use crate::services::atomic;
+use crate::services::properties::{
+  PropertyService, parse_properties, replace_front_matter, split_front_matter,
+};
+use crate::services::search::escape_like;
+use crate::services::{NoteService, fts};
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult, Row, params};
+use std::ops::Range;
 use std::sync::Arc;
 
+const TAG_COLUMNS: &str = "id, name, color, parent_id, created_at, updated_at";
+
+fn row_to_tag(row: &Row) -> SqlResult<Tag> {
+  Ok(Tag {
+    id: row.get(0)?,
+    name: row.get(1)?,
+    color: row.get(2)?,
+    parent_id: row.get(3)?,
+    created_at: row.get(4)?,
+    updated_at: row.get(5)?,
+  })
+}
+
+/// タグ名を "親/子/孫" の形に揃える (各階層の前後の空白と先頭の # を取り除く)
+pub fn normalize_tag_name(name: &str) -> Result<String, String> {
+  let name = name.trim().trim_start_matches('#');
+  let segments: Vec<&str> = name.split('/').map(str::trim).collect();
+  if segments.iter().any(|segment| segment.is_empty()) {
+    return Err(format!("Invalid tag name: {}", name));
+  }
+  Ok(segments.join("/"))
+}
+
+// "a/b/c" の親 "a/b" (最上位なら None)
+fn parent_name(name: &str) -> Option<&str> {
+  name.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// タグを名前で探し、なければ親のタグも含めて作成する
+pub fn ensure_tag(conn: &Connection, name: &str) -> SqlResult<i64> {
+  if let Some(id) = conn
+    .query_row("SELECT id FROM tags WHERE name = ?", params![name], |row| {
+      row.get(0)
+    })
+    .optional()?
+  {
+    return Ok(id);
+  }
+  let parent_id = match parent_name(name) {
+    Some(parent) => Some(ensure_tag(conn, parent)?),
+    None => None,
+  };
+  conn.execute(
+    "INSERT INTO tags (name, parent_id) VALUES (?, ?)",
+    params![name, parent_id],
+  )?;
+  Ok(conn.last_insert_rowid())
+}
+
+fn find_tag(conn: &Connection, id: i64) -> SqlResult<Tag> {
+  conn.query_row(
+    &format!("SELECT {} FROM tags WHERE id = ?", TAG_COLUMNS),
+    params![id],
+    row_to_tag,
+  )
+}
+
+// 子孫のタグ ("name/..." で始まるもの) に一致する LIKE のパターン
+fn descendants_pattern(name: &str) -> String {
+  format!("{}/%", escape_like(name))
+}
+
+// #タグ の直前に置ける文字 (URL の #fragment や &#123; を拾わないようにする)
+fn can_precede_hashtag(c: Option<char>) -> bool {
+  match c {
+    None => true,
+    Some(c) => c.is_whitespace() || "([{（「『【、。，,".contains(c),
+  }
+}
+
+fn is_hashtag_char(c: char) -> bool {
+  c.is_alphanumeric() || c == '_' || c == '-' || c == '/'
+}
+
+// 見出しの行か (# の後に空白が続く)
+fn is_heading(line: &str) -> bool {
+  let hashes = line.chars().take_while(|c| *c == '#').count();
+  (1..=6).contains(&hashes)
+    && line[hashes..]
+      .chars()
+      .next()
+      .is_none_or(char::is_whitespace)
+}
+
+// 本文中の #タグ の名前部分 (# を除く) のバイト範囲を出現順に返す
+//
+// front matter、コードブロック、行内のコード、見出しの行は対象にしない。数字だけのもの (`#123`) はタグにしない。
+fn hashtag_spans(content: &str) -> Vec<Range<usize>> {
+  let mut spans = Vec::new();
+  let mut fence: Option<&str> = None;
+  let (_, body) = split_front_matter(content);
+  let mut offset = content.len() - body.len();
+
+  for line in body.split_inclusive('\n') {
+    let line_start = offset;
+    offset += line.len();
+    let trimmed = line.trim_start();
+    if let Some(marker) = fence {
+      if trimmed.starts_with(marker) {
+        fence = None;
+      }
+      continue;
+    }
+    if trimmed.starts_with("```") {
+      fence = Some("```");
+      continue;
+    }
+    if trimmed.starts_with("~~~") {
+      fence = Some("~~~");
+      continue;
+    }
+    if is_heading(trimmed) {
+      continue;
+    }
+
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut in_code = false;
+    let mut i = 0;
+    while i < chars.len() {
+      let (_, c) = chars[i];
+      if c == '`' {
+        in_code = !in_code;
+      }
+      if in_code || c != '#' || !can_precede_hashtag(i.checked_sub(1).map(|p| chars[p].1)) {
+        i += 1;
+        continue;
+      }
+      // # は 1 バイト
+      let byte_start = chars[i].0 + 1;
+      let mut end = i + 1;
+      while end < chars.len() && is_hashtag_char(chars[end].1) {
+        end += 1;
+      }
+      i = end;
+
+      let byte_end = chars.get(end).map_or(line.len(), |(pos, _)| *pos);
+      let raw = line[byte_start..byte_end].trim_end_matches(['/', '-']);
+      if raw.is_empty() || raw.chars().all(|c| c.is_ascii_digit()) {
+        continue;
+      }
+      spans.push(line_start + byte_start..line_start + byte_start + raw.len());
+    }
+  }
+
+  spans
+}
+
+/// 本文から `#タグ` を重複なく出現順に取り出す
+///
+/// `#work/projectA` のように / で区切ると階層のあるタグになる。
+pub fn parse_hashtags(content: &str) -> Vec<String> {
+  let mut tags: Vec<String> = Vec::new();
+  for span in hashtag_spans(content) {
+    if let Ok(name) = normalize_tag_name(&content[span])
+      && !tags.contains(&name)
+    {
+      tags.push(name);
+    }
+  }
+  tags
+}
+
+/// 本文の `#old` (子孫の `#old/...` も含む) を `#new` に書き換える
+///
+/// 書き換える箇所がなければ None を返す。
+pub fn rename_hashtags(content: &str, old: &str, new: &str) -> Option<String> {
+  let mut result = String::with_capacity(content.len());
+  let mut last = 0;
+  for span in hashtag_spans(content) {
+    let raw = &content[span.clone()];
+    let rest = if raw == old {
+      ""
+    } else if let Some(rest) = raw.strip_prefix(old)
+      && rest.starts_with('/')
+    {
+      rest
+    } else {
+      continue;
+    };
+    result.push_str(&content[last..span.start]);
+    result.push_str(new);
+    result.push_str(rest);
+    last = span.end;
+  }
+  if last == 0 {
+    return None;
+  }
+  result.push_str(&content[last..]);
+  Some(result)
+}
+
+// front matter の tags の old (子孫を含む) を new に書き換える
+fn rename_front_matter_tags(content: &str, old: &str, new: &str) -> Option<String> {
+  let mut properties = parse_properties(content).ok()?;
+  let mut changed = false;
+  for tag in &mut properties.tags {
+    let rest = if tag == old {
+      ""
+    } else if let Some(rest) = tag.strip_prefix(old)
+      && rest.starts_with('/')
+    {
+      rest
+    } else {
+      continue;
+    };
+    *tag = format!("{}{}", new, rest);
+    changed = true;
+  }
+  if !changed {
+    return None;
+  }
+  replace_front_matter(content, &properties).ok()
+}
+
 pub struct TagService {
   db: Arc<Database>,
 }
@@ -12,100 +233,461 @@ impl TagService {
     TagService { db }
   }
 
+  /// タグを作成する ("work/projectA" のように書くと親のタグも作成する)
   pub fn create_tag(&self, name: String, color: Option<String>) -> Result<Tag, String> {
+    let name = normalize_tag_name(&name)?;
     let conn = self.db.conn.lock().unwrap();
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let parent_id = match parent_name(&name) {
+      Some(parent) => {
+        Some(ensure_tag(&tx, parent).map_err(|e| format!("Failed to create parent tag: {}", e))?)
+      }
+      None => None,
+    };
+    tx.execute(
+      "INSERT INTO tags (name, color, parent_id) VALUES (?, ?, ?)",
+      params![name, color, parent_id],
+    )
+    .map_err(|e| format!("Failed to create tag: {}", e))?;
+
+    let tag = find_tag(&tx, tx.last_insert_rowid())
+      .map_err(|e| format!("Failed to fetch created tag: {}", e))?;
+    tx.commit()
+      .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(tag)
+  }
+
+  /// タグの名前と色を更新する
+  ///
+  /// 名前を変えると子孫のタグの名前も変わる。親の部分を変えた場合は別の親の下に移動する。
+  /// `rewrite_content` が true なら、ノートの本文の `#旧名` も `#新名` に書き換える。
+  pub fn update_tag(
+    &self,
+    id: i64,
+    name: String,
+    color: Option<String>,
+    rewrite_content: bool,
+  ) -> Result<Tag, String> {
+    let name = normalize_tag_name(&name)?;
+    let (tag, old_name, note_ids) = {
+      let conn = self.db.conn.lock().unwrap();
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+      let old_name = find_tag(&tx, id)
+        .map_err(|e| format!("Failed to fetch tag: {}", e))?
+        .name;
+      let note_ids = if rewrite_content && old_name != name {
+        Self::inline_note_ids(&tx, &old_name)
+          .map_err(|e| format!("Failed to query notes: {}", e))?
+      } else {
+        Vec::new()
+      };
+
+      Self::rename(&tx, id, &name)?;
+      tx.execute(
+        "UPDATE tags SET color = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![color, id],
+      )
+      .map_err(|e| format!("Failed to update tag: {}", e))?;
+
+      let tag = find_tag(&tx, id).map_err(|e| format!("Failed to fetch updated tag: {}", e))?;
+      tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+      (tag, old_name, note_ids)
+    };
+
+    self.rewrite_notes(&note_ids, &[(old_name, tag.name.clone())])?;
+    Ok(tag)
+  }
+
+  /// 複数のタグを target_id のタグにまとめる
+  ///
+  /// ノートの関連付けは重複なく移し、子のタグは target の下に移す (同じ名前があればそれにまとめる)。
+  /// まとめたタグが本文の #タグ で付いていたノートは、本文も書き換える。
+  pub fn merge_tags(&self, source_ids: &[i64], target_id: i64) -> Result<Tag, String> {
+    let (target, renames, note_ids) = {
+      let conn = self.db.conn.lock().unwrap();
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+      let target = find_tag(&tx, target_id).map_err(|e| format!("Failed to fetch tag: {}", e))?;
+      let mut renames = Vec::new();
+      let mut note_ids: Vec<i64> = Vec::new();
+      for &source_id in source_ids {
+        if source_id == target_id {
+          continue;
+        }
+        // 先にまとめた親のタグと一緒に移動・統合済みのものは飛ばす
+        let Some(source) = find_tag(&tx, source_id)
+          .optional()
+          .map_err(|e| format!("Failed to fetch tag: {}", e))?
+        else {
+          continue;
+        };
+        for note_id in Self::inline_note_ids(&tx, &source.name)
+          .map_err(|e| format!("Failed to query notes: {}", e))?
+        {
+          if !note_ids.contains(&note_id) {
+            note_ids.push(note_id);
+          }
+        }
+        Self::merge_into(&tx, source_id, target_id)?;
+        renames.push((source.name, target.name.clone()));
+      }
+
+      tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+      (target, renames, note_ids)
+    };
+
+    self.rewrite_notes(&note_ids, &renames)?;
+    Ok(target)
+  }
+
+  // source のタグを target のタグにまとめて削除する
+  fn merge_into(conn: &Connection, source_id: i64, target_id: i64) -> Result<(), String> {
+    let source = find_tag(conn, source_id).map_err(|e| format!("Failed to fetch tag: {}", e))?;
+    let target = find_tag(conn, target_id).map_err(|e| format!("Failed to fetch tag: {}", e))?;
+    if target.name.starts_with(&format!("{}/", source.name)) {
+      return Err(format!(
+        "Cannot merge tag into its own descendant: {}",
+        target.name
+      ));
+    }
+
     conn
       .execute(
-        "INSERT INTO tags (name, color) VALUES (?, ?)",
-        params![name, color],
+        "INSERT INTO note_tags (note_id, tag_id, is_manual, is_inline, created_at)
+         SELECT note_id, ?, is_manual, is_inline, created_at FROM note_tags WHERE tag_id = ?
+         ON CONFLICT(note_id, tag_id) DO UPDATE SET
+           is_manual = note_tags.is_manual OR excluded.is_manual,
+           is_inline = note_tags.is_inline OR excluded.is_inline,
+           created_at = MAX(note_tags.created_at, excluded.created_at)",
+        params![target_id, source_id],
       )
-      .map_err(|e| format!("Failed to create tag: {}", e))?;
+      .map_err(|e| format!("Failed to merge tags: {}", e))?;
 
-    let id = conn.last_insert_rowid();
+    let children: Vec<(i64, String)> = {
+      let mut stmt = conn
+        .prepare("SELECT id, name FROM tags WHERE parent_id = ?")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+      stmt
+        .query_map(params![source_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to query tags: {}", e))?
+        .collect::<SqlResult<_>>()
+        .map_err(|e| format!("Failed to collect tags: {}", e))?
+    };
+    for (child_id, child_name) in children {
+      let leaf = child_name.rsplit('/').next().unwrap_or(&child_name);
+      let new_name = format!("{}/{}", target.name, leaf);
+      let existing: Option<i64> = conn
+        .query_row(
+          "SELECT id FROM tags WHERE name = ?",
+          params![new_name],
+          |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to fetch tag: {}", e))?;
+      match existing {
+        Some(existing_id) => Self::merge_into(conn, child_id, existing_id)?,
+        None => Self::rename(conn, child_id, &new_name)?,
+      }
+    }
 
-    let tag = conn
-      .query_row(
-        "SELECT id, name, color, created_at, updated_at FROM tags WHERE id = ?",
-        params![id],
-        |row| {
-          Ok(Tag {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            color: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-          })
-        },
+    conn
+      .execute("DELETE FROM tags WHERE id = ?", params![source_id])
+      .map_err(|e| format!("Failed to delete tag: {}", e))?;
+    Ok(())
+  }
+
+  // 本文の #タグ でタグ (子孫を含む) が付いているノート
+  fn inline_note_ids(conn: &Connection, name: &str) -> SqlResult<Vec<i64>> {
+    let mut stmt = conn.prepare(
+      "SELECT DISTINCT nt.note_id FROM note_tags nt
+         INNER JOIN tags t ON t.id = nt.tag_id
+         WHERE nt.is_inline = TRUE AND (t.name = ? OR t.name LIKE ? ESCAPE '\\')",
+    )?;
+    stmt
+      .query_map(params![name, descendants_pattern(name)], |row| row.get(0))?
+      .collect()
+  }
+
+  // ノートの本文の #タグ を書き換え、検索インデックスとタグを更新する
+  fn rewrite_notes(&self, note_ids: &[i64], renames: &[(String, String)]) -> Result<(), String> {
+    for &id in note_ids {
+      let file_path: Option<String> = {
+        let conn = self.db.conn.lock().unwrap();
+        conn
+          .query_row(
+            "SELECT file_path FROM notes WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+          )
+          .optional()
+          .map_err(|e| format!("Failed to fetch note: {}", e))?
+          .flatten()
+      };
+      let Some(file_path) = file_path else {
+        continue;
+      };
+      let Ok(content) = std::fs::read_to_string(&file_path) else {
+        continue;
+      };
+
+      let mut rewritten = content.clone();
+      for (old, new) in renames {
+        if let Some(next) = rename_hashtags(&rewritten, old, new) {
+          rewritten = next;
+        }
+        if let Some(next) = rename_front_matter_tags(&rewritten, old, new) {
+          rewritten = next;
+        }
+      }
+      if rewritten == content {
+        continue;
+      }
+
+      atomic::write_file(std::path::Path::new(&file_path), &rewritten)
+        .map_err(|e| format!("Failed to write note: {}", e))?;
+      let conn = self.db.conn.lock().unwrap();
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+      tx.execute(
+        "UPDATE notes SET preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![NoteService::generate_preview(&rewritten), id],
       )
-      .map_err(|e| format!("Failed to fetch created tag: {}", e))?;
+      .map_err(|e| format!("Failed to update note: {}", e))?;
+      fts::update_content(&tx, id, &rewritten)
+        .map_err(|e| format!("Failed to update search index: {}", e))?;
+      Self::index_note(&tx, id, &rewritten).map_err(|e| format!("Failed to index tags: {}", e))?;
+      PropertyService::index_note(&tx, id, &rewritten)
+        .map_err(|e| format!("Failed to index properties: {}", e))?;
+      tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    }
+    Ok(())
+  }
+
+  /// タグを別の親の下 (None なら最上位) に移動する
+  pub fn move_tag(&self, id: i64, new_parent_id: Option<i64>) -> Result<Tag, String> {
+    let conn = self.db.conn.lock().unwrap();
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let tag = find_tag(&tx, id).map_err(|e| format!("Failed to fetch tag: {}", e))?;
+    let leaf = tag.name.rsplit('/').next().unwrap_or(&tag.name);
+    let new_name = match new_parent_id {
+      Some(parent_id) => {
+        let parent = find_tag(&tx, parent_id).map_err(|e| format!("Failed to fetch tag: {}", e))?;
+        format!("{}/{}", parent.name, leaf)
+      }
+      None => leaf.to_string(),
+    };
+    Self::rename(&tx, id, &new_name)?;
+
+    let tag = find_tag(&tx, id).map_err(|e| format!("Failed to fetch updated tag: {}", e))?;
+    tx.commit()
+      .map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
     Ok(tag)
   }
 
-  pub fn update_tag(&self, id: i64, name: String, color: Option<String>) -> Result<Tag, String> {
-    let conn = self.db.conn.lock().unwrap();
+  // タグの名前を変え、子孫のタグの名前と親を付け替える
+  fn rename(conn: &Connection, id: i64, new_name: &str) -> Result<(), String> {
+    let old_name: String = conn
+      .query_row("SELECT name FROM tags WHERE id = ?", params![id], |row| {
+        row.get(0)
+      })
+      .map_err(|e| format!("Failed to fetch tag: {}", e))?;
+    if old_name == new_name {
+      return Ok(());
+    }
+    if new_name.starts_with(&format!("{}/", old_name)) {
+      return Err(format!(
+        "Cannot move tag into its own descendant: {}",
+        new_name
+      ));
+    }
+    let exists: bool = conn
+      .query_row(
+        "SELECT EXISTS(SELECT 1 FROM tags WHERE name = ?)",
+        params![new_name],
+        |row| row.get(0),
+      )
+      .map_err(|e| format!("Failed to fetch tag: {}", e))?;
+    if exists {
+      return Err(format!("Tag already exists: {}", new_name));
+    }
+
+    let parent_id = match parent_name(new_name) {
+      Some(parent) => {
+        Some(ensure_tag(conn, parent).map_err(|e| format!("Failed to create parent tag: {}", e))?)
+      }
+      None => None,
+    };
     conn
       .execute(
-        "UPDATE tags SET name = ?, color = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-        params![name, color, id],
+        "UPDATE tags SET name = ?, parent_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![new_name, parent_id, id],
       )
       .map_err(|e| format!("Failed to update tag: {}", e))?;
-
-    let tag = conn
-      .query_row(
-        "SELECT id, name, color, created_at, updated_at FROM tags WHERE id = ?",
-        params![id],
-        |row| {
-          Ok(Tag {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            color: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-          })
-        },
+    // 子孫は parent_id はそのままで、名前の先頭だけを置き換える
+    conn
+      .execute(
+        "UPDATE tags SET name = ? || substr(name, ?), updated_at = CURRENT_TIMESTAMP
+         WHERE name LIKE ? ESCAPE '\\'",
+        params![
+          new_name,
+          old_name.chars().count() as i64 + 1,
+          descendants_pattern(&old_name)
+        ],
       )
-      .map_err(|e| format!("Failed to fetch updated tag: {}", e))?;
-
-    Ok(tag)
+      .map_err(|e| format!("Failed to rename child tags: {}", e))?;
+    Ok(())
   }
 
+  /// タグを削除する (子孫のタグも削除する)
   pub fn delete_tag(&self, id: i64) -> Result<(), String> {
     let conn = self.db.conn.lock().unwrap();
+    let Some(name) = conn
+      .query_row("SELECT name FROM tags WHERE id = ?", params![id], |row| {
+        row.get::<_, String>(0)
+      })
+      .optional()
+      .map_err(|e| format!("Failed to fetch tag: {}", e))?
+    else {
+      return Ok(());
+    };
     conn
-      .execute("DELETE FROM tags WHERE id = ?", params![id])
+      .execute(
+        "DELETE FROM tags WHERE id = ? OR name LIKE ? ESCAPE '\\'",
+        params![id, descendants_pattern(&name)],
+      )
       .map_err(|e| format!("Failed to delete tag: {}", e))?;
     Ok(())
   }
 
-  pub fn get_all_tags(&self) -> Result<Vec<Tag>, String> {
+  /// すべてのタグを、付いているノートの数と最後に使われた日時とともに返す
+  pub fn get_all_tags(&self) -> Result<Vec<TagWithStats>, String> {
     let conn = self.db.conn.lock().unwrap();
     let mut stmt = conn
-      .prepare("SELECT id, name, color, created_at, updated_at FROM tags ORDER BY name ASC")
+      .prepare(
+        "SELECT t.id, t.name, t.color, t.parent_id, t.created_at, t.updated_at,
+                COUNT(n.id), MAX(MAX(nt.created_at, n.updated_at))
+           FROM tags t
+           LEFT JOIN note_tags nt ON nt.tag_id = t.id
+           LEFT JOIN notes n ON n.id = nt.note_id AND n.is_deleted = FALSE
+           GROUP BY t.id
+           ORDER BY t.name ASC",
+      )
       .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     let tags = stmt
       .query_map([], |row| {
-        Ok(Tag {
-          id: row.get(0)?,
-          name: row.get(1)?,
-          color: row.get(2)?,
-          created_at: row.get(3)?,
-          updated_at: row.get(4)?,
+        Ok(TagWithStats {
+          tag: row_to_tag(row)?,
+          note_count: row.get(6)?,
+          last_used_at: row.get(7)?,
         })
       })
       .map_err(|e| format!("Failed to query tags: {}", e))?
-      .collect::<SqlResult<Vec<Tag>>>()
+      .collect::<SqlResult<Vec<TagWithStats>>>()
       .map_err(|e| format!("Failed to collect tags: {}", e))?;
 
     Ok(tags)
   }
 
+  /// ノートの本文にある #タグ を note_tags に反映する
+  ///
+  /// front matter の tags も本文の #タグ と同じに扱う。
+  /// 本文から消えたタグは、手動でも付けられていなければノートから外す。
+  pub fn index_note(conn: &Connection, note_id: i64, content: &str) -> SqlResult<()> {
+    let mut names = parse_properties(content).unwrap_or_default().tags;
+    for name in parse_hashtags(content) {
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+    let mut tag_ids = Vec::new();
+    for name in names {
+      tag_ids.push(ensure_tag(conn, &name)?);
+    }
+
+    conn.execute(
+      "UPDATE note_tags SET is_inline = FALSE WHERE note_id = ?",
+      params![note_id],
+    )?;
+    for tag_id in tag_ids {
+      conn.execute(
+        "INSERT INTO note_tags (note_id, tag_id, is_manual, is_inline) VALUES (?, ?, FALSE, TRUE)
+         ON CONFLICT(note_id, tag_id) DO UPDATE SET is_inline = TRUE",
+        params![note_id, tag_id],
+      )?;
+    }
+    conn.execute(
+      "DELETE FROM note_tags WHERE note_id = ? AND is_manual = FALSE AND is_inline = FALSE",
+      params![note_id],
+    )?;
+
+    conn.execute(
+      "UPDATE notes SET tags_indexed = TRUE WHERE id = ?",
+      params![note_id],
+    )?;
+
+    Ok(())
+  }
+
+  /// まだ #タグ を解析していないノートを解析する
+  ///
+  /// ファイルの読み込み中はデータベースをロックしないため、起動直後にバックグラウンドで実行できる。
+  pub fn ensure_index(&self) -> Result<usize, String> {
+    let pending: Vec<(i64, String)> = {
+      let conn = self.db.conn.lock().unwrap();
+      let mut stmt = conn
+        .prepare(
+          "SELECT id, file_path FROM notes WHERE tags_indexed = FALSE AND is_deleted = FALSE AND file_path IS NOT NULL",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+      stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to query notes: {}", e))?
+        .collect::<SqlResult<Vec<(i64, String)>>>()
+        .map_err(|e| format!("Failed to collect notes: {}", e))?
+    };
+
+    let mut indexed = 0;
+    for (id, file_path) in pending {
+      let Ok(content) = std::fs::read_to_string(&file_path) else {
+        continue;
+      };
+      let conn = self.db.conn.lock().unwrap();
+      let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+      Self::index_note(&tx, id, &content).map_err(|e| format!("Failed to index tags: {}", e))?;
+      tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+      indexed += 1;
+    }
+
+    Ok(indexed)
+  }
+
   pub fn add_tag_to_note(&self, note_id: i64, tag_id: i64) -> Result<(), String> {
     let conn = self.db.conn.lock().unwrap();
     conn
       .execute(
-        "INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?, ?)",
+        "INSERT INTO note_tags (note_id, tag_id) VALUES (?, ?)
+         ON CONFLICT(note_id, tag_id) DO UPDATE SET is_manual = TRUE",
         params![note_id, tag_id],
       )
       .map_err(|e| format!("Failed to add tag to note: {}", e))?;
@@ -120,7 +702,10 @@ impl TagService {
 
     {
       let mut stmt = tx
-        .prepare("INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?, ?)")
+        .prepare(
+          "INSERT INTO note_tags (note_id, tag_id) VALUES (?, ?)
+           ON CONFLICT(note_id, tag_id) DO UPDATE SET is_manual = TRUE",
+        )
         .map_err(|e| e.to_string())?;
       for note_id in note_ids {
         stmt
@@ -134,29 +719,46 @@ impl TagService {
     Ok(())
   }
 
+  /// 手動で付けたタグを外す (本文に #タグ が残っていればタグは付いたままになる)
   pub fn remove_tag_from_note(&self, note_id: i64, tag_id: i64) -> Result<(), String> {
     let conn = self.db.conn.lock().unwrap();
     conn
       .execute(
-        "DELETE FROM note_tags WHERE note_id = ? AND tag_id = ?",
+        "UPDATE note_tags SET is_manual = FALSE WHERE note_id = ? AND tag_id = ?",
+        params![note_id, tag_id],
+      )
+      .map_err(|e| format!("Failed to remove tag from note: {}", e))?;
+    conn
+      .execute(
+        "DELETE FROM note_tags WHERE note_id = ? AND tag_id = ? AND is_inline = FALSE",
         params![note_id, tag_id],
       )
       .map_err(|e| format!("Failed to remove tag from note: {}", e))?;
     Ok(())
   }
 
-  pub fn get_notes_by_tag(&self, tag_id: i64) -> Result<Vec<Note>, String> {
+  /// タグが付いたノートを取得する (include_descendants なら子孫のタグが付いたノートも含める)
+  pub fn get_notes_by_tag(
+    &self,
+    tag_id: i64,
+    include_descendants: bool,
+  ) -> Result<Vec<Note>, String> {
     let conn = self.db.conn.lock().unwrap();
     let mut stmt = conn.prepare(
-      "SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at, n.is_favorite, n.favorite_order
+      "WITH RECURSIVE subtree(id) AS (
+         SELECT ?1
+         UNION
+         SELECT t.id FROM tags t JOIN subtree s ON t.parent_id = s.id WHERE ?2
+       )
+       SELECT n.id, n.title, n.created_at, n.updated_at, n.parent_id, n.file_path, n.preview, n.is_deleted, n.deleted_at, n.is_favorite, n.favorite_order
        FROM notes n
-       INNER JOIN note_tags nt ON n.id = nt.note_id
-       WHERE nt.tag_id = ? AND n.is_deleted = FALSE
+       WHERE n.id IN (SELECT nt.note_id FROM note_tags nt JOIN subtree s ON nt.tag_id = s.id)
+       AND n.is_deleted = FALSE
        ORDER BY n.updated_at DESC"
     ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     let notes = stmt
-      .query_map(params![tag_id], |row| {
+      .query_map(params![tag_id, include_descendants], |row| {
         Ok(Note {
           id: row.get(0)?,
           title: row.get(1)?,
@@ -178,11 +780,11 @@ impl TagService {
     Ok(notes)
   }
 
-  pub fn get_tags_by_note(&self, note_id: i64) -> Result<Vec<Tag>, String> {
+  pub fn get_tags_by_note(&self, note_id: i64) -> Result<Vec<NoteTag>, String> {
     let conn = self.db.conn.lock().unwrap();
     let mut stmt = conn
       .prepare(
-        "SELECT t.id, t.name, t.color, t.created_at, t.updated_at
+        "SELECT t.id, t.name, t.color, t.parent_id, t.created_at, t.updated_at, nt.is_manual, nt.is_inline
            FROM tags t
            INNER JOIN note_tags nt ON t.id = nt.tag_id
            WHERE nt.note_id = ?
@@ -192,16 +794,14 @@ impl TagService {
 
     let tags = stmt
       .query_map(params![note_id], |row| {
-        Ok(Tag {
-          id: row.get(0)?,
-          name: row.get(1)?,
-          color: row.get(2)?,
-          created_at: row.get(3)?,
-          updated_at: row.get(4)?,
+        Ok(NoteTag {
+          tag: row_to_tag(row)?,
+          is_manual: row.get(6)?,
+          is_inline: row.get(7)?,
         })
       })
       .map_err(|e| format!("Failed to query tags: {}", e))?
-      .collect::<SqlResult<Vec<Tag>>>()
+      .collect::<SqlResult<Vec<NoteTag>>>()
       .map_err(|e| format!("Failed to collect tags: {}", e))?;
 
     Ok(tags)
@@ -248,8 +848,7 @@ mod tests {
     service.create_tag("タグ2".to_string(), None).unwrap();
 
     let tags = service.get_all_tags().unwrap();
-    // デフォルトで「お気に入り」タグが存在するため、3つになる
-    assert!(tags.len() >= 2);
+    assert_eq!(tags.len(), 2);
   }
 
   #[test]
@@ -263,6 +862,7 @@ mod tests {
       created.id,
       "新しい名前".to_string(),
       Some("#00FF00".to_string()),
+      false,
     );
     assert!(result.is_ok());
     let updated = result.unwrap();
@@ -281,8 +881,8 @@ mod tests {
     assert!(result.is_ok());
 
     let tags = service.get_all_tags().unwrap();
-    // 削除されたタグは含まれない（デフォルトの「お気に入り」タグは残る）
-    assert!(!tags.iter().any(|t| t.id == created.id));
+    // 削除されたタグは含まれない
+    assert!(!tags.iter().any(|t| t.tag.id == created.id));
   }
 
   #[test]
@@ -303,7 +903,7 @@ mod tests {
     assert!(result.is_ok());
 
     let tags = tag_service.get_tags_by_note(note.id).unwrap();
-    assert!(tags.iter().any(|t| t.id == tag.id));
+    assert!(tags.iter().any(|t| t.tag.id == tag.id));
   }
 
   #[test]
@@ -325,7 +925,7 @@ mod tests {
     assert!(result.is_ok());
 
     let tags = tag_service.get_tags_by_note(note.id).unwrap();
-    assert!(!tags.iter().any(|t| t.id == tag.id));
+    assert!(!tags.iter().any(|t| t.tag.id == tag.id));
   }
 
   #[test]
@@ -348,7 +948,292 @@ mod tests {
     tag_service.add_tag_to_note(note1.id, tag.id).unwrap();
     tag_service.add_tag_to_note(note2.id, tag.id).unwrap();
 
-    let notes = tag_service.get_notes_by_tag(tag.id).unwrap();
+    let notes = tag_service.get_notes_by_tag(tag.id, false).unwrap();
     assert_eq!(notes.len(), 2);
   }
+
+  #[test]
+  fn test_hierarchical_tags() {
+    let (db, temp_dir) = setup_test_db();
+    let tag_service = TagService::new(db.clone());
+    let note_service =
+      crate::services::note::NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+
+    // 親のタグは自動で作られる
+    let meeting = tag_service
+      .create_tag(" #work / projectA/meeting ".to_string(), None)
+      .unwrap();
+    assert_eq!(meeting.name, "work/projectA/meeting");
+    let tags = tag_service.get_all_tags().unwrap();
+    let by_name = |name: &str| {
+      tags
+        .iter()
+        .find(|t| t.tag.name == name)
+        .unwrap()
+        .tag
+        .clone()
+    };
+    let work = by_name("work");
+    let project = by_name("work/projectA");
+    assert_eq!(work.parent_id, None);
+    assert_eq!(project.parent_id, Some(work.id));
+    assert_eq!(meeting.parent_id, Some(project.id));
+    assert!(tag_service.create_tag("work//x".to_string(), None).is_err());
+
+    let note1 = note_service
+      .create_note("ノート1".to_string(), String::new(), None, None)
+      .unwrap();
+    let note2 = note_service
+      .create_note("ノート2".to_string(), String::new(), None, None)
+      .unwrap();
+    tag_service.add_tag_to_note(note1.id, work.id).unwrap();
+    tag_service.add_tag_to_note(note2.id, meeting.id).unwrap();
+
+    let ids = |notes: Vec<Note>| {
+      let mut ids: Vec<i64> = notes.iter().map(|n| n.id).collect();
+      ids.sort();
+      ids
+    };
+    assert_eq!(
+      ids(tag_service.get_notes_by_tag(work.id, false).unwrap()),
+      vec![note1.id]
+    );
+    assert_eq!(
+      ids(tag_service.get_notes_by_tag(work.id, true).unwrap()),
+      vec![note1.id, note2.id]
+    );
+
+    // 親の名前を変えると子孫の名前も変わる
+    let renamed = tag_service
+      .update_tag(project.id, "work/projectB".to_string(), None, false)
+      .unwrap();
+    assert_eq!(renamed.parent_id, Some(work.id));
+    let tags = tag_service.get_tags_by_note(note2.id).unwrap();
+    assert_eq!(tags[0].tag.name, "work/projectB/meeting");
+
+    // 別の親への移動と、自身の子孫への移動の禁止
+    let moved = tag_service.move_tag(project.id, None).unwrap();
+    assert_eq!(moved.name, "projectB");
+    assert_eq!(moved.parent_id, None);
+    assert_eq!(
+      tag_service.get_tags_by_note(note2.id).unwrap()[0].tag.name,
+      "projectB/meeting"
+    );
+    assert!(tag_service.move_tag(project.id, Some(meeting.id)).is_err());
+    assert!(
+      tag_service
+        .update_tag(meeting.id, "work".to_string(), None, false)
+        .is_err()
+    );
+
+    // 削除すると子孫のタグも消える
+    tag_service.delete_tag(project.id).unwrap();
+    let names: Vec<String> = tag_service
+      .get_all_tags()
+      .unwrap()
+      .into_iter()
+      .map(|t| t.tag.name)
+      .collect();
+    assert_eq!(names, vec!["work".to_string()]);
+    assert!(tag_service.get_tags_by_note(note2.id).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_parse_hashtags() {
+    let content = "# 見出し #heading\n\
+      今日は #work/projectA と #日記 を書いた #work/projectA\n\
+      ```\n#code_block\n```\n\
+      `#inline_code` https://example.com/page#anchor #123 (#todo)\n\
+      ## 小見出し\n\
+      #done/";
+    assert_eq!(
+      parse_hashtags(content),
+      vec!["work/projectA", "日記", "todo", "done"]
+    );
+  }
+
+  #[test]
+  fn test_inline_tags_sync_with_content() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = crate::services::note::NoteService::new(db.clone(), temp_dir.path().join("notes"));
+    let tag_service = TagService::new(db.clone());
+
+    let note = notes
+      .create_note(
+        "Memo".to_string(),
+        "#idea と #work/a".to_string(),
+        None,
+        None,
+      )
+      .unwrap();
+    let tags = tag_service.get_tags_by_note(note.id).unwrap();
+    let names: Vec<&str> = tags.iter().map(|t| t.tag.name.as_str()).collect();
+    assert_eq!(names, vec!["idea", "work/a"]);
+    assert!(tags.iter().all(|t| t.is_inline && !t.is_manual));
+    // 親のタグも作られる
+    assert!(
+      tag_service
+        .get_all_tags()
+        .unwrap()
+        .iter()
+        .any(|t| t.tag.name == "work")
+    );
+
+    // 手動でも付けたタグは本文から消しても残り、本文だけのタグは外れる
+    let idea = tags[0].tag.id;
+    tag_service.add_tag_to_note(note.id, idea).unwrap();
+    notes
+      .update_note(note.id, "Memo".to_string(), "本文".to_string())
+      .unwrap();
+    let tags = tag_service.get_tags_by_note(note.id).unwrap();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].tag.id, idea);
+    assert!(tags[0].is_manual && !tags[0].is_inline);
+
+    // 本文に #タグ が残っていれば、手動で外しても付いたままになる
+    notes
+      .update_note(note.id, "Memo".to_string(), "#idea".to_string())
+      .unwrap();
+    tag_service.remove_tag_from_note(note.id, idea).unwrap();
+    let tags = tag_service.get_tags_by_note(note.id).unwrap();
+    assert_eq!(tags.len(), 1);
+    assert!(!tags[0].is_manual && tags[0].is_inline);
+  }
+
+  #[test]
+  fn test_rename_hashtags() {
+    let content = "#work と #work/a と #workshop\n```\n#work\n```\n`#work`";
+    assert_eq!(
+      rename_hashtags(content, "work", "job").unwrap(),
+      "#job と #job/a と #workshop\n```\n#work\n```\n`#work`"
+    );
+    assert!(rename_hashtags(content, "other", "job").is_none());
+  }
+
+  #[test]
+  fn test_rename_tag_rewrites_content() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = crate::services::note::NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let tag_service = TagService::new(db.clone());
+
+    let note = notes
+      .create_note("Memo".to_string(), "#old/a と #old".to_string(), None, None)
+      .unwrap();
+    let old = tag_service
+      .get_all_tags()
+      .unwrap()
+      .into_iter()
+      .find(|t| t.tag.name == "old")
+      .unwrap();
+
+    tag_service
+      .update_tag(old.tag.id, "new".to_string(), None, true)
+      .unwrap();
+    let saved = notes.get_note_by_id(note.id).unwrap();
+    assert_eq!(saved.content, "#new/a と #new");
+    let names: Vec<String> = tag_service
+      .get_tags_by_note(note.id)
+      .unwrap()
+      .into_iter()
+      .map(|t| t.tag.name)
+      .collect();
+    assert_eq!(names, vec!["new".to_string(), "new/a".to_string()]);
+    // 古い名前のタグは作り直されない
+    assert!(
+      !tag_service
+        .get_all_tags()
+        .unwrap()
+        .iter()
+        .any(|t| t.tag.name.starts_with("old"))
+    );
+  }
+
+  #[test]
+  fn test_merge_tags() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = crate::services::note::NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let tag_service = TagService::new(db.clone());
+
+    let todo = tag_service.create_tag("todo".to_string(), None).unwrap();
+    let task = tag_service.create_tag("task".to_string(), None).unwrap();
+    tag_service
+      .create_tag("task/urgent".to_string(), None)
+      .unwrap();
+    let target_child = tag_service
+      .create_tag("todo/urgent".to_string(), None)
+      .unwrap();
+
+    let both = notes
+      .create_note("Both".to_string(), "本文".to_string(), None, None)
+      .unwrap();
+    let inline = notes
+      .create_note("Inline".to_string(), "#task/urgent".to_string(), None, None)
+      .unwrap();
+    tag_service.add_tag_to_note(both.id, todo.id).unwrap();
+    tag_service.add_tag_to_note(both.id, task.id).unwrap();
+
+    let merged = tag_service.merge_tags(&[task.id], todo.id).unwrap();
+    assert_eq!(merged.id, todo.id);
+
+    // 重複なく移り、子のタグは同じ名前のタグにまとまる
+    let tags = tag_service.get_all_tags().unwrap();
+    let names: Vec<&str> = tags.iter().map(|t| t.tag.name.as_str()).collect();
+    assert_eq!(names, vec!["todo", "todo/urgent"]);
+    assert_eq!(tags[0].note_count, 1);
+    assert_eq!(tags[1].note_count, 1);
+    assert!(tags[0].last_used_at.is_some());
+    assert_eq!(tag_service.get_tags_by_note(both.id).unwrap().len(), 1);
+    assert!(
+      tag_service
+        .get_tags_by_note(inline.id)
+        .unwrap()
+        .iter()
+        .all(|t| t.tag.id == target_child.id)
+    );
+    assert_eq!(
+      notes.get_note_by_id(inline.id).unwrap().content,
+      "#todo/urgent"
+    );
+
+    // 自身の子孫にはまとめられない
+    let parent = tag_service.create_tag("a/b".to_string(), None).unwrap();
+    let a = tag_service
+      .get_all_tags()
+      .unwrap()
+      .into_iter()
+      .find(|t| t.tag.name == "a")
+      .unwrap();
+    assert!(tag_service.merge_tags(&[a.tag.id], parent.id).is_err());
+  }
+
+  #[test]
+  fn test_get_all_tags_counts_live_notes() {
+    let (db, temp_dir) = setup_test_db();
+    let notes = crate::services::note::NoteService::new(db.clone(), temp_dir.path().to_path_buf());
+    let tag_service = TagService::new(db.clone());
+
+    let unused = tag_service.create_tag("unused".to_string(), None).unwrap();
+    let note = notes
+      .create_note("Memo".to_string(), "#used".to_string(), None, None)
+      .unwrap();
+
+    let tags = tag_service.get_all_tags().unwrap();
+    let stats = |id: i64| tags.iter().find(|t| t.tag.id == id).unwrap();
+    assert_eq!(stats(unused.id).note_count, 0);
+    assert_eq!(stats(unused.id).last_used_at, None);
+    let used = tags.iter().find(|t| t.tag.name == "used").unwrap();
+    assert_eq!(used.note_count, 1);
+    assert!(used.last_used_at.is_some());
+
+    notes.delete_note(note.id).unwrap();
+    let tags = tag_service.get_all_tags().unwrap();
+    assert_eq!(
+      tags
+        .iter()
+        .find(|t| t.tag.name == "used")
+        .unwrap()
+        .note_count,
+      0
+    );
+  }
 }
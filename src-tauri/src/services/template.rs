@@ -1,15 +1,198 @@
 use crate::db::Database;
-use crate::db::models::{CreateTemplateInput, Template, UpdateTemplateInput};
-use rusqlite::{Result as SqlResult, params};
+use crate::db::models::{
+  CreateTemplateInput, RenderedTemplate, Template, TemplateContext, UpdateTemplateInput,
+};
+use crate::services::properties::split_front_matter;
+use crate::services::{atomic, filename};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, Result as SqlResult, params};
+use serde_yaml::Mapping;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use zip::ZipWriter;
+use zip::write::FileOptions;
+
+const WEEKDAYS: [&str; 7] = [
+  "月曜日",
+  "火曜日",
+  "水曜日",
+  "木曜日",
+  "金曜日",
+  "土曜日",
+  "日曜日",
+];
+
+/// Format a date/time with YYYY, YY, MM, DD, HH, mm and ss tokens (other characters are kept)
+pub fn format_datetime(format: &str, at: NaiveDateTime) -> String {
+  const TOKENS: [(&str, &str); 7] = [
+    ("YYYY", "%Y"),
+    ("YY", "%y"),
+    ("MM", "%m"),
+    ("DD", "%d"),
+    ("HH", "%H"),
+    ("mm", "%M"),
+    ("ss", "%S"),
+  ];
+  let mut result = String::new();
+  let mut rest = format;
+  'outer: while let Some(c) = rest.chars().next() {
+    for (token, spec) in TOKENS {
+      if let Some(after) = rest.strip_prefix(token) {
+        result.push_str(&at.format(spec).to_string());
+        rest = after;
+        continue 'outer;
+      }
+    }
+    result.push(c);
+    rest = &rest[c.len_utf8()..];
+  }
+  result
+}
+
+/// Names of the custom prompts (`{{prompt:name}}`) declared in a template, in order of appearance
+pub fn template_prompts(content: &str) -> Vec<String> {
+  let mut prompts: Vec<String> = Vec::new();
+  let mut rest = content;
+  while let Some(start) = rest.find("{{") {
+    let Some(end) = rest[start + 2..].find("}}") else {
+      break;
+    };
+    let variable = rest[start + 2..start + 2 + end].trim();
+    if let Some(name) = variable.strip_prefix("prompt:") {
+      let name = name.trim().to_string();
+      if !prompts.contains(&name) {
+        prompts.push(name);
+      }
+    }
+    rest = &rest[start + 2 + end + 2..];
+  }
+  prompts
+}
+
+/// Expand the `{{...}}` variables of a template
+///
+/// `{{cursor}}` is removed and its position is returned. Unknown variables and
+/// prompts without a value are errors.
+pub fn render_content(
+  content: &str,
+  context: &TemplateContext,
+  now: NaiveDateTime,
+) -> Result<RenderedTemplate, String> {
+  let mut result = String::with_capacity(content.len());
+  let mut cursor = None;
+  let mut rest = content;
+
+  while let Some(start) = rest.find("{{") {
+    // Text without a closing }} is kept as is
+    let Some(end) = rest[start + 2..].find("}}") else {
+      break;
+    };
+    result.push_str(&rest[..start]);
+    let variable = rest[start + 2..start + 2 + end].trim();
+    rest = &rest[start + 2 + end + 2..];
+
+    let (name, argument) = match variable.split_once(':') {
+      Some((name, argument)) => (name.trim(), Some(argument.trim())),
+      None => (variable, None),
+    };
+    match (name, argument) {
+      ("date", format) => result.push_str(&format_datetime(format.unwrap_or("YYYY-MM-DD"), now)),
+      ("time", format) => result.push_str(&format_datetime(format.unwrap_or("HH:mm"), now)),
+      ("title", None) => result.push_str(context.title.as_deref().unwrap_or_default()),
+      ("folder", None) => result.push_str(context.folder.as_deref().unwrap_or_default()),
+      ("weekday", None) => result.push_str(WEEKDAYS[now.weekday().num_days_from_monday() as usize]),
+      ("cursor", None) => {
+        if cursor.is_none() {
+          cursor = Some(result.encode_utf16().count());
+        }
+      }
+      ("prompt", Some(prompt)) => match context.prompts.get(prompt) {
+        Some(value) => result.push_str(value),
+        None => return Err(format!("Missing value for template prompt: {}", prompt)),
+      },
+      _ => return Err(format!("Unknown template variable: {{{{{}}}}}", variable)),
+    }
+  }
+  result.push_str(rest);
+
+  Ok(RenderedTemplate {
+    content: result,
+    cursor,
+  })
+}
+
+/// Split a template file into its name, description and content
+///
+/// The name and description come from front matter. A file without them is used
+/// as-is and named after the file.
+pub fn parse_template_file(file_stem: &str, text: &str) -> CreateTemplateInput {
+  let (front_matter, body) = split_front_matter(text);
+  let metadata = front_matter
+    .and_then(|yaml| serde_yaml::from_str::<Mapping>(yaml).ok())
+    .filter(|mapping| mapping.contains_key("name") || mapping.contains_key("description"));
+  let Some(metadata) = metadata else {
+    return CreateTemplateInput {
+      name: file_stem.to_string(),
+      content: text.to_string(),
+      description: None,
+    };
+  };
+  let get = |key: &str| {
+    metadata
+      .get(key)
+      .and_then(|value| value.as_str())
+      .map(|value| value.trim().to_string())
+      .filter(|value| !value.is_empty())
+  };
+  CreateTemplateInput {
+    name: get("name").unwrap_or_else(|| file_stem.to_string()),
+    content: body.to_string(),
+    description: get("description"),
+  }
+}
+
+/// Render a template as a Markdown file with its name and description in front matter
+pub fn render_template_file(
+  name: &str,
+  description: Option<&str>,
+  content: &str,
+) -> Result<String, String> {
+  let mut metadata = Mapping::new();
+  metadata.insert("name".into(), name.into());
+  if let Some(description) = description.filter(|d| !d.trim().is_empty()) {
+    metadata.insert("description".into(), description.into());
+  }
+  let yaml = serde_yaml::to_string(&metadata)
+    .map_err(|e| format!("Failed to write template file: {}", e))?;
+  Ok(format!("---\n{}---\n{}", yaml, content))
+}
+
+// Empty descriptions are not written to files, so treat them as missing when comparing
+fn same_description(a: Option<&str>, b: Option<&str>) -> bool {
+  a.filter(|d| !d.trim().is_empty()) == b.filter(|d| !d.trim().is_empty())
+}
 
 pub struct TemplateService {
   db: Arc<Database>,
+  templates_dir: Option<PathBuf>,
 }
 
 impl TemplateService {
   pub fn new(db: Arc<Database>) -> Self {
-    TemplateService { db }
+    TemplateService {
+      db,
+      templates_dir: None,
+    }
+  }
+
+  /// Keep templates in sync with `.md` files in `templates_dir`
+  pub fn with_templates_dir(db: Arc<Database>, templates_dir: PathBuf) -> Self {
+    TemplateService {
+      db,
+      templates_dir: Some(templates_dir),
+    }
   }
 
   /// Get all templates
@@ -66,13 +249,77 @@ impl TemplateService {
     Ok(template)
   }
 
+  // Write a template to its file, renaming the file when the name changes
+  //
+  // Returns the path of the file, or None when templates are not kept as files.
+  fn write_template_file(
+    &self,
+    conn: &Connection,
+    current: Option<&Path>,
+    input: &CreateTemplateInput,
+  ) -> Result<Option<String>, String> {
+    let Some(dir) = self
+      .templates_dir
+      .clone()
+      .or_else(|| current.and_then(Path::parent).map(Path::to_path_buf))
+    else {
+      return Ok(None);
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create templates directory: {}", e))?;
+
+    let mut stem = filename::sanitize_file_name(&input.name);
+    if stem.is_empty() {
+      stem = "template".to_string();
+    }
+    let path = match current {
+      Some(current) if current.file_name() == Some(format!("{}.md", stem).as_ref()) => {
+        current.to_path_buf()
+      }
+      _ => filename::unique_path(&dir, &stem, Some("md"), |path| {
+        Some(path) != current && (path.exists() || Self::is_file_taken(conn, path))
+      }),
+    };
+
+    let text = render_template_file(&input.name, input.description.as_deref(), &input.content)?;
+    atomic::write_file(&path, &text)
+      .map_err(|e| format!("Failed to write template file: {}", e))?;
+    if let Some(current) = current
+      && current != path
+    {
+      let _ = fs::remove_file(current);
+    }
+    Ok(Some(path.to_string_lossy().to_string()))
+  }
+
+  fn is_file_taken(conn: &Connection, path: &Path) -> bool {
+    conn
+      .query_row(
+        "SELECT EXISTS(SELECT 1 FROM templates WHERE file_path = ?)",
+        params![path.to_string_lossy()],
+        |row| row.get(0),
+      )
+      .unwrap_or(false)
+  }
+
+  fn get_file_path(conn: &Connection, id: i64) -> Result<Option<PathBuf>, String> {
+    conn
+      .query_row(
+        "SELECT file_path FROM templates WHERE id = ?",
+        params![id],
+        |row| row.get::<_, Option<String>>(0),
+      )
+      .map(|path| path.map(PathBuf::from))
+      .map_err(|e| format!("Failed to fetch template: {}", e))
+  }
+
   /// Create a new template
   pub fn create_template(&self, input: CreateTemplateInput) -> Result<Template, String> {
     let conn = self.db.conn.lock().unwrap();
+    let file_path = self.write_template_file(&conn, None, &input)?;
     conn
       .execute(
-        "INSERT INTO templates (name, content, description) VALUES (?, ?, ?)",
-        params![input.name, input.content, input.description],
+        "INSERT INTO templates (name, content, description, file_path) VALUES (?, ?, ?, ?)",
+        params![input.name, input.content, input.description, file_path],
       )
       .map_err(|e| format!("Failed to create template: {}", e))?;
 
@@ -84,10 +331,20 @@ impl TemplateService {
   /// Update a template
   pub fn update_template(&self, input: UpdateTemplateInput) -> Result<Template, String> {
     let conn = self.db.conn.lock().unwrap();
+    let current = Self::get_file_path(&conn, input.id)?;
+    let file_path = self.write_template_file(
+      &conn,
+      current.as_deref(),
+      &CreateTemplateInput {
+        name: input.name.clone(),
+        content: input.content.clone(),
+        description: input.description.clone(),
+      },
+    )?;
     conn
       .execute(
-        "UPDATE templates SET name = ?, content = ?, description = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-        params![input.name, input.content, input.description, input.id],
+        "UPDATE templates SET name = ?, content = ?, description = ?, file_path = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![input.name, input.content, input.description, file_path, input.id],
       )
       .map_err(|e| format!("Failed to update template: {}", e))?;
 
@@ -95,12 +352,538 @@ impl TemplateService {
     self.get_template_by_id(input.id)
   }
 
+  /// Render a template with the given context (the date defaults to today)
+  pub fn render_template(
+    &self,
+    id: i64,
+    context: &TemplateContext,
+  ) -> Result<RenderedTemplate, String> {
+    let template = self.get_template_by_id(id)?;
+    let now = chrono::Local::now().naive_local();
+    let now = match &context.date {
+      Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {}", date))?
+        .and_time(now.time()),
+      None => now,
+    };
+    render_content(&template.content, context, now)
+  }
+
+  /// Get the custom prompts declared in a template
+  pub fn get_template_prompts(&self, id: i64) -> Result<Vec<String>, String> {
+    Ok(template_prompts(&self.get_template_by_id(id)?.content))
+  }
+
   /// Delete a template
   pub fn delete_template(&self, id: i64) -> Result<(), String> {
     let conn = self.db.conn.lock().unwrap();
+    let file_path = Self::get_file_path(&conn, id)?;
+    Self::delete_row(&conn, id)?;
+    if let Some(file_path) = file_path {
+      let _ = fs::remove_file(file_path);
+    }
+    Ok(())
+  }
+
+  fn delete_row(conn: &Connection, id: i64) -> Result<(), String> {
+    // Folders using it fall back to their parent's default template
+    conn
+      .execute(
+        "UPDATE folders SET default_template_id = NULL WHERE default_template_id = ?",
+        params![id],
+      )
+      .map_err(|e| format!("Failed to delete template: {}", e))?;
     conn
       .execute("DELETE FROM templates WHERE id = ?", params![id])
       .map_err(|e| format!("Failed to delete template: {}", e))?;
     Ok(())
   }
+
+  /// Sync the templates table with the `.md` files in the templates directory
+  ///
+  /// Files that were added, edited or removed outside the app are applied to the
+  /// table, and templates that have no file yet are written out. Returns the
+  /// number of templates changed from files.
+  pub fn sync_templates_dir(&self) -> Result<usize, String> {
+    let Some(dir) = &self.templates_dir else {
+      return Ok(0);
+    };
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create templates directory: {}", e))?;
+
+    let mut files = Vec::new();
+    for entry in
+      fs::read_dir(dir).map_err(|e| format!("Failed to read templates directory: {}", e))?
+    {
+      let path = entry
+        .map_err(|e| format!("Failed to read templates directory: {}", e))?
+        .path();
+      if !path.is_file() || path.extension().is_none_or(|ext| ext != "md") {
+        continue;
+      }
+      // Files that can't be read right now (locked by an editor, not UTF-8)
+      // are still recorded so their templates are left as they are
+      let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+      let file = fs::read_to_string(&path)
+        .ok()
+        .map(|text| parse_template_file(&stem, &text));
+      files.push((path.to_string_lossy().to_string(), file));
+    }
+
+    let conn = self.db.conn.lock().unwrap();
+    let rows = {
+      let mut stmt = conn
+        .prepare("SELECT id, name, content, description, file_path FROM templates")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+      stmt
+        .query_map([], |row| {
+          Ok((
+            row.get::<_, i64>(0)?,
+            CreateTemplateInput {
+              name: row.get(1)?,
+              content: row.get(2)?,
+              description: row.get(3)?,
+            },
+            row.get::<_, Option<String>>(4)?,
+          ))
+        })
+        .map_err(|e| format!("Failed to query templates: {}", e))?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to collect templates: {}", e))?
+    };
+
+    let mut changed = 0;
+    for (path, file) in &files {
+      let Some(file) = file else {
+        continue;
+      };
+      match rows
+        .iter()
+        .find(|(_, _, file_path)| file_path.as_deref() == Some(path.as_str()))
+      {
+        Some((id, row, _)) => {
+          if row.name != file.name
+            || row.content != file.content
+            || !same_description(row.description.as_deref(), file.description.as_deref())
+          {
+            conn
+              .execute(
+                "UPDATE templates SET name = ?, content = ?, description = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                params![file.name, file.content, file.description, id],
+              )
+              .map_err(|e| format!("Failed to update template: {}", e))?;
+            changed += 1;
+          }
+        }
+        None => {
+          conn
+            .execute(
+              "INSERT INTO templates (name, content, description, file_path) VALUES (?, ?, ?, ?)",
+              params![file.name, file.content, file.description, path],
+            )
+            .map_err(|e| format!("Failed to create template: {}", e))?;
+          changed += 1;
+        }
+      }
+    }
+
+    for (id, row, file_path) in &rows {
+      match file_path {
+        // The file was removed outside the app
+        Some(file_path) => {
+          if !files.iter().any(|(path, _)| path == file_path) && !Path::new(file_path).exists() {
+            Self::delete_row(&conn, *id)?;
+            changed += 1;
+          }
+        }
+        None => {
+          let file_path = self.write_template_file(&conn, None, row)?;
+          conn
+            .execute(
+              "UPDATE templates SET file_path = ? WHERE id = ?",
+              params![file_path, id],
+            )
+            .map_err(|e| format!("Failed to update template: {}", e))?;
+        }
+      }
+    }
+
+    Ok(changed)
+  }
+
+  /// Export all templates to a zip file as `.md` files (returns the number exported)
+  pub fn export_templates(&self, zip_path: &Path) -> Result<usize, String> {
+    let templates = self.get_all_templates()?;
+    let file =
+      fs::File::create(zip_path).map_err(|e| format!("Failed to create zip file: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::<()>::default();
+
+    let mut names = HashSet::new();
+    for template in &templates {
+      let mut stem = filename::sanitize_file_name(&template.name);
+      if stem.is_empty() {
+        stem = "template".to_string();
+      }
+      let name = filename::unique_path(Path::new(""), &stem, Some("md"), |path| {
+        names.contains(path)
+      });
+      let text = render_template_file(
+        &template.name,
+        template.description.as_deref(),
+        &template.content,
+      )?;
+      zip
+        .start_file(name.to_string_lossy(), options)
+        .map_err(|e| format!("Failed to add template to zip: {}", e))?;
+      std::io::Write::write_all(&mut zip, text.as_bytes())
+        .map_err(|e| format!("Failed to write template to zip: {}", e))?;
+      names.insert(name);
+    }
+
+    zip
+      .finish()
+      .map_err(|e| format!("Failed to finish zip: {}", e))?;
+    Ok(templates.len())
+  }
+
+  /// Import the `.md` files in a zip file as templates
+  ///
+  /// A template with the same name as an existing one replaces its content.
+  pub fn import_templates(&self, zip_path: &Path) -> Result<Vec<Template>, String> {
+    let file = fs::File::open(zip_path).map_err(|e| format!("Failed to open zip file: {}", e))?;
+    let mut archive =
+      zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+      let mut entry = archive
+        .by_index(i)
+        .map_err(|e| format!("Failed to read file from archive: {}", e))?;
+      let Some(path) = entry.enclosed_name() else {
+        continue;
+      };
+      // Skip directories, hidden files and the metadata macOS adds to archives
+      if entry.is_dir()
+        || path.extension().is_none_or(|ext| ext != "md")
+        || path.components().any(|c| {
+          let c = c.as_os_str().to_string_lossy();
+          c.starts_with('.') || c == "__MACOSX"
+        })
+      {
+        continue;
+      }
+      let mut text = String::new();
+      std::io::Read::read_to_string(&mut entry, &mut text)
+        .map_err(|e| format!("Failed to read template from archive: {}", e))?;
+      let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+      files.push(parse_template_file(&stem, &text));
+    }
+
+    let existing = self.get_all_templates()?;
+    let mut imported = Vec::new();
+    for file in files {
+      let template = match existing.iter().find(|t| t.name == file.name) {
+        Some(template) => self.update_template(UpdateTemplateInput {
+          id: template.id,
+          name: file.name,
+          content: file.content,
+          description: file.description,
+        })?,
+        None => self.create_template(file)?,
+      };
+      imported.push(template);
+    }
+    Ok(imported)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use std::collections::HashMap;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  fn at(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+  }
+
+  #[test]
+  fn test_render_content() {
+    let context = TemplateContext {
+      title: Some("定例".to_string()),
+      folder: Some("会議".to_string()),
+      date: None,
+      prompts: HashMap::from([("参加者".to_string(), "山田".to_string())]),
+    };
+    let rendered = render_content(
+      "# {{title}} ({{folder}})\n{{date:YYYY年MM月DD日}} {{weekday}} {{time}}\n参加者: {{ prompt:参加者 }}\n{{cursor}}\n{{ 閉じていない",
+      &context,
+      at("2024-03-04 09:05:00"),
+    )
+    .unwrap();
+    assert_eq!(
+      rendered.content,
+      "# 定例 (会議)\n2024年03月04日 月曜日 09:05\n参加者: 山田\n\n{{ 閉じていない"
+    );
+    assert_eq!(
+      rendered.cursor,
+      Some(
+        "# 定例 (会議)\n2024年03月04日 月曜日 09:05\n参加者: 山田\n"
+          .encode_utf16()
+          .count()
+      )
+    );
+    assert_eq!(
+      render_content("{{date}}", &context, at("2024-03-04 09:05:00"))
+        .unwrap()
+        .content,
+      "2024-03-04"
+    );
+
+    let error = render_content("{{author}}", &context, at("2024-03-04 09:05:00")).unwrap_err();
+    assert!(error.contains("{{author}}"));
+    assert!(render_content("{{prompt:場所}}", &context, at("2024-03-04 09:05:00")).is_err());
+    assert!(render_content("{{title:x}}", &context, at("2024-03-04 09:05:00")).is_err());
+  }
+
+  #[test]
+  fn test_render_template() {
+    let (db, _temp_dir) = setup_test_db();
+    let service = TemplateService::new(db);
+    let template = service
+      .create_template(CreateTemplateInput {
+        name: "日報".to_string(),
+        content: "{{date}} {{prompt:気分}} {{prompt:気分}} {{prompt:天気}}".to_string(),
+        description: None,
+      })
+      .unwrap();
+
+    assert_eq!(
+      service.get_template_prompts(template.id).unwrap(),
+      vec!["気分".to_string(), "天気".to_string()]
+    );
+    let rendered = service
+      .render_template(
+        template.id,
+        &TemplateContext {
+          date: Some("2024-12-31".to_string()),
+          prompts: HashMap::from([
+            ("気分".to_string(), "良い".to_string()),
+            ("天気".to_string(), "晴れ".to_string()),
+          ]),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert_eq!(rendered.content, "2024-12-31 良い 良い 晴れ");
+    assert!(
+      service
+        .render_template(
+          template.id,
+          &TemplateContext {
+            date: Some("12/31".to_string()),
+            ..Default::default()
+          }
+        )
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn test_create_note_from_template() {
+    let (db, temp_dir) = setup_test_db();
+    let notes_dir = temp_dir.path().join("notes");
+    let folder = crate::services::FolderService::new(db.clone(), notes_dir.clone())
+      .create_folder("議事録".to_string(), None, None)
+      .unwrap();
+    let template = TemplateService::new(db.clone())
+      .create_template(CreateTemplateInput {
+        name: "会議".to_string(),
+        content: "# {{title}}\n{{folder}}: {{cursor}}".to_string(),
+        description: None,
+      })
+      .unwrap();
+
+    let notes = crate::services::NoteService::new(db.clone(), notes_dir);
+    let created = notes
+      .create_note_from_template(
+        template.id,
+        "定例".to_string(),
+        Some(folder.id),
+        Some(folder.folder_path.clone()),
+        HashMap::new(),
+      )
+      .unwrap();
+    assert_eq!(created.note.content, "# 定例\n議事録: ");
+    assert_eq!(
+      created.cursor,
+      Some("# 定例\n議事録: ".encode_utf16().count())
+    );
+    assert_eq!(created.note.parent_id, Some(folder.id));
+
+    // 展開できないテンプレートではノートを作らない
+    let broken = TemplateService::new(db.clone())
+      .create_template(CreateTemplateInput {
+        name: "壊れた".to_string(),
+        content: "{{unknown}}".to_string(),
+        description: None,
+      })
+      .unwrap();
+    assert!(
+      notes
+        .create_note_from_template(broken.id, "x".to_string(), None, None, HashMap::new())
+        .is_err()
+    );
+    assert_eq!(notes.get_all_notes().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn test_template_file_round_trip() {
+    // テンプレート自身の front matter は本文として残る
+    let content = "---\ntags: [daily]\n---\n# {{title}}\n";
+    let text = render_template_file("日報", Some("毎日の記録"), content).unwrap();
+    let parsed = parse_template_file("ignored", &text);
+    assert_eq!(parsed.name, "日報");
+    assert_eq!(parsed.description.as_deref(), Some("毎日の記録"));
+    assert_eq!(parsed.content, content);
+
+    // 名前のないファイルはファイル名を使い、全体を本文にする
+    let parsed = parse_template_file("メモ", content);
+    assert_eq!(parsed.name, "メモ");
+    assert_eq!(parsed.description, None);
+    assert_eq!(parsed.content, content);
+  }
+
+  #[test]
+  fn test_sync_templates_dir() {
+    let (db, temp_dir) = setup_test_db();
+    let dir = temp_dir.path().join("templates");
+
+    // ファイルを持たない既存のテンプレートは書き出す
+    let existing = TemplateService::new(db.clone())
+      .create_template(CreateTemplateInput {
+        name: "会議/定例".to_string(),
+        content: "議題".to_string(),
+        description: None,
+      })
+      .unwrap();
+    let service = TemplateService::with_templates_dir(db.clone(), dir.clone());
+    assert_eq!(service.sync_templates_dir().unwrap(), 0);
+    let existing_path = dir.join("会議_定例.md");
+    assert_eq!(
+      parse_template_file("", &fs::read_to_string(&existing_path).unwrap()).name,
+      "会議/定例"
+    );
+
+    // アプリでの変更はファイルに書き込み、名前を変えるとファイル名も変わる
+    let created = service
+      .create_template(CreateTemplateInput {
+        name: "日報".to_string(),
+        content: "今日".to_string(),
+        description: Some("毎日".to_string()),
+      })
+      .unwrap();
+    service
+      .update_template(UpdateTemplateInput {
+        id: created.id,
+        name: "週報".to_string(),
+        content: "今週".to_string(),
+        description: None,
+      })
+      .unwrap();
+    assert!(!dir.join("日報.md").exists());
+    assert_eq!(
+      fs::read_to_string(dir.join("週報.md")).unwrap(),
+      "---\nname: 週報\n---\n今週"
+    );
+
+    // 外部での編集、追加、削除を反映する
+    fs::write(
+      dir.join("週報.md"),
+      "---\nname: 週報\ndescription: 毎週\n---\n先週",
+    )
+    .unwrap();
+    fs::write(dir.join("読書.md"), "# {{title}}").unwrap();
+    fs::write(dir.join("メモ.txt"), "対象外").unwrap();
+    fs::remove_file(&existing_path).unwrap();
+    assert_eq!(service.sync_templates_dir().unwrap(), 3);
+
+    let updated = service.get_template_by_id(created.id).unwrap();
+    assert_eq!(updated.content, "先週");
+    assert_eq!(updated.description.as_deref(), Some("毎週"));
+    assert!(service.get_template_by_id(existing.id).is_err());
+    let mut names: Vec<String> = service
+      .get_all_templates()
+      .unwrap()
+      .into_iter()
+      .map(|t| t.name)
+      .collect();
+    names.sort();
+    assert_eq!(names, vec!["読書", "週報"]);
+    assert_eq!(service.sync_templates_dir().unwrap(), 0);
+
+    // UTF-8 として読めないファイルのテンプレートはそのまま残す
+    fs::write(dir.join("週報.md"), [0x93, 0xfa, 0x95, 0xf1]).unwrap();
+    assert_eq!(service.sync_templates_dir().unwrap(), 0);
+    assert_eq!(
+      service.get_template_by_id(created.id).unwrap().content,
+      "先週"
+    );
+
+    // 削除するとファイルも消える
+    service.delete_template(created.id).unwrap();
+    assert!(!dir.join("週報.md").exists());
+  }
+
+  #[test]
+  fn test_export_and_import_templates() {
+    let (db, temp_dir) = setup_test_db();
+    let service = TemplateService::with_templates_dir(db, temp_dir.path().join("templates"));
+    for (name, content) in [("日報", "今日"), ("読書", "# {{title}}")] {
+      service
+        .create_template(CreateTemplateInput {
+          name: name.to_string(),
+          content: content.to_string(),
+          description: None,
+        })
+        .unwrap();
+    }
+    let zip_path = temp_dir.path().join("templates.zip");
+    assert_eq!(service.export_templates(&zip_path).unwrap(), 2);
+
+    let (other_db, other_dir) = setup_test_db();
+    let other = TemplateService::with_templates_dir(other_db, other_dir.path().join("templates"));
+    let daily = other
+      .create_template(CreateTemplateInput {
+        name: "日報".to_string(),
+        content: "古い".to_string(),
+        description: None,
+      })
+      .unwrap();
+
+    // 同じ名前のテンプレートは上書きする
+    let imported = other.import_templates(&zip_path).unwrap();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(other.get_template_by_id(daily.id).unwrap().content, "今日");
+    assert_eq!(other.get_all_templates().unwrap().len(), 2);
+    assert!(other_dir.path().join("templates").join("読書.md").exists());
+  }
 }
@@ -0,0 +1,582 @@
+use crate::db::Database;
+use crate::db::models::FilesChanged;
+use crate::services::{LinkService, NoteService, PropertyService, TagService, fts};
+use rusqlite::{Connection, Result as SqlResult, params};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// 外部のエディタや git による変更を確認する間隔 (秒)
+pub const WATCH_INTERVAL_SECS: u64 = 2;
+
+type FileStamp = (SystemTime, u64);
+
+// ノートディレクトリそのものを識別する値 (マウントの解除や作り直しで変わる)
+type DirIdentity = (u64, u64);
+
+fn dir_identity(path: &Path) -> Option<DirIdentity> {
+  let metadata = fs::metadata(path).ok()?;
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+  }
+  #[cfg(not(unix))]
+  {
+    let created = metadata
+      .created()
+      .ok()?
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .ok()?;
+    Some((created.as_secs(), created.subsec_nanos() as u64))
+  }
+}
+
+/// ノートディレクトリを定期的に走査し、外部での変更をデータベースに反映する
+///
+/// アプリ自身の書き込み途中の状態を拾わないよう、連続する2回の走査で
+/// 同じ状態が観測された変更だけを反映する。
+pub struct WatcherService {
+  db: Arc<Database>,
+  base_path: PathBuf,
+  // 前回の走査で見つかったファイルとフォルダ
+  last_files: Option<HashMap<PathBuf, FileStamp>>,
+  last_folders: HashSet<PathBuf>,
+  last_identity: Option<DirIdentity>,
+  // 内容を確認済みのファイルの状態
+  synced: HashMap<PathBuf, FileStamp>,
+}
+
+impl WatcherService {
+  pub fn new(db: Arc<Database>, base_path: PathBuf) -> Self {
+    WatcherService {
+      db,
+      base_path,
+      last_files: None,
+      last_folders: HashSet::new(),
+      last_identity: None,
+      synced: HashMap::new(),
+    }
+  }
+
+  // `.trash` などの隠しディレクトリを除いてノートディレクトリを走査
+  fn scan_dir(dir: &Path, folders: &mut HashSet<PathBuf>, files: &mut HashMap<PathBuf, FileStamp>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+      return;
+    };
+
+    for entry in entries.flatten() {
+      if entry.file_name().to_string_lossy().starts_with('.') {
+        continue;
+      }
+      let Ok(metadata) = entry.metadata() else {
+        continue;
+      };
+      let path = entry.path();
+
+      if metadata.is_dir() {
+        Self::scan_dir(&path, folders, files);
+        folders.insert(path);
+      } else if path.extension().is_some_and(|ext| ext == "md") {
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        files.insert(path, (modified, metadata.len()));
+      }
+    }
+  }
+
+  fn load_paths(conn: &Connection, query: &str) -> Result<HashMap<PathBuf, i64>, String> {
+    let mut stmt = conn
+      .prepare(query)
+      .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    stmt
+      .query_map([], |row| {
+        Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+      })
+      .map_err(|e| format!("データベースの読み込みに失敗しました: {}", e))?
+      .collect::<SqlResult<HashMap<PathBuf, i64>>>()
+      .map_err(|e| format!("データベースの読み込みに失敗しました: {}", e))
+  }
+
+  fn indexed_content(conn: &Connection, id: i64) -> Option<String> {
+    conn
+      .query_row(
+        "SELECT content FROM notes_fts WHERE rowid = ?",
+        params![id],
+        |row| row.get(0),
+      )
+      .ok()
+  }
+
+  /// ディスク上の変更をデータベースに反映し、反映した内容を返す
+  pub fn sync_changes(&mut self) -> Result<FilesChanged, String> {
+    let mut changes = FilesChanged::default();
+
+    // ノートディレクトリが一時的に見えない間は何も反映しない
+    if !self.base_path.is_dir() {
+      return Ok(changes);
+    }
+
+    let identity = dir_identity(&self.base_path);
+    let mut disk_folders = HashSet::new();
+    let mut disk_files = HashMap::new();
+    Self::scan_dir(&self.base_path, &mut disk_folders, &mut disk_files);
+
+    // マウントが外れて空のマウントポイントが見えている場合などを全削除と取り違えないよう、
+    // ディレクトリ自体が前回と別物になって空になったときは反映しない
+    if disk_files.is_empty() && identity != self.last_identity {
+      return Ok(changes);
+    }
+    self.last_identity = identity;
+
+    // 初回の走査は状態を記録するだけ
+    let Some(last_files) = self.last_files.replace(disk_files.clone()) else {
+      self.last_folders = disk_folders;
+      return Ok(changes);
+    };
+    let last_folders = std::mem::replace(&mut self.last_folders, disk_folders.clone());
+
+    // 前回から変化のないファイルのうち、未確認のものだけ内容を読み込む
+    // (ディスクの読み込み中に他のコマンドを止めないよう、ロックを取る前に読む)
+    let read_files: Vec<(PathBuf, FileStamp, String)> = disk_files
+      .iter()
+      .filter(|(path, stamp)| {
+        last_files.get(*path) == Some(*stamp) && self.synced.get(*path) != Some(*stamp)
+      })
+      .filter_map(|(path, stamp)| {
+        let content = fs::read_to_string(path).ok()?;
+        Some((path.clone(), *stamp, content))
+      })
+      .collect();
+
+    let conn = self.db.conn.lock().unwrap();
+    let db_folders = Self::load_paths(
+      &conn,
+      "SELECT folder_path, id FROM folders WHERE is_deleted = FALSE AND folder_path IS NOT NULL",
+    )?;
+    let db_notes = Self::load_paths(
+      &conn,
+      "SELECT file_path, id FROM notes WHERE is_deleted = FALSE AND file_path IS NOT NULL",
+    )?;
+
+    let mut new_files: Vec<(PathBuf, FileStamp, String)> = Vec::new();
+    let mut touched_notes: Vec<(i64, PathBuf, FileStamp, String)> = Vec::new();
+    for (path, stamp, content) in read_files {
+      match db_notes.get(&path) {
+        Some(id) => touched_notes.push((*id, path, stamp, content)),
+        None => new_files.push((path, stamp, content)),
+      }
+    }
+    new_files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut missing_notes: Vec<(PathBuf, i64)> = db_notes
+      .iter()
+      .filter(|(path, _)| !disk_files.contains_key(*path) && !last_files.contains_key(*path))
+      .map(|(path, id)| (path.clone(), *id))
+      .collect();
+    missing_notes.sort_by_key(|(_, id)| *id);
+
+    let mut missing_folders: Vec<(PathBuf, i64)> = db_folders
+      .iter()
+      .filter(|(path, _)| !disk_folders.contains(*path) && !last_folders.contains(*path))
+      .map(|(path, id)| (path.clone(), *id))
+      .collect();
+    missing_folders.sort_by_key(|(path, _)| path.components().count());
+
+    let tx = conn
+      .unchecked_transaction()
+      .map_err(|e| format!("トランザクションの開始に失敗しました: {}", e))?;
+    let mut synced = Vec::new();
+
+    // 新しいフォルダ (親フォルダから順に登録する)
+    // 消えたフォルダと中のファイル構成が同じならリネームとみなし、設定を引き継ぐ
+    let mut folder_ids = db_folders.clone();
+    let mut renamed_folders: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut new_folders: Vec<&PathBuf> = disk_folders
+      .iter()
+      .filter(|path| !db_folders.contains_key(*path) && last_folders.contains(*path))
+      .collect();
+    new_folders.sort_by_key(|path| path.components().count());
+    for path in new_folders {
+      let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+      let path_str = path.to_str().unwrap_or_default();
+      let parent_id = path.parent().and_then(|p| folder_ids.get(p)).copied();
+
+      let contents = Self::contained_files(path, disk_files.keys());
+      let renamed = missing_folders
+        .iter()
+        .position(|(old_path, _)| {
+          !contents.is_empty() && Self::contained_files(old_path, db_notes.keys()) == contents
+        })
+        .map(|i| missing_folders.remove(i));
+
+      let id = if let Some((old_path, id)) = renamed {
+        tx.execute(
+          "UPDATE folders SET name = ?, folder_path = ?, parent_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+          params![name, path_str, parent_id, id],
+        )
+        .map_err(|e| format!("フォルダの更新に失敗しました: {}", e))?;
+        renamed_folders.push((old_path, path.clone()));
+        changes.renamed_folders.push(id);
+        id
+      } else {
+        tx.execute(
+          "INSERT INTO folders (name, folder_path, parent_id) VALUES (?, ?, ?)",
+          params![name, path_str, parent_id],
+        )
+        .map_err(|e| format!("フォルダの登録に失敗しました: {}", e))?;
+        let id = tx.last_insert_rowid();
+        changes.created_folders.push(id);
+        id
+      };
+      folder_ids.insert(path.clone(), id);
+    }
+
+    // 外部で編集されたノート
+    for (id, path, stamp, content) in touched_notes {
+      if Self::indexed_content(&tx, id).as_deref() != Some(content.as_str()) {
+        self.reindex_note(&tx, id, &content)?;
+        changes.modified_notes.push(id);
+      }
+      synced.push((path, stamp));
+    }
+
+    // 新しいファイル
+    // リネームされたフォルダ内の同じ位置にあるか、消えたノートと内容が同じならリネームとみなす
+    for (path, stamp, content) in new_files {
+      let title = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+      let path_str = path.to_str().unwrap_or_default().to_string();
+      let parent_id = path.parent().and_then(|p| folder_ids.get(p)).copied();
+
+      let old_path = renamed_folders.iter().find_map(|(old, new)| {
+        path
+          .strip_prefix(new)
+          .ok()
+          .map(|relative| old.join(relative))
+      });
+      let renamed = old_path
+        .and_then(|old| missing_notes.iter().position(|(p, _)| *p == old))
+        .or_else(|| {
+          missing_notes.iter().position(|(_, id)| {
+            Self::indexed_content(&tx, *id).as_deref() == Some(content.as_str())
+          })
+        })
+        .map(|i| missing_notes.remove(i).1);
+
+      if let Some(id) = renamed {
+        tx.execute(
+          "UPDATE notes SET title = ?, file_path = ?, parent_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+          params![title, path_str, parent_id, id],
+        )
+        .map_err(|e| format!("ノートの更新に失敗しました: {}", e))?;
+        fts::update_title(&tx, id, &title)
+          .map_err(|e| format!("検索インデックスの更新に失敗しました: {}", e))?;
+        if Self::indexed_content(&tx, id).as_deref() != Some(content.as_str()) {
+          self.reindex_note(&tx, id, &content)?;
+        }
+        LinkService::resolve_links_to(&tx, &self.base_path, id, &path_str, &title)
+          .map_err(|e| format!("リンクの索引更新に失敗しました: {}", e))?;
+        changes.renamed_notes.push(id);
+      } else {
+        let id =
+          NoteService::register_existing_file(&tx, &self.base_path, &path, parent_id, &content)
+            .map_err(|e| format!("ノートの登録に失敗しました: {}", e))?;
+        changes.created_notes.push(id);
+      }
+
+      synced.push((path, stamp));
+    }
+
+    // ディスクから消えたノートはゴミ箱に入れ、履歴やメタデータを残す
+    for (_, id) in missing_notes {
+      tx.execute(
+        "UPDATE notes SET is_deleted = TRUE, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![id],
+      )
+      .map_err(|e| format!("ノートの削除に失敗しました: {}", e))?;
+      fts::remove(&tx, id).ok();
+      changes.deleted_notes.push(id);
+    }
+
+    // ディスクから消えたフォルダ (子フォルダから順に削除する)
+    for (_, id) in missing_folders.into_iter().rev() {
+      // ゴミ箱内のノートやフォルダからの参照を外す
+      tx.execute(
+        "UPDATE notes SET parent_id = NULL WHERE parent_id = ?",
+        params![id],
+      )
+      .map_err(|e| format!("フォルダの削除に失敗しました: {}", e))?;
+      tx.execute(
+        "UPDATE folders SET parent_id = NULL WHERE parent_id = ?",
+        params![id],
+      )
+      .map_err(|e| format!("フォルダの削除に失敗しました: {}", e))?;
+      tx.execute("DELETE FROM folders WHERE id = ?", params![id])
+        .map_err(|e| format!("フォルダの削除に失敗しました: {}", e))?;
+      changes.deleted_folders.push(id);
+    }
+
+    tx.commit()
+      .map_err(|e| format!("変更の反映に失敗しました: {}", e))?;
+
+    self.synced.extend(synced);
+    self.synced.retain(|path, _| disk_files.contains_key(path));
+
+    Ok(changes)
+  }
+
+  // 外部で書き換えられた内容でプレビューと各索引を更新
+  fn reindex_note(&self, conn: &Connection, id: i64, content: &str) -> Result<(), String> {
+    conn
+      .execute(
+        "UPDATE notes SET preview = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        params![NoteService::generate_preview(content), id],
+      )
+      .map_err(|e| format!("ノートの更新に失敗しました: {}", e))?;
+    fts::update_content(conn, id, content)
+      .map_err(|e| format!("検索インデックスの更新に失敗しました: {}", e))?;
+    LinkService::index_note(conn, &self.base_path, id, content)
+      .map_err(|e| format!("リンクの索引更新に失敗しました: {}", e))?;
+    TagService::index_note(conn, id, content)
+      .map_err(|e| format!("タグの更新に失敗しました: {}", e))?;
+    PropertyService::index_note(conn, id, content)
+      .map_err(|e| format!("プロパティの索引更新に失敗しました: {}", e))?;
+    Ok(())
+  }
+
+  // フォルダ以下にあるファイルのフォルダからの相対パス
+  fn contained_files<'a>(
+    folder: &Path,
+    files: impl Iterator<Item = &'a PathBuf>,
+  ) -> HashSet<PathBuf> {
+    files
+      .filter_map(|path| path.strip_prefix(folder).ok())
+      .map(Path::to_path_buf)
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::migrate;
+  use crate::services::FolderService;
+  use tempfile::TempDir;
+
+  fn setup_test_db() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let db = Database::new(db_path.to_str().unwrap()).unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      migrate(&conn).unwrap();
+    }
+    (Arc::new(db), temp_dir)
+  }
+
+  // 変更は2回続けて観測されてから反映される
+  fn sync(watcher: &mut WatcherService) -> FilesChanged {
+    let first = watcher.sync_changes().unwrap();
+    let second = watcher.sync_changes().unwrap();
+    assert!(second.is_empty() || first.is_empty());
+    if first.is_empty() { second } else { first }
+  }
+
+  #[test]
+  fn test_sync_external_changes() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    fs::create_dir_all(&base_path).unwrap();
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let mut watcher = WatcherService::new(db.clone(), base_path.clone());
+
+    let edited = note_service
+      .create_note("編集".to_string(), "元の内容".to_string(), None, None)
+      .unwrap();
+    let removed = note_service
+      .create_note("削除".to_string(), "消える".to_string(), None, None)
+      .unwrap();
+    let moved = note_service
+      .create_note("移動前".to_string(), "移動する内容".to_string(), None, None)
+      .unwrap();
+
+    // アプリ自身の書き込みは変更として扱わない
+    watcher.sync_changes().unwrap();
+    assert!(sync(&mut watcher).is_empty());
+
+    fs::write(&edited.file_path, "外部エディタで編集").unwrap();
+    fs::remove_file(&removed.file_path).unwrap();
+    fs::create_dir_all(base_path.join("新規フォルダ")).unwrap();
+    fs::rename(
+      &moved.file_path,
+      base_path.join("新規フォルダ").join("移動後.md"),
+    )
+    .unwrap();
+    fs::write(base_path.join("新規.md"), "git で追加").unwrap();
+
+    let changes = sync(&mut watcher);
+    assert_eq!(changes.modified_notes, vec![edited.id]);
+    assert_eq!(changes.deleted_notes, vec![removed.id]);
+    assert_eq!(changes.renamed_notes, vec![moved.id]);
+    assert_eq!(changes.created_notes.len(), 1);
+    assert_eq!(changes.created_folders.len(), 1);
+
+    let results = note_service.search_notes("外部エディタ").unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(is_deleted(&db, removed.id));
+
+    let moved = note_service.get_note_by_id(moved.id).unwrap();
+    assert_eq!(moved.title, "移動後");
+    assert_eq!(moved.parent_id, Some(changes.created_folders[0]));
+
+    let created = note_service
+      .get_note_by_id(changes.created_notes[0])
+      .unwrap();
+    assert_eq!(created.title, "新規");
+    assert_eq!(created.content, "git で追加");
+
+    // 外部で削除されたフォルダ
+    fs::remove_dir_all(base_path.join("新規フォルダ")).unwrap();
+    let changes = sync(&mut watcher);
+    assert_eq!(changes.deleted_notes, vec![moved.id]);
+    assert_eq!(changes.deleted_folders.len(), 1);
+    assert!(is_deleted(&db, moved.id));
+  }
+
+  fn is_deleted(db: &Database, id: i64) -> bool {
+    let conn = db.conn.lock().unwrap();
+    conn
+      .query_row(
+        "SELECT is_deleted FROM notes WHERE id = ?",
+        params![id],
+        |row| row.get(0),
+      )
+      .unwrap()
+  }
+
+  #[test]
+  fn test_sync_renamed_folder() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    fs::create_dir_all(&base_path).unwrap();
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let folder_service = FolderService::new(db.clone(), base_path.clone());
+    let mut watcher = WatcherService::new(db.clone(), base_path.clone());
+
+    let folder = folder_service
+      .create_folder("旧フォルダ".to_string(), None, None)
+      .unwrap();
+    let child = folder_service
+      .create_folder(
+        "子".to_string(),
+        Some(folder.id),
+        Some(folder.folder_path.clone()),
+      )
+      .unwrap();
+    // 内容が同じノートがあってもフォルダ内の位置で対応付ける
+    let first = note_service
+      .create_note(
+        "a".to_string(),
+        "同じ内容".to_string(),
+        Some(folder.id),
+        Some(folder.folder_path.clone()),
+      )
+      .unwrap();
+    let second = note_service
+      .create_note(
+        "b".to_string(),
+        "同じ内容".to_string(),
+        Some(child.id),
+        Some(child.folder_path.clone()),
+      )
+      .unwrap();
+    {
+      let conn = db.conn.lock().unwrap();
+      conn
+        .execute(
+          "UPDATE folders SET icon = 'star', color = 'red' WHERE id = ?",
+          params![folder.id],
+        )
+        .unwrap();
+    }
+
+    watcher.sync_changes().unwrap();
+    assert!(sync(&mut watcher).is_empty());
+
+    fs::rename(base_path.join("旧フォルダ"), base_path.join("新フォルダ")).unwrap();
+
+    let mut changes = sync(&mut watcher);
+    changes.renamed_folders.sort();
+    changes.renamed_notes.sort();
+    assert_eq!(changes.renamed_folders, vec![folder.id, child.id]);
+    assert_eq!(changes.renamed_notes, vec![first.id, second.id]);
+    assert!(changes.created_folders.is_empty());
+    assert!(changes.deleted_folders.is_empty());
+    assert!(changes.deleted_notes.is_empty());
+
+    let renamed = folder_service.get_folder_by_id(folder.id).unwrap();
+    assert_eq!(renamed.name, "新フォルダ");
+    assert_eq!(renamed.icon.as_deref(), Some("star"));
+    assert_eq!(renamed.color.as_deref(), Some("red"));
+
+    let second = note_service.get_note_by_id(second.id).unwrap();
+    assert_eq!(second.parent_id, Some(child.id));
+    assert!(second.file_path.contains("新フォルダ"));
+  }
+
+  #[test]
+  fn test_sync_skips_unavailable_directory() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    fs::create_dir_all(&base_path).unwrap();
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let mut watcher = WatcherService::new(db.clone(), base_path.clone());
+
+    let note = note_service
+      .create_note("残る".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    watcher.sync_changes().unwrap();
+    assert!(sync(&mut watcher).is_empty());
+
+    // ディレクトリごと見えなくなっても、中身が空になってもノートは消さない
+    let hidden = temp_dir.path().join("hidden");
+    fs::rename(&base_path, &hidden).unwrap();
+    assert!(sync(&mut watcher).is_empty());
+    fs::create_dir_all(&base_path).unwrap();
+    assert!(sync(&mut watcher).is_empty());
+    assert!(!is_deleted(&db, note.id));
+
+    fs::remove_dir(&base_path).unwrap();
+    fs::rename(&hidden, &base_path).unwrap();
+    assert!(sync(&mut watcher).is_empty());
+    assert!(!is_deleted(&db, note.id));
+  }
+
+  #[test]
+  fn test_sync_deletes_last_note() {
+    let (db, temp_dir) = setup_test_db();
+    let base_path = temp_dir.path().join("notes");
+    fs::create_dir_all(&base_path).unwrap();
+    let note_service = NoteService::new(db.clone(), base_path.clone());
+    let mut watcher = WatcherService::new(db.clone(), base_path.clone());
+
+    let note = note_service
+      .create_note("最後".to_string(), "内容".to_string(), None, None)
+      .unwrap();
+    watcher.sync_changes().unwrap();
+    assert!(sync(&mut watcher).is_empty());
+
+    // 同じディレクトリの中で最後のノートを消した場合は反映する
+    fs::remove_file(&note.file_path).unwrap();
+    let changes = sync(&mut watcher);
+    assert_eq!(changes.deleted_notes, vec![note.id]);
+    assert!(is_deleted(&db, note.id));
+  }
+}
@@ -4,6 +4,8 @@ import { toast } from 'sonner';
 import { exit } from '@tauri-apps/plugin-process';
 
 import { checkInitialization } from '@/lib/api/app';
+import { useFileStore } from '@/stores/files';
+import { useFolderStore } from '@/stores/folders';
 import { useNoteStore } from '@/stores/notes';
 import { useSettingsStore } from '@/stores/settings';
 import { useTemplateStore } from '@/stores/templates';
@@ -16,6 +18,7 @@ import { TitleBar } from '@/components/layout/title-bar';
 import './App.css';
 import { ThemeProvider } from './components/theme/theme-provider';
 import { Toaster } from './components/ui/sonner';
+import { type FilesChanged } from './types/files';
 
 import { listen } from '@tauri-apps/api/event';
 
@@ -53,7 +56,7 @@ export default function App() {
   const [isSidebarOpen, setIsSidebarOpen] = useState(false);
   const [isInitialized, setIsInitialized] = useState<boolean | null>(null);
   const [showActivityDashboard, setShowActivityDashboard] = useState(false);
-  const { setCurrentNote, createNote } = useNoteStore();
+  const { setCurrentNote, createNote, loadNote } = useNoteStore();
   const { isTemplateEditorOpen } = useTemplateStore();
   const { isSettingsOpen, toggleSettings } = useSettingsStore();
 
@@ -100,6 +103,80 @@ export default function App() {
     };
   }, [createNote]);
 
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let ignore = false;
+
+    async function setupListener() {
+      // ショートカットで開いた (または作成した) 今日のデイリーノートを表示する
+      const unlistenFn = await listen<number>('open-daily-note', (event) => {
+        loadNote(event.payload);
+        useSettingsStore.getState().setSettingsOpen(false);
+        useTemplateStore.getState().setTemplateEditorOpen(false);
+        setShowActivityDashboard(false);
+      });
+
+      if (ignore) {
+        unlistenFn();
+      } else {
+        unlisten = unlistenFn;
+      }
+    }
+
+    setupListener();
+
+    return () => {
+      ignore = true;
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [loadNote]);
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let ignore = false;
+
+    async function setupListener() {
+      // 外部のエディタや git での変更をファイルツリーと開いているノートに反映する
+      const unlistenFn = await listen<FilesChanged>('files-changed', event => {
+        const changes = event.payload;
+        useFileStore.getState().loadFiles();
+        useFolderStore.getState().loadFolders();
+
+        const noteStore = useNoteStore.getState();
+        const { currentNote } = noteStore;
+        if (!currentNote) {
+          return;
+        }
+        if (changes.deleted_notes.includes(currentNote.id)) {
+          noteStore.setCurrentNote(null);
+          noteStore.setCurrentContent(null);
+        } else if (
+          changes.modified_notes.includes(currentNote.id) ||
+          changes.renamed_notes.includes(currentNote.id)
+        ) {
+          loadNote(currentNote.id);
+        }
+      });
+
+      if (ignore) {
+        unlistenFn();
+      } else {
+        unlisten = unlistenFn;
+      }
+    }
+
+    setupListener();
+
+    return () => {
+      ignore = true;
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [loadNote]);
+
   useEffect(() => {
     function handleCloseActivityDashboard() {
       setShowActivityDashboard(false);
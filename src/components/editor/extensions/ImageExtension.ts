@@ -1,9 +1,50 @@
 import { toast } from 'sonner';
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
 import { Image } from '@tiptap/extension-image';
 import { Plugin, PluginKey } from '@tiptap/pm/state';
 
+import { attachFile } from '@/lib/api/assets';
+
+// ノートには assets/<hash>.png のリンクを保存し、表示するときだけカスタムプロトコルの URL にする
+const ASSET_PREFIX = 'assets/';
+const ASSET_PROTOCOL = 'notly-asset';
+
+export function assetUrl(src: string): string {
+  return src.startsWith(ASSET_PREFIX)
+    ? convertFileSrc(src.slice(ASSET_PREFIX.length), ASSET_PROTOCOL)
+    : src;
+}
+
+// プレビュー用の縮小画像 (初めて表示したときに作られる)
+// 大きさを URL に含め、設定を変えたときに古いキャッシュが使われないようにする
+export function thumbnailUrl(src: string, size: number): string {
+  return src.startsWith(ASSET_PREFIX)
+    ? convertFileSrc(`thumbnails/${size}/${src.slice(ASSET_PREFIX.length)}`, ASSET_PROTOCOL)
+    : src;
+}
+
+function assetLink(src: string): string {
+  const base = convertFileSrc('', ASSET_PROTOCOL);
+  return src.startsWith(base) ? ASSET_PREFIX + decodeURIComponent(src.slice(base.length)) : src;
+}
+
 export const ImageExtension = Image.extend({
+  addAttributes() {
+    return {
+      ...this.parent?.(),
+      src: {
+        default: null,
+        parseHTML: element => {
+          const src = element.getAttribute('src');
+          return src ? assetLink(src) : null;
+        },
+        renderHTML: attributes => ({
+          src: attributes.src ? assetUrl(attributes.src) : null
+        })
+      }
+    };
+  },
+
   addProseMirrorPlugins() {
     return [
       new Plugin({
@@ -27,18 +68,17 @@ export const ImageExtension = Image.extend({
             const hasFiles = event.dataTransfer?.files?.length;
             if (!hasFiles) return false;
 
-            const images = [...event.dataTransfer.files].filter(file =>
-              file.type.startsWith('image/')
-            );
-
-            if (images.length > 0) {
-              event.preventDefault();
-              for (const image of images) {
-                handleImageUpload(image, view);
+            const files = [...event.dataTransfer.files];
+            event.preventDefault();
+            for (const file of files) {
+              // 画像以外のファイルはリンクとして添付する
+              if (file.type.startsWith('image/')) {
+                handleImageUpload(file, view);
+              } else {
+                handleFileAttach(file, view);
               }
-              return true;
             }
-            return false;
+            return true;
           }
         }
       })
@@ -55,16 +95,31 @@ async function handleImageUpload(file: File, view: any) {
     // Get extension from file name or type
     const extension = file.name.split('.').pop() || file.type.split('/')[1] || 'png';
 
-    const dataUrl = await invoke<string>('save_image', {
+    const link = await invoke<string>('save_image', {
       imageData: [...uint8Array],
       extension
     });
 
     const { schema } = view.state;
-    const node = schema.nodes.image.create({ src: dataUrl });
+    const node = schema.nodes.image.create({ src: link });
     const transaction = view.state.tr.replaceSelectionWith(node);
     view.dispatch(transaction);
   } catch {
     toast.error('Failed to upload image');
   }
 }
+
+// eslint-disable-next-line @typescript-eslint/no-explicit-any
+async function handleFileAttach(file: File, view: any) {
+  try {
+    const attachment = await attachFile(file);
+    const { schema } = view.state;
+    const label = file.name || attachment.file_name;
+    const node = schema.marks.link
+      ? schema.text(label, [schema.marks.link.create({ href: attachment.link })])
+      : schema.text(attachment.markdown);
+    view.dispatch(view.state.tr.replaceSelectionWith(node, false));
+  } catch (error) {
+    toast.error(`Failed to attach file: ${error}`);
+  }
+}
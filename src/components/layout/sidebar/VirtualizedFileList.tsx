@@ -9,8 +9,12 @@ import { flattenFiles } from './utils/flatten';
 export const VirtualizedFileList = memo(function VirtualizedFileList() {
   const { files } = useFiles();
   const openFolderIds = useFolderStore(state => state.openFolderIds);
+  const openSavedSearchIds = useFolderStore(state => state.openSavedSearchIds);
 
-  const flattenedItems = useMemo(() => flattenFiles(files, openFolderIds), [files, openFolderIds]);
+  const flattenedItems = useMemo(
+    () => flattenFiles(files, openFolderIds, openSavedSearchIds),
+    [files, openFolderIds, openSavedSearchIds]
+  );
 
   return (
     <Virtuoso
@@ -21,7 +25,10 @@ export const VirtualizedFileList = memo(function VirtualizedFileList() {
         return (
           <div style={{ paddingLeft: `${flatItem.depth * 16}px` }}>
             <VirtualizedProvider value>
-              <FileItem item={flatItem.item} />
+              <FileItem
+                item={flatItem.item}
+                readOnly={flatItem.readOnly}
+              />
             </VirtualizedProvider>
           </div>
         );
@@ -4,8 +4,15 @@ import { type FileItem as FileItemType } from '@/types/files';
 
 import { FolderItem } from './FolderItem';
 import { NoteItem } from './NoteItem';
+import { SavedSearchHitItem, SavedSearchItem } from './SavedSearchItem';
 
-export const FileItem = memo(function FileItem({ item }: { item: FileItemType }) {
+type FileItemProps = {
+  item: FileItemType;
+  // 保存済み検索の子として表示する
+  readOnly?: boolean;
+};
+
+export const FileItem = memo(function FileItem({ item, readOnly = false }: FileItemProps) {
   const setCurrentFolder = useFolderStore(state => state.setCurrentFolder);
 
   if ('folder' in item) {
@@ -19,7 +26,10 @@ export const FileItem = memo(function FileItem({ item }: { item: FileItemType })
     );
   }
   if ('note' in item) {
-    return <NoteItem note={item.note} />;
+    return readOnly ? <SavedSearchHitItem note={item.note} /> : <NoteItem note={item.note} />;
+  }
+  if ('savedSearch' in item) {
+    return <SavedSearchItem savedSearch={item.savedSearch} />;
   }
   return null;
 });
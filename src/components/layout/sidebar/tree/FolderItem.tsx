@@ -76,7 +76,7 @@ import { FolderItemMoveMenu } from './folder-item/FolderItemMoveMenu';
 import { getAllDescendants } from './folder-item/utils';
 
 // lucide-reactアイコンと文字列のマッピング
-const iconMap: Record<
+export const iconMap: Record<
   string,
   React.ComponentType<{ className?: string; style?: React.CSSProperties }>
 > = {
@@ -227,7 +227,8 @@ export const FolderItem = memo(function FolderItem({
         folder.icon,
         folder.color,
         folder.sortBy,
-        folder.sortOrder
+        folder.sortOrder,
+        folder.defaultTemplateId
       );
       setIsEditing(false);
     }
@@ -264,7 +265,8 @@ export const FolderItem = memo(function FolderItem({
       icon,
       folder.color,
       folder.sortBy,
-      folder.sortOrder
+      folder.sortOrder,
+      folder.defaultTemplateId
     );
     setShowIconDialog(false);
   }
@@ -278,7 +280,8 @@ export const FolderItem = memo(function FolderItem({
       folder.icon,
       selectedColor,
       folder.sortBy,
-      folder.sortOrder
+      folder.sortOrder,
+      folder.defaultTemplateId
     );
     setShowColorPicker(false);
   }
@@ -292,7 +295,8 @@ export const FolderItem = memo(function FolderItem({
       folder.icon,
       folder.color,
       tempSortBy,
-      tempSortOrder
+      tempSortOrder,
+      folder.defaultTemplateId
     );
     setShowSortSettings(false);
   }
@@ -572,7 +576,13 @@ export const FolderItem = memo(function FolderItem({
           <div className="space-y-0.5 relative">
             {folder.children.map(item => (
               <FileItemComponent
-                key={'folder' in item ? `folder-${item.folder.id}` : `note-${item.note.id}`}
+                key={
+                  'folder' in item
+                    ? `folder-${item.folder.id}`
+                    : 'note' in item
+                      ? `note-${item.note.id}`
+                      : `saved-search-${item.savedSearch.id}`
+                }
                 item={item}
               />
             ))}
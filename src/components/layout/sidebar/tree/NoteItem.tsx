@@ -207,10 +207,10 @@ export const NoteItem = memo(function NoteItem({ note }: NoteItemProps) {
                         <Star
                           className={cn(
                             'mr-2 h-4 w-4',
-                            note.isFavorite && 'fill-yellow-400 text-yellow-400'
+                            note.is_favorite && 'fill-yellow-400 text-yellow-400'
                           )}
                         />
-                        {note.isFavorite ? 'お気に入り解除' : 'お気に入りに追加'}
+                        {note.is_favorite ? 'お気に入り解除' : 'お気に入りに追加'}
                       </DropdownMenuItem>
                       <DropdownMenuItem onClick={() => setIsEditing(true)}>
                         <Edit2 className="mr-2 h-4 w-4" />
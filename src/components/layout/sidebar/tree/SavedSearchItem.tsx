@@ -0,0 +1,71 @@
+import { ChevronRight, FileText, Search } from 'lucide-react';
+import { memo } from 'react';
+
+import { cn } from '@/lib/utils';
+import { useFolderStore } from '@/stores/folders';
+import { useNoteStore } from '@/stores/notes';
+import { useSettingsStore } from '@/stores/settings';
+import { type SavedSearchFolder } from '@/types/files';
+import { type Note } from '@/types/notes';
+
+import { iconMap } from './FolderItem';
+
+type SavedSearchItemProps = {
+  savedSearch: SavedSearchFolder;
+};
+
+// 保存済み検索の仮想フォルダ (ヒットしたノートを子として表示する読み取り専用のフォルダ)
+export const SavedSearchItem = memo(function SavedSearchItem({
+  savedSearch
+}: SavedSearchItemProps) {
+  const isOpen = useFolderStore(state => state.openSavedSearchIds.includes(savedSearch.id));
+  const toggleSavedSearch = useFolderStore(state => state.toggleSavedSearch);
+
+  const Icon = (savedSearch.icon && iconMap[savedSearch.icon]) || Search;
+
+  return (
+    <div
+      className="flex items-center gap-2 pl-2 pr-2 py-1.5 rounded text-primary dark:text-white cursor-pointer hover:bg-gray-200 dark:hover:bg-gray-700/50"
+      title={savedSearch.query}
+      onClick={e => {
+        e.stopPropagation();
+        toggleSavedSearch(savedSearch.id);
+      }}>
+      <ChevronRight
+        className={cn('h-4 w-4 transform transition-transform', isOpen && 'rotate-90')}
+      />
+      <span className="rounded-full p-1">
+        <Icon className="h-4 w-4" />
+      </span>
+      <p className="text-sm font-medium flex-1 truncate">{savedSearch.name}</p>
+      <span className="text-xs text-muted-foreground">{savedSearch.children.length}</span>
+    </div>
+  );
+});
+
+// 保存済み検索にヒットしたノート (移動や名前の変更はできない)
+export const SavedSearchHitItem = memo(function SavedSearchHitItem({ note }: { note: Note }) {
+  const loadNote = useNoteStore(state => state.loadNote);
+  const isCurrentNote = useNoteStore(state => state.currentNote?.id === note.id);
+
+  return (
+    <div
+      className={cn(
+        'flex items-center gap-2 pl-6 pr-2 py-1.5 rounded text-primary dark:text-white cursor-pointer',
+        isCurrentNote
+          ? 'bg-gray-300/50 dark:bg-gray-600/50'
+          : 'hover:bg-gray-200 dark:hover:bg-gray-700/50'
+      )}
+      onClick={() => {
+        const { isSettingsOpen, setSettingsOpen } = useSettingsStore.getState();
+        if (isSettingsOpen) {
+          setSettingsOpen(false);
+        }
+        globalThis.dispatchEvent(new CustomEvent('close-activity-dashboard'));
+        loadNote(note.id);
+      }}>
+      <FileText className="h-4 w-4 shrink-0" />
+      <p className="text-sm font-medium truncate">{note.title}</p>
+    </div>
+  );
+});
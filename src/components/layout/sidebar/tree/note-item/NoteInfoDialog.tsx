@@ -1,9 +1,12 @@
 import { useEffect, useState } from 'react';
 import { toast } from 'sonner';
 
+import { thumbnailUrl } from '@/components/editor/extensions/ImageExtension';
 import { Dialog, DialogContent, DialogHeader, DialogTitle } from '@/components/ui/dialog';
+import { getAssetSettings, getNoteAttachments } from '@/lib/api/assets';
 import { loadNote } from '@/lib/api/notes';
 import { getTagsByNote } from '@/lib/api/tags';
+import { type Asset } from '@/types/assets';
 import { type Note } from '@/types/notes';
 import { type Tag } from '@/types/tags';
 
@@ -23,25 +26,36 @@ function formatDate(dateStr: Date | string) {
   });
 }
 
+// 画像以外や SVG はサムネイルを作らないので名前だけを表示する
+function hasThumbnail(asset: Asset) {
+  return asset.mime_type.startsWith('image/') && asset.mime_type !== 'image/svg+xml';
+}
+
 export function NoteInfoDialog({ noteId, open, onOpenChange }: NoteInfoDialogProps) {
   const [info, setInfo] = useState<{
     note: Note | null;
     content: string;
     tags: Tag[];
-  }>({ note: null, content: '', tags: [] });
+    attachments: Asset[];
+    thumbnailSize: number;
+  }>({ note: null, content: '', tags: [], attachments: [], thumbnailSize: 0 });
 
   useEffect(() => {
     async function fetchData() {
       if (open) {
         try {
-          const [noteWithContent, tags] = await Promise.all([
+          const [noteWithContent, tags, attachments, settings] = await Promise.all([
             loadNote(noteId),
-            getTagsByNote(noteId)
+            getTagsByNote(noteId),
+            getNoteAttachments(noteId),
+            getAssetSettings()
           ]);
           setInfo({
             note: noteWithContent,
             content: noteWithContent.content,
-            tags
+            tags,
+            attachments,
+            thumbnailSize: settings.thumbnail_size
           });
         } catch (error) {
           toast.error('ノート情報の取得に失敗しました', {
@@ -103,6 +117,27 @@ export function NoteInfoDialog({ noteId, open, onOpenChange }: NoteInfoDialogPro
                   ))
                 : 'なし'}
             </div>
+
+            <div className="text-muted-foreground">添付ファイル</div>
+            <div className="space-y-1">
+              {info.attachments.length > 0
+                ? info.attachments.map(asset => (
+                    <div
+                      key={asset.id}
+                      className="flex items-center gap-2 text-xs">
+                      {hasThumbnail(asset) && (
+                        <img
+                          src={thumbnailUrl(asset.link, info.thumbnailSize)}
+                          alt={asset.original_name ?? asset.file_name}
+                          className="h-10 w-10 rounded object-cover"
+                          loading="lazy"
+                        />
+                      )}
+                      <span className="break-all">{asset.original_name ?? asset.file_name}</span>
+                    </div>
+                  ))
+                : 'なし'}
+            </div>
           </div>
         </div>
       </DialogContent>
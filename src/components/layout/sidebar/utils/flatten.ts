@@ -4,23 +4,45 @@ export type FlattenedItem = {
   item: FileItem;
   depth: number;
   index: number;
+  // 保存済み検索のヒット (読み取り専用で表示する)
+  readOnly: boolean;
 };
 
 export function flattenFiles(
   files: FileItem[],
   openFolderIds: number[],
+  openSavedSearchIds: number[] = [],
   depth = 0,
-  result: FlattenedItem[] = []
+  result: FlattenedItem[] = [],
+  readOnly = false
 ): FlattenedItem[] {
   for (const file of files) {
     result.push({
       item: file,
       depth,
-      index: result.length
+      index: result.length,
+      readOnly
     });
 
     if ('folder' in file && openFolderIds.includes(file.folder.id) && file.folder.children) {
-      flattenFiles(file.folder.children, openFolderIds, depth + 1, result);
+      flattenFiles(
+        file.folder.children,
+        openFolderIds,
+        openSavedSearchIds,
+        depth + 1,
+        result,
+        readOnly
+      );
+    }
+    if ('savedSearch' in file && openSavedSearchIds.includes(file.savedSearch.id)) {
+      flattenFiles(
+        file.savedSearch.children,
+        openFolderIds,
+        openSavedSearchIds,
+        depth + 1,
+        result,
+        true
+      );
     }
   }
   return result;
@@ -0,0 +1,50 @@
+import {
+  type Asset,
+  type AssetCleanupAction,
+  type AssetCleanupReport,
+  type AssetSettings,
+  type Attachment,
+  type UnusedAsset
+} from '@/types/assets';
+
+import { safeInvoke } from '../tauri';
+
+// 大きすぎるファイルは読み込む前に断り、内容は生のバイト列として送る
+export async function attachFile(file: File): Promise<Attachment> {
+  const { max_file_size } = await getAssetSettings();
+  if (file.size > max_file_size) {
+    throw new Error(`File is too large: ${file.size} bytes (limit ${max_file_size} bytes)`);
+  }
+  const fileData = new Uint8Array(await file.arrayBuffer());
+  return safeInvoke<Attachment>('attach_file', fileData, {
+    headers: { 'x-file-name': encodeURIComponent(file.name) }
+  });
+}
+
+export function getNoteAttachments(noteId: number): Promise<Asset[]> {
+  return safeInvoke<Asset[]>('get_note_attachments', { noteId });
+}
+
+export function getAssetSettings(): Promise<AssetSettings> {
+  return safeInvoke<AssetSettings>('get_asset_settings');
+}
+
+export function updateAssetSettings(settings: AssetSettings): Promise<AssetSettings> {
+  return safeInvoke<AssetSettings>('update_asset_settings', { settings });
+}
+
+export function getAsset(fileName: string): Promise<Asset> {
+  return safeInvoke<Asset>('get_asset', { fileName });
+}
+
+export function findUnusedAssets(): Promise<UnusedAsset[]> {
+  return safeInvoke<UnusedAsset[]>('find_unused_assets');
+}
+
+// dryRun なら何も変更せずに対象の一覧とサイズだけを返す
+export function cleanUpUnusedAssets(
+  action: AssetCleanupAction,
+  dryRun: boolean
+): Promise<AssetCleanupReport> {
+  return safeInvoke<AssetCleanupReport>('clean_up_unused_assets', { action, dryRun });
+}
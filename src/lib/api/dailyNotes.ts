@@ -0,0 +1,21 @@
+import { type DailyNoteNeighbors, type DailyNoteSettings } from '@/types/dailyNotes';
+import { type NoteWithContent } from '@/types/notes';
+
+import { safeInvoke } from '../tauri';
+
+// date は YYYY-MM-DD (省略すると今日)
+export function openOrCreateDailyNote(date?: string): Promise<NoteWithContent> {
+  return safeInvoke<NoteWithContent>('open_or_create_daily_note', { date });
+}
+
+export function getDailyNoteNeighbors(date?: string): Promise<DailyNoteNeighbors> {
+  return safeInvoke<DailyNoteNeighbors>('get_daily_note_neighbors', { date });
+}
+
+export function getDailyNoteSettings(): Promise<DailyNoteSettings> {
+  return safeInvoke<DailyNoteSettings>('get_daily_note_settings');
+}
+
+export function updateDailyNoteSettings(settings: DailyNoteSettings): Promise<DailyNoteSettings> {
+  return safeInvoke<DailyNoteSettings>('update_daily_note_settings', { settings });
+}
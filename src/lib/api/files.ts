@@ -1,7 +1,25 @@
-import { type FileItem } from '@/types/files';
+import { type FileItem, type SortSettings } from '@/types/files';
 
 import { safeInvoke } from '../tauri';
 
 export function getAllFiles(): Promise<FileItem[]> {
   return safeInvoke<FileItem[]>('get_all_files');
 }
+
+export function getDefaultSort(): Promise<SortSettings> {
+  return safeInvoke<SortSettings>('get_default_sort');
+}
+
+export function updateDefaultSort(input: SortSettings): Promise<SortSettings> {
+  return safeInvoke<SortSettings>('update_default_sort', { input });
+}
+
+export function reorderFiles(
+  parentId: number | null,
+  folderIds: number[],
+  noteIds: number[]
+): Promise<void> {
+  return safeInvoke<void>('reorder_files', {
+    input: { parent_id: parentId, folder_ids: folderIds, note_ids: noteIds }
+  });
+}
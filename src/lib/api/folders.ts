@@ -34,7 +34,8 @@ export function updateFolder(
   icon?: string | null,
   color?: string | null,
   sortBy?: string | null,
-  sortOrder?: string | null
+  sortOrder?: string | null,
+  defaultTemplateId?: number | null
 ): Promise<Folder> {
   return safeInvoke<Folder>('update_folder', {
     input: {
@@ -45,7 +46,8 @@ export function updateFolder(
       icon,
       color,
       sort_by: sortBy,
-      sort_order: sortOrder
+      sort_order: sortOrder,
+      default_template_id: defaultTemplateId
     }
   });
 }
@@ -1,4 +1,4 @@
-import { type Note, type NoteWithContent } from '@/types/notes';
+import { type Note, type NoteProperties, type NoteWithContent } from '@/types/notes';
 
 import { safeInvoke } from '../tauri';
 
@@ -36,6 +36,18 @@ export function updateNote(id: number, title: string, content: string): Promise<
   });
 }
 
+export function updateNoteProperties(
+  id: number,
+  properties: NoteProperties
+): Promise<NoteWithContent> {
+  return safeInvoke<NoteWithContent>('update_note_properties', {
+    input: {
+      id,
+      properties
+    }
+  });
+}
+
 export function deleteNote(id: number): Promise<void> {
   return safeInvoke<void>('delete_note', { id });
 }
@@ -100,3 +112,7 @@ export function importNotes(
 export function updateFavoriteOrder(id: number, order: number): Promise<void> {
   return safeInvoke<void>('update_favorite_order', { id, order });
 }
+
+export function reorderFavorites(ids: number[]): Promise<void> {
+  return safeInvoke<void>('reorder_favorites', { ids });
+}
@@ -1,5 +1,5 @@
 import { type Note } from '@/types/notes';
-import { type Tag } from '@/types/tags';
+import { type NoteTag, type Tag, type TagWithStats } from '@/types/tags';
 
 import { safeInvoke } from '../tauri';
 
@@ -12,12 +12,36 @@ export function createTag(name: string, color?: string | null): Promise<Tag> {
   });
 }
 
-export function updateTag(id: number, name: string, color?: string | null): Promise<Tag> {
+export function updateTag(
+  id: number,
+  name: string,
+  color?: string | null,
+  rewriteContent = false
+): Promise<Tag> {
   return safeInvoke<Tag>('update_tag', {
     input: {
       id,
       name,
-      color
+      color,
+      rewrite_content: rewriteContent
+    }
+  });
+}
+
+export function mergeTags(sourceIds: number[], targetId: number): Promise<Tag> {
+  return safeInvoke<Tag>('merge_tags', {
+    input: {
+      source_ids: sourceIds,
+      target_id: targetId
+    }
+  });
+}
+
+export function moveTag(id: number, newParentId: number | null): Promise<Tag> {
+  return safeInvoke<Tag>('move_tag', {
+    input: {
+      id,
+      new_parent_id: newParentId
     }
   });
 }
@@ -26,8 +50,8 @@ export function deleteTag(id: number): Promise<void> {
   return safeInvoke<void>('delete_tag', { id });
 }
 
-export function getAllTags(): Promise<Tag[]> {
-  return safeInvoke<Tag[]>('get_all_tags');
+export function getAllTags(): Promise<TagWithStats[]> {
+  return safeInvoke<TagWithStats[]>('get_all_tags');
 }
 
 export function addTagToNote(noteId: number, tagId: number): Promise<void> {
@@ -42,10 +66,10 @@ export function removeTagFromNote(noteId: number, tagId: number): Promise<void>
   return safeInvoke<void>('remove_tag_from_note', { noteId, tagId });
 }
 
-export function getNotesByTag(tagId: number): Promise<Note[]> {
-  return safeInvoke<Note[]>('get_notes_by_tag', { tagId });
+export function getNotesByTag(tagId: number, includeDescendants = false): Promise<Note[]> {
+  return safeInvoke<Note[]>('get_notes_by_tag', { tagId, includeDescendants });
 }
 
-export function getTagsByNote(noteId: number): Promise<Tag[]> {
-  return safeInvoke<Tag[]>('get_tags_by_note', { noteId });
+export function getTagsByNote(noteId: number): Promise<NoteTag[]> {
+  return safeInvoke<NoteTag[]>('get_tags_by_note', { noteId });
 }
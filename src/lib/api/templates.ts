@@ -1,7 +1,11 @@
+import { type NoteWithContent } from '@/types/notes';
 import {
   type Template,
   type CreateTemplateInput,
-  type UpdateTemplateInput
+  type UpdateTemplateInput,
+  type TemplateContext,
+  type RenderedTemplate,
+  type CreateNoteFromTemplateInput
 } from '@/types/templates';
 import { invoke } from '@tauri-apps/api/core';
 
@@ -24,3 +28,32 @@ export async function updateTemplate(input: UpdateTemplateInput): Promise<Templa
 export async function deleteTemplate(id: number): Promise<void> {
   return await invoke<void>('delete_template', { id });
 }
+
+export async function renderTemplate(
+  id: number,
+  context: TemplateContext
+): Promise<RenderedTemplate> {
+  return await invoke<RenderedTemplate>('render_template', { id, context });
+}
+
+export async function getTemplatePrompts(id: number): Promise<string[]> {
+  return await invoke<string[]>('get_template_prompts', { id });
+}
+
+export async function createNoteFromTemplate(
+  input: CreateNoteFromTemplateInput
+): Promise<NoteWithContent & { cursor: number | null }> {
+  return await invoke<NoteWithContent & { cursor: number | null }>('create_note_from_template', {
+    input
+  });
+}
+
+// テンプレートを .md ファイルの zip として書き出す (書き出した数を返す)
+export async function exportTemplates(path: string): Promise<number> {
+  return await invoke<number>('export_templates', { path });
+}
+
+// zip の .md ファイルをテンプレートとして取り込む (同じ名前のものは上書きする)
+export async function importTemplates(path: string): Promise<Template[]> {
+  return await invoke<Template[]>('import_templates', { path });
+}
@@ -1,10 +1,14 @@
 import { toast } from 'sonner';
 
-import { invoke } from '@tauri-apps/api/core';
+import { type InvokeArgs, type InvokeOptions, invoke } from '@tauri-apps/api/core';
 
-export async function safeInvoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+export async function safeInvoke<T>(
+  command: string,
+  args?: InvokeArgs,
+  options?: InvokeOptions
+): Promise<T> {
   try {
-    return await invoke<T>(command, args);
+    return await invoke<T>(command, args, options);
   } catch (error) {
     toast.error(`Command "${command}":`, {
       description: error as string
@@ -1,15 +1,12 @@
 import { create } from 'zustand';
 
-import { type FileItem } from '@/types/files';
+import { type FileItem, type SortBy, type SortOrder } from '@/types/files';
 
-import { getAllFiles } from '@/lib/api/files';
+import { getAllFiles, getDefaultSort, updateDefaultSort } from '@/lib/api/files';
 import { searchNotes } from '@/lib/api/notes';
 import { getNotesByTag } from '@/lib/api/tags';
 import { useFolderStore } from './folders';
 
-type SortBy = 'name' | 'createdAt' | 'updatedAt';
-type SortOrder = 'asc' | 'desc';
-
 type FileStore = {
   files: FileItem[];
   filteredFiles: FileItem[];
@@ -20,8 +17,8 @@ type FileStore = {
   setSearchQuery: (query: string) => void;
   sortBy: SortBy;
   sortOrder: SortOrder;
-  setSortBy: (sortBy: SortBy) => void;
-  setSortOrder: (sortOrder: SortOrder) => void;
+  setSortBy: (sortBy: SortBy) => Promise<void>;
+  setSortOrder: (sortOrder: SortOrder) => Promise<void>;
   selectedTagId: number | null;
   setSelectedTagId: (tagId: number | null) => Promise<void>;
 };
@@ -39,6 +36,10 @@ function collectFolderIds(items: FileItem[]): number[] {
   return folderIds;
 }
 
+function collectSavedSearchIds(items: FileItem[]): number[] {
+  return items.flatMap(item => ('savedSearch' in item ? [item.savedSearch.id] : []));
+}
+
 async function filterFilesWithContent(files: FileItem[], query: string): Promise<FileItem[]> {
   if (!query.trim()) {
     return files;
@@ -63,6 +64,16 @@ async function filterFilesWithContent(files: FileItem[], query: string): Promise
             }
           });
         }
+      } else if ('savedSearch' in item) {
+        const filteredChildren = filterRecursive(item.savedSearch.children);
+        if (filteredChildren.length > 0) {
+          result.push({
+            savedSearch: {
+              ...item.savedSearch,
+              children: filteredChildren
+            }
+          });
+        }
       } else if ('note' in item && matchedNoteIds.has(item.note.id)) {
         result.push(item);
       }
@@ -73,56 +84,6 @@ async function filterFilesWithContent(files: FileItem[], query: string): Promise
   return filterRecursive(files);
 }
 
-function getComparator(sortBy: SortBy, sortOrder: SortOrder) {
-  return (a: FileItem, b: FileItem) => {
-    const aIsFolder = 'folder' in a;
-    const bIsFolder = 'folder' in b;
-
-    if (aIsFolder && !bIsFolder) return -1;
-    if (!aIsFolder && bIsFolder) return 1;
-
-    function getTime(item: FileItem): number {
-      const isFolder = 'folder' in item;
-      if (sortBy === 'createdAt') {
-        return new Date(isFolder ? item.folder.createdAt : item.note.created_at).getTime();
-      }
-      return new Date(isFolder ? item.folder.updatedAt : item.note.updated_at).getTime();
-    }
-
-    let comparison = 0;
-
-    if (sortBy === 'name') {
-      const aName = aIsFolder ? a.folder.name : a.note.title;
-      const bName = bIsFolder ? b.folder.name : b.note.title;
-      comparison = aName.localeCompare(bName);
-    } else {
-      comparison = getTime(a) - getTime(b);
-    }
-
-    return sortOrder === 'asc' ? comparison : -comparison;
-  };
-}
-
-function sortFiles(files: FileItem[], sortBy: SortBy, sortOrder: SortOrder): FileItem[] {
-  const comparator = getComparator(sortBy, sortOrder);
-  const sortedFiles = [...files].toSorted(comparator);
-
-  return sortedFiles.map(item => {
-    if ('folder' in item) {
-      const folderSortBy = (item.folder.sortBy as SortBy) || sortBy;
-      const folderSortOrder = (item.folder.sortOrder as SortOrder) || sortOrder;
-
-      return {
-        folder: {
-          ...item.folder,
-          children: sortFiles(item.folder.children, folderSortBy, folderSortOrder)
-        }
-      };
-    }
-    return item;
-  });
-}
-
 export const useFileStore = create<FileStore>()((set, get) => ({
   files: [],
   filteredFiles: [],
@@ -144,11 +105,17 @@ export const useFileStore = create<FileStore>()((set, get) => ({
         return;
       }
 
-      const files = await getAllFiles();
-      const { searchQuery, sortBy, sortOrder } = get();
+      // 並び順はサーバー側で各フォルダの設定に従って適用済み
+      const [files, defaultSort] = await Promise.all([getAllFiles(), getDefaultSort()]);
+      const { searchQuery } = get();
       const filteredFiles = await filterFilesWithContent(files, searchQuery);
-      const sortedFiles = sortFiles(filteredFiles, sortBy, sortOrder);
-      set({ files, filteredFiles: sortedFiles, isLoading: false });
+      set({
+        files,
+        filteredFiles,
+        sortBy: defaultSort.sort_by,
+        sortOrder: defaultSort.sort_order,
+        isLoading: false
+      });
     } catch (error) {
       set({ isLoading: false, error: String(error) });
       throw error;
@@ -156,28 +123,30 @@ export const useFileStore = create<FileStore>()((set, get) => ({
   },
 
   setSearchQuery: async (query: string) => {
-    const { files, sortBy, sortOrder } = get();
+    const { files } = get();
     const filteredFiles = await filterFilesWithContent(files, query);
-    const sortedFiles = sortFiles(filteredFiles, sortBy, sortOrder);
 
-    set({ searchQuery: query, filteredFiles: sortedFiles });
+    set({ searchQuery: query, filteredFiles });
 
     if (query.trim()) {
       const folderIds = collectFolderIds(filteredFiles);
       useFolderStore.getState().openFolders(folderIds);
+      useFolderStore.getState().openSavedSearches(collectSavedSearchIds(filteredFiles));
     }
   },
 
-  setSortBy: sortBy => {
-    const { filteredFiles, sortOrder } = get();
-    const sortedFiles = sortFiles(filteredFiles, sortBy, sortOrder);
-    set({ sortBy, filteredFiles: sortedFiles });
+  setSortBy: async sortBy => {
+    const { sortOrder } = get();
+    await updateDefaultSort({ sort_by: sortBy, sort_order: sortOrder });
+    set({ sortBy });
+    await get().loadFiles();
   },
 
-  setSortOrder: sortOrder => {
-    const { filteredFiles, sortBy } = get();
-    const sortedFiles = sortFiles(filteredFiles, sortBy, sortOrder);
-    set({ sortOrder, filteredFiles: sortedFiles });
+  setSortOrder: async sortOrder => {
+    const { sortBy } = get();
+    await updateDefaultSort({ sort_by: sortBy, sort_order: sortOrder });
+    set({ sortOrder });
+    await get().loadFiles();
   },
 
   setSelectedTagId: async (tagId: number | null) => {
@@ -23,6 +23,10 @@ type FolderStore = {
   openFolderIds: number[];
   toggleFolder: (folderId: number) => void;
   openFolders: (folderIds: number[]) => void;
+  // 保存済み検索の仮想フォルダはフォルダとは別に開閉状態を持つ
+  openSavedSearchIds: number[];
+  toggleSavedSearch: (savedSearchId: number) => void;
+  openSavedSearches: (savedSearchIds: number[]) => void;
   expandAllFolders: () => void;
   collapseAllFolders: () => void;
 
@@ -37,7 +41,8 @@ type FolderStore = {
     icon?: string | null,
     color?: string | null,
     sortBy?: string | null,
-    sortOrder?: string | null
+    sortOrder?: string | null,
+    defaultTemplateId?: number | null
   ) => Promise<void>;
   deleteFolder: (id: number) => Promise<void>;
   moveFolder: (id: number, newParentId: number | null) => Promise<void>;
@@ -60,13 +65,25 @@ export const useFolderStore = create<FolderStore>()((set, get) => ({
     set(state => ({
       openFolderIds: [...new Set([...state.openFolderIds, ...folderIds])]
     })),
+  openSavedSearchIds: [],
+  toggleSavedSearch: (savedSearchId: number) =>
+    set(state => ({
+      openSavedSearchIds: state.openSavedSearchIds.includes(savedSearchId)
+        ? state.openSavedSearchIds.filter(id => id !== savedSearchId)
+        : [...state.openSavedSearchIds, savedSearchId]
+    })),
+  openSavedSearches: (savedSearchIds: number[]) =>
+    set(state => ({
+      openSavedSearchIds: [...new Set([...state.openSavedSearchIds, ...savedSearchIds])]
+    })),
   expandAllFolders: () =>
     set(state => ({
       openFolderIds: state.folders.map(folder => folder.id)
     })),
   collapseAllFolders: () =>
     set({
-      openFolderIds: []
+      openFolderIds: [],
+      openSavedSearchIds: []
     }),
 
   createFolder: async (name: string, parentPath = '', parentId?: number | null) => {
@@ -182,7 +199,8 @@ export const useFolderStore = create<FolderStore>()((set, get) => ({
     icon?: string | null,
     color?: string | null,
     sortBy?: string | null,
-    sortOrder?: string | null
+    sortOrder?: string | null,
+    defaultTemplateId?: number | null
   ) => {
     set({
       isLoading: true,
@@ -197,7 +215,8 @@ export const useFolderStore = create<FolderStore>()((set, get) => ({
         icon,
         color,
         sortBy,
-        sortOrder
+        sortOrder,
+        defaultTemplateId
       );
       const updatedFolderWithChildren: FolderWithChildren = {
         id: updatedFolder.id,
@@ -210,6 +229,7 @@ export const useFolderStore = create<FolderStore>()((set, get) => ({
         color: updatedFolder.color ?? undefined,
         sortBy: updatedFolder.sortBy,
         sortOrder: updatedFolder.sortOrder,
+        defaultTemplateId: updatedFolder.default_template_id ?? null,
         children: []
       };
       set(state => ({
@@ -0,0 +1,56 @@
+// data_dir/assets に内容のハッシュの名前で保存したファイル
+export type Asset = {
+  id: number;
+  hash: string;
+  file_name: string;
+  mime_type: string;
+  size: number;
+  width: number | null;
+  height: number | null;
+  created_at: string;
+  is_deleted: boolean;
+  // 添付したときのファイル名 (貼り付けた画像などは null)
+  original_name: string | null;
+  // ノートから参照するリンク (assets/<hash>.png)
+  link: string;
+};
+
+// 添付したファイルと、ノートに挿入する Markdown のリンク
+export type Attachment = Asset & {
+  markdown: string;
+};
+
+// 貼り付けた PNG を再エンコードするときの形式 (webp はロスレス)
+export type ImageEncoding = 'webp' | 'jpeg';
+
+// サイズはバイト単位、画像の大きさはピクセル単位
+export type AssetSettings = {
+  max_file_size: number;
+  // 貼り付けた画像の縮小と再エンコードを行うか
+  process_images: boolean;
+  max_image_dimension: number;
+  // これより大きい PNG は reencode_format で保存し直す
+  reencode_threshold: number;
+  reencode_format: ImageEncoding;
+  jpeg_quality: number;
+  strip_exif: boolean;
+  thumbnail_size: number;
+};
+
+// どのノートからも参照されていないアセット (asset_id が null のものは assets テーブルにない古いファイル)
+export type UnusedAsset = {
+  file_name: string;
+  link: string;
+  size: number;
+  asset_id: number | null;
+  is_deleted: boolean;
+};
+
+export type AssetCleanupAction = 'trash' | 'delete';
+
+export type AssetCleanupReport = {
+  assets: UnusedAsset[];
+  total_size: number;
+  dry_run: boolean;
+  restored: number;
+};
@@ -0,0 +1,17 @@
+// pattern は folder からのパスで、YYYY・MM・DD を日付に置き換える
+export type DailyNoteSettings = {
+  folder: string;
+  pattern: string;
+  template_id: number | null;
+};
+
+export type DailyNote = {
+  // YYYY-MM-DD
+  date: string;
+  note_id: number;
+};
+
+export type DailyNoteNeighbors = {
+  previous: DailyNote | null;
+  next: DailyNote | null;
+};
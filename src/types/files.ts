@@ -1,5 +1,13 @@
 import { type Note } from './notes';
 
+export type SortBy = 'name' | 'createdAt' | 'updatedAt' | 'manual';
+export type SortOrder = 'asc' | 'desc';
+
+export type SortSettings = {
+  sort_by: SortBy;
+  sort_order: SortOrder;
+};
+
 export type FolderWithChildren = {
   id: number;
   name: string;
@@ -12,6 +20,31 @@ export type FolderWithChildren = {
   color?: string;
   sortBy?: string | null;
   sortOrder?: string | null;
+  defaultTemplateId?: number | null;
+};
+
+// 保存済み検索 (ツリーでは仮想フォルダとして表示する)
+export type SavedSearchFolder = {
+  id: number;
+  name: string;
+  query: string;
+  icon: string | null;
+  sort: 'relevance' | 'updated' | 'created';
+  children: FileItem[];
 };
 
-export type FileItem = { folder: FolderWithChildren } | { note: Note };
+export type FileItem =
+  | { folder: FolderWithChildren }
+  | { note: Note }
+  | { savedSearch: SavedSearchFolder };
+
+// 外部のエディタや git による変更を反映した結果 (files-changed イベントのペイロード)
+export type FilesChanged = {
+  created_notes: number[];
+  modified_notes: number[];
+  renamed_notes: number[];
+  deleted_notes: number[];
+  created_folders: number[];
+  renamed_folders: number[];
+  deleted_folders: number[];
+};
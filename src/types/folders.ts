@@ -11,4 +11,6 @@ export type Folder = {
   color?: string | null;
   sortBy?: string | null;
   sortOrder?: string | null;
+  // 既定のテンプレート (未設定なら親フォルダのものを使う)
+  default_template_id?: number | null;
 };
@@ -15,10 +15,12 @@ export type UpdateHotkeyInput = {
 
 export const HOTKEY_ACTIONS = {
   QUICK_NOTE: 'quick_note',
-  TOGGLE_WINDOW: 'toggle_window'
+  TOGGLE_WINDOW: 'toggle_window',
+  DAILY_NOTE: 'daily_note'
 } as const;
 
 export const HOTKEY_ACTION_LABELS: Record<string, string> = {
   [HOTKEY_ACTIONS.QUICK_NOTE]: 'クイックノート作成',
-  [HOTKEY_ACTIONS.TOGGLE_WINDOW]: 'ウィンドウの表示/非表示'
+  [HOTKEY_ACTIONS.TOGGLE_WINDOW]: 'ウィンドウの表示/非表示',
+  [HOTKEY_ACTIONS.DAILY_NOTE]: '今日のデイリーノートを開く'
 };
@@ -8,10 +8,26 @@ export type Note = {
   preview: string;
   isDeleted?: boolean;
   deletedAt?: string | null;
-  isFavorite?: boolean;
-  favoriteOrder?: number | null;
+  is_favorite?: boolean;
+  favorite_order?: number | null;
 };
 
 export type NoteWithContent = Note & {
   content: string;
+  properties?: NoteProperties;
+};
+
+// ノートの先頭の YAML front matter に書かれたプロパティ
+export type NoteProperties = {
+  title: string | null;
+  tags: string[];
+  aliases: string[];
+  created: string | null;
+  // それ以外のキーと値 (書かれた順)
+  custom: NoteProperty[];
+};
+
+export type NoteProperty = {
+  key: string;
+  value: unknown;
 };
@@ -2,10 +2,24 @@ export type Tag = {
   id: number;
   name: string;
   color: string | null;
+  // 親のタグ (名前は "親/子" のように / で区切る)
+  parent_id?: number | null;
   createdAt: string;
   updatedAt: string;
 };
 
+// ノートに付いたタグ (手動で付けたものか、本文の #タグ から付いたものか)
+export type NoteTag = Tag & {
+  is_manual: boolean;
+  is_inline: boolean;
+};
+
+// 使用状況付きのタグ (last_used_at はタグを付けたノートが最後に更新された日時)
+export type TagWithStats = Tag & {
+  note_count: number;
+  last_used_at: string | null;
+};
+
 export type CreateTagInput = {
   name: string;
   color?: string | null;
@@ -15,4 +29,5 @@ export type UpdateTagInput = {
   id: number;
   name: string;
   color?: string | null;
+  rewrite_content?: boolean;
 };
@@ -19,3 +19,26 @@ export type UpdateTemplateInput = {
   content: string;
   description?: string | null;
 };
+
+// テンプレートの変数に入れる値 (date は YYYY-MM-DD、省略すると今日)
+export type TemplateContext = {
+  title?: string | null;
+  folder?: string | null;
+  date?: string | null;
+  // {{prompt:名前}} に入れる値
+  prompts?: Record<string, string>;
+};
+
+export type RenderedTemplate = {
+  content: string;
+  // {{cursor}} の位置
+  cursor: number | null;
+};
+
+export type CreateNoteFromTemplateInput = {
+  template_id: number;
+  title: string;
+  parent_id: number | null;
+  folder_path?: string | null;
+  prompts?: Record<string, string>;
+};